@@ -13,6 +13,17 @@ mod switch;
 /// At its simplest, the route matcher string will create your variant/struct if it exactly matches the browser's route.
 /// If the route in the url bar is `http://yoursite.com/some/route` and your route matcher string
 /// for an enum variant is `/some/route`, then that variant will be created when `switch()` is called with the route.
+/// A route matcher string with a syntax error is a compile error pointing at the string,
+/// which explains what was expected where.
+/// ```compile_fail
+/// use yew_router::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/user/{id"]
+///     User { id: u32 }, // Error: the capture isn't closed with a `}`.
+/// }
+/// ```
 ///
 /// But the route matcher has other capabilities.
 /// If you want to capture data from the route matcher string, for example, extract an id or user name from the route,
@@ -25,12 +36,72 @@ mod switch;
 /// There are also `{*:field_name}` and `{3:field_name}` types of capture sections that will capture
 /// _everything_, and the next 3 path sections respectively.
 /// `{1:field_name}` is the same as `{field_name}`.
+/// A `{*:field_name}` capture followed by a literal stops at the first occurrence of the literal,
+/// unless the rest of the route only matches after a later one,
+/// so `#[to = "/docs/{*:path}/edit!"]` captures `a/edit/b` from `/docs/a/edit/b/edit`.
+///
+/// A section can hold several captures separated by literals, like `/files/{stem}.{ext}`.
+/// Captures never extend past their section, and each takes the longest value that still lets
+/// the rest of the route match, so `/files/archive.tar.gz` captures `archive.tar` and `gz`.
+///
+/// Fields that are converted to and from strings, like `String` and numbers, are percent-decoded
+/// when they are captured, and percent-encoded when a route is built from the variant/struct,
+/// so any string can make a round trip through a route.
+/// `{field_name}` captures escape `/` when writing, while `{*:field_name}` and `{3:field_name}`
+/// captures keep it.
+/// Fields that are `Switch` types themselves are handed the captured section as it is,
+/// and write their routes unescaped.
+///
+/// Captures can be constrained by following the field name with a `:` and either a primitive type
+/// name, or a regular expression that must match the whole section: `{id:u32}`,
+/// `{slug:[a-z0-9-]+}` or `{date:\d{4}-\d{2}-\d{2}}`.
+/// If the captured section doesn't satisfy the constraint, the next variant will be tried instead.
+/// The derive checks that any value allowed by a type constraint can be parsed as the field's type,
+/// and that regular expressions are valid.
+/// ```compile_fail
+/// use yew_router::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/user/{id:u32}"]
+///     User { id: u8 }, // Error: 300 satisfies `u32` but doesn't fit in a `u8`.
+/// }
+/// ```
+///
+/// The special characters `!`, `{`, `}`, `&` and `=` can be matched literally by doubling them,
+/// so `#[to = "/wow!!/{{id}}"]` matches and writes the route `/wow!/{id}`.
 ///
 /// Tuple-structs and Tuple-enum-variants are also supported.
 /// If you don't want to specify keys that don't correspond to any specific field,
 /// `{}`, `{*}`, and `{4}` also denote valid capture sections when used on structs and variants without named fields.
 /// In datastructures without field names, the captures will be assigned in order - left to right.
 ///
+/// Parts of the path can be made optional by wrapping them in `[]`.
+/// Fields captured within an optional section must be `Option`s, which are `None` if the section was absent.
+/// For example, `#[to = "/posts[/page/{n}]"]` will create `Posts { n: Option<usize> }` for both
+/// "/posts" and "/posts/page/3".
+/// When building a route, an optional section is only written if the fields captured within it are all `Some`.
+/// For datastructures without field names, captures can't follow an optional section that contains captures,
+/// because the fields after it couldn't be told apart from the fields within it.
+///
+/// Query parameters are matched by their keys, so `#[to = "/search?q={query}&page={page}"]` will match
+/// both "/search?q=lorem&page=2" and "/search?page=2&q=lorem".
+/// Keys are compared once they are percent-decoded, and a `+` in a key or value is a space,
+/// as in form-encoded queries.
+/// Parameters that aren't in the matcher string are ignored, unless the query is followed by a `!`.
+/// A parameter captured into an `Option` field may be left out of the query,
+/// and it is only written when building a route if the field is `Some`.
+/// Every occurrence of a parameter can be captured into a collection like `Vec<T>` with `{*:name}`,
+/// eg. `#[to = "/posts?tag={*:tags}"]` for `Posts { tags: Vec<String> }`.
+/// The whole query section can be captured into a field with `{?name}`,
+/// eg. `#[to = "/search{?query}"]` for `Search { query: SearchParams }`,
+/// where `SearchParams` implements serde's `Deserialize` and `Serialize`.
+/// Alternatively, marking a field with `#[query]` captures the parameters that aren't otherwise part
+/// of the matcher string into it, so `#[query] params: HashMap<String, String>` collects the rest.
+/// These are written sorted by key when building a route, leaving out any whose key is also a
+/// parameter in the matcher string.
+/// Repeated parameters and query fields are only supported for datastructures with field names.
+///
 /// # Note
 /// It should be mentioned that the derived function for matching will try enum variants in order,
 /// from top to bottom, and that the whole route doesn't need to be matched by the route
@@ -42,6 +113,34 @@ mod switch;
 /// any characters are left after matching the route matcher string, the match should fail.
 /// This means that `[to = "/!"]` will match "/" and _only_ "/".
 ///
+/// Literals are matched ignoring case, and the route must have a trailing slash where the route
+/// matcher string does.
+/// This can be changed for a struct, an enum, or an enum variant with the `#[switch(...)]` attribute,
+/// where the settings of a variant override those of its enum:
+/// * `#[switch(case_sensitive)]` matches literals case sensitively,
+///   and `#[switch(case_insensitive)]` goes back to ignoring case.
+/// * `#[switch(trailing_slash = "ignore")]` matches routes with or without a trailing slash,
+///   so `/about` and `/about/` match the same routes.
+///   `"require"` only matches routes whose path ends with a slash, and `"strict"` is the default.
+///   `"redirect"` matches like `"ignore"`, but a `Router` replaces the route with the one built from
+///   the switched item, so the address has the trailing slash of the route matcher string.
+///
+/// A variant that can never be matched, because a variant before it matches every route it would,
+/// is an error.
+/// This is detected when the earlier variant's route matcher string is made up of literals,
+/// optionally followed by a single capture into a `String`.
+/// ```compile_fail
+/// use yew_router::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/"]
+///     Home,
+///     #[to = "/about"]
+///     About, // Error: "/about" is matched by `Home`, which should be `#[to = "/!"]`.
+/// }
+/// ```
+///
 /// -----
 /// There are other attributes as well.
 /// `#[rest]`, `#[rest="field_name"]` and `#[end]` attributes exist as well.
@@ -77,7 +176,7 @@ mod switch;
 /// }
 /// ```
 /// Check out the examples directory in the repository to see some more usages of the routing syntax.
-#[proc_macro_derive(Switch, attributes(to, rest, end))]
+#[proc_macro_derive(Switch, attributes(to, rest, end, query, switch))]
 pub fn switch(tokens: TokenStream) -> TokenStream {
     let input: DeriveInput = parse_macro_input!(tokens as DeriveInput);
 
@@ -1,16 +1,24 @@
-use crate::switch::shadow::{ShadowCaptureVariant, ShadowMatcherToken};
+use crate::switch::shadow::{
+    ShadowCaptureVariant, ShadowMatcherToken, ShadowQueryParameter, ShadowQueryValue,
+};
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
-use syn::{Data, DeriveInput, Fields, Ident, Variant};
+use syn::{Data, DeriveInput, Field, Fields, GenericArgument, Ident, PathArguments, Type, Variant};
 
 mod attribute;
+mod constraint;
 mod enum_impl;
+mod explain;
+mod optional;
+mod query;
+mod reachability;
+mod settings;
 mod shadow;
 mod struct_impl;
 mod switch_impl;
 
-use self::{attribute::AttrToken, switch_impl::SwitchImpl};
-use crate::switch::{enum_impl::EnumInner, struct_impl::StructInner};
+use self::{attribute::AttrToken, settings::Settings, switch_impl::SwitchImpl};
+use crate::switch::{enum_impl::EnumInner, explain::ExplainRoute, struct_impl::StructInner};
 use yew_router_route_parser::FieldNamingScheme;
 
 /// Holds data that is required to derive Switch for a struct or a single enum variant.
@@ -18,6 +26,138 @@ pub struct SwitchItem {
     pub matcher: Vec<ShadowMatcherToken>,
     pub ident: Ident,
     pub fields: Fields,
+    pub settings: Settings,
+}
+
+impl SwitchItem {
+    /// Gets every capture in the matcher in the order they appear,
+    /// along with whether they are within an optional section, or are optional query parameters.
+    pub fn captures(&self) -> Vec<(&ShadowCaptureVariant, bool)> {
+        fn captures_impl<'a>(
+            tokens: &'a [ShadowMatcherToken],
+            within_optional: bool,
+            acc: &mut Vec<(&'a ShadowCaptureVariant, bool)>,
+        ) {
+            for token in tokens {
+                match token {
+                    ShadowMatcherToken::Capture(capture) => acc.push((capture, within_optional)),
+                    ShadowMatcherToken::Optional(tokens) => captures_impl(tokens, true, acc),
+                    ShadowMatcherToken::Query { parameters, .. } => {
+                        for parameter in parameters {
+                            if let ShadowQueryValue::Capture(capture) = &parameter.value {
+                                acc.push((capture, within_optional || parameter.optional))
+                            }
+                        }
+                    }
+                    ShadowMatcherToken::Exact(_) | ShadowMatcherToken::End => {}
+                }
+            }
+        }
+        let mut acc = vec![];
+        captures_impl(&self.matcher, false, &mut acc);
+        acc
+    }
+
+    /// Determines if the field at the given index is captured within an optional section.
+    pub fn is_optional_field(&self, index: usize, field: &Field) -> bool {
+        match &field.ident {
+            Some(ident) => self
+                .captures()
+                .into_iter()
+                .any(|(capture, within_optional)| {
+                    within_optional && capture_name(capture).map_or(false, |name| ident == name)
+                }),
+            None => self
+                .captures()
+                .get(index)
+                .map_or(false, |(_, within_optional)| *within_optional),
+        }
+    }
+
+    /// Determines if the whole query section is captured into the field,
+    /// either with `{?name}`, or by marking the field with `#[query]`.
+    pub fn is_query_field(&self, field: &Field) -> bool {
+        let ident = match &field.ident {
+            Some(ident) => ident,
+            None => return false,
+        };
+        self.matcher.iter().any(|token| match token {
+            ShadowMatcherToken::Query {
+                rest: Some(rest), ..
+            } => ident == rest,
+            _ => false,
+        })
+    }
+
+    /// Determines if the field captures every occurrence of a query parameter with `{*:name}`.
+    pub fn is_repeated_field(&self, field: &Field) -> bool {
+        let ident = match &field.ident {
+            Some(ident) => ident,
+            None => return false,
+        };
+        self.matcher.iter().any(|token| match token {
+            ShadowMatcherToken::Query { parameters, .. } => {
+                parameters.iter().any(|parameter| match &parameter.value {
+                    ShadowQueryValue::Capture(ShadowCaptureVariant::ManyNamed(name)) => {
+                        ident == name
+                    }
+                    _ => false,
+                })
+            }
+            _ => false,
+        })
+    }
+
+    /// Gets the `T` of a field of type `Option<T>` that is captured within an optional section.
+    pub fn optional_field_type<'a>(&self, index: usize, field: &'a Field) -> Option<&'a Type> {
+        if self.is_optional_field(index, field) {
+            option_inner_type(&field.ty)
+        } else {
+            None
+        }
+    }
+}
+
+/// Gets the name of the field a capture is stored in, if it has one.
+fn capture_name(capture: &ShadowCaptureVariant) -> Option<&str> {
+    match capture {
+        ShadowCaptureVariant::Named { name, .. }
+        | ShadowCaptureVariant::ManyNamed(name)
+        | ShadowCaptureVariant::NumberedNamed { name, .. } => Some(name),
+        ShadowCaptureVariant::Unnamed
+        | ShadowCaptureVariant::ManyUnnamed
+        | ShadowCaptureVariant::NumberedUnnamed { .. } => None,
+    }
+}
+
+/// Gets the `T` from `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    match single_type_argument(ty)? {
+        (ident, ty) if ident == "Option" => Some(ty),
+        _ => None,
+    }
+}
+
+/// Gets the `T` from collections like `Vec<T>`.
+pub(crate) fn collection_item_type(ty: &Type) -> Option<&Type> {
+    single_type_argument(ty).map(|(_, ty)| ty)
+}
+
+/// Gets the name of a type, along with its type argument, if it has exactly one.
+fn single_type_argument(ty: &Type) -> Option<(&Ident, &Type)> {
+    let segment = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first()? {
+                GenericArgument::Type(ty) => Some((&segment.ident, ty)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
 }
 
 pub fn switch_impl(input: DeriveInput) -> syn::Result<TokenStream> {
@@ -31,18 +171,26 @@ pub fn switch_impl(input: DeriveInput) -> syn::Result<TokenStream> {
                 Fields::Unit => FieldNamingScheme::Unit,
                 Fields::Named(_) => FieldNamingScheme::Named,
             };
+            let settings = Settings::default().with_attributes(&input.attrs)?;
             let matcher = AttrToken::convert_attributes_to_tokens(input.attrs)?
                 .into_iter()
                 .enumerate()
                 .map(|(index, at)| at.into_shadow_matcher_tokens(index, field_naming_scheme))
+                .collect::<syn::Result<Vec<_>>>()?
+                .into_iter()
                 .flatten()
                 .collect::<Vec<_>>();
 
-            let item = SwitchItem {
+            let mut item = SwitchItem {
                 matcher,
                 ident: ident.clone(), // TODO make SwitchItem take references instead.
                 fields: ds.fields,
+                settings,
             };
+            optional::mark_optional_query_parameters(&mut item);
+            query::prepare_query_fields(&mut item)?;
+            constraint::check_constraints(&item)?;
+            optional::check_optional_sections(&item)?;
 
             SwitchImpl {
                 target_ident: &ident,
@@ -53,11 +201,19 @@ pub fn switch_impl(input: DeriveInput) -> syn::Result<TokenStream> {
                         switch_item: &item,
                         item: &Ident::new("self", Span::call_site()),
                     },
+                    redirect_trailing_slash: RedirectTrailingSlash {
+                        switch_items: std::slice::from_ref(&item),
+                        enum_ident: None,
+                    },
+                    explain_route: ExplainRoute {
+                        switch_items: std::slice::from_ref(&item),
+                    },
                 },
             }
             .to_token_stream()
         }
         Data::Enum(de) => {
+            let enum_settings = Settings::default().with_attributes(&input.attrs)?;
             let switch_variants = de
                 .variants
                 .into_iter()
@@ -67,19 +223,29 @@ pub fn switch_impl(input: DeriveInput) -> syn::Result<TokenStream> {
                         Fields::Unit => FieldNamingScheme::Unit,
                         Fields::Named(_) => yew_router_route_parser::FieldNamingScheme::Named,
                     };
+                    let settings = enum_settings.with_attributes(&variant.attrs)?;
                     let matcher = AttrToken::convert_attributes_to_tokens(variant.attrs)?
                         .into_iter()
                         .enumerate()
                         .map(|(index, at)| at.into_shadow_matcher_tokens(index, field_type))
+                        .collect::<syn::Result<Vec<_>>>()?
+                        .into_iter()
                         .flatten()
                         .collect::<Vec<_>>();
-                    Ok(SwitchItem {
+                    let mut item = SwitchItem {
                         matcher,
                         ident: variant.ident,
                         fields: variant.fields,
-                    })
+                        settings,
+                    };
+                    optional::mark_optional_query_parameters(&mut item);
+                    query::prepare_query_fields(&mut item)?;
+                    constraint::check_constraints(&item)?;
+                    optional::check_optional_sections(&item)?;
+                    Ok(item)
                 })
                 .collect::<syn::Result<Vec<_>>>()?;
+            reachability::check_unreachable_variants(&switch_variants)?;
 
 
             SwitchImpl {
@@ -95,6 +261,13 @@ pub fn switch_impl(input: DeriveInput) -> syn::Result<TokenStream> {
                         enum_ident: &ident,
                         match_item: &Ident::new("self", Span::call_site()),
                     },
+                    redirect_trailing_slash: RedirectTrailingSlash {
+                        switch_items: &switch_variants,
+                        enum_ident: Some(&ident),
+                    },
+                    explain_route: ExplainRoute {
+                        switch_items: &switch_variants,
+                    },
                 },
             }
             .to_token_stream()
@@ -119,15 +292,59 @@ impl<T> Flatten<T> for Option<Option<T>> {
     }
 }
 
-fn build_matcher_from_tokens(tokens: &[ShadowMatcherToken]) -> TokenStream {
+/// Creates an expression that builds the matcher.
+///
+/// The matcher is meant to be stored in a `thread_local!`,
+/// so that it is only built the first time it is used on a thread.
+pub(crate) fn build_matcher_from_tokens(item: &SwitchItem) -> TokenStream {
+    let SwitchItem {
+        matcher: tokens,
+        settings,
+        ..
+    } = item;
     quote! {
-        let settings = ::yew_router::matcher::MatcherSettings {
-            case_insensitive: true,
-        };
-        let matcher = ::yew_router::matcher::RouteMatcher {
+        ::yew_router::matcher::RouteMatcher {
             tokens: ::std::vec![#(#tokens),*],
-            settings
+            settings: #settings,
+        }
+    }
+}
+
+/// Implements `Switch::redirect_trailing_slash` if any of the items redirect trailing slashes.
+///
+/// For enums, `enum_ident` is used to match each variant, otherwise there is a single item.
+pub struct RedirectTrailingSlash<'a> {
+    pub switch_items: &'a [SwitchItem],
+    pub enum_ident: Option<&'a Ident>,
+}
+
+impl<'a> ToTokens for RedirectTrailingSlash<'a> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let redirects =
+            |item: &SwitchItem| item.settings.trailing_slash == settings::TrailingSlash::Redirect;
+        if !self.switch_items.iter().any(redirects) {
+            return;
+        }
+        let body = match self.enum_ident {
+            Some(enum_ident) => {
+                let arms = self.switch_items.iter().map(|item| {
+                    let variant_ident = &item.ident;
+                    let redirect = redirects(item);
+                    quote! {#enum_ident::#variant_ident { .. } => #redirect}
+                });
+                quote! {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+            None => quote! {true},
         };
+        tokens.extend(quote! {
+            fn redirect_trailing_slash(&self) -> bool {
+                #body
+            }
+        });
     }
 }
 
@@ -138,6 +355,227 @@ pub(crate) enum FieldType {
     Unit,
 }
 
+/// Creates the writers for a sequence of tokens.
+///
+/// Unnamed fields are numbered in the order that their captures appear, starting at `next_index`.
+///
+/// This assumes that the variant/struct has been destructured.
+fn write_for_tokens(
+    tokens: &[ShadowMatcherToken],
+    fields: &Fields,
+    settings: Settings,
+    next_index: &mut usize,
+) -> TokenStream {
+    let mut writers = TokenStream::new();
+    for token in tokens {
+        let writer = match token {
+            ShadowMatcherToken::Capture(_) => {
+                write_for_token(token, field_type(fields, next_index))
+            }
+            ShadowMatcherToken::Optional(tokens) => {
+                write_for_optional(tokens, fields, settings, next_index)
+            }
+            ShadowMatcherToken::Query {
+                parameters, rest, ..
+            } => write_for_query(parameters, rest.as_ref(), fields, settings, next_index),
+            ShadowMatcherToken::Exact(_) | ShadowMatcherToken::End => {
+                write_for_token(token, FieldType::Unit)
+            }
+        };
+        writers.extend(writer);
+    }
+    writers
+}
+
+/// Gets the kind of writer needed for the next capture.
+fn field_type(fields: &Fields, next_index: &mut usize) -> FieldType {
+    match fields {
+        Fields::Named(_) => FieldType::Named,
+        Fields::Unnamed(_) => {
+            let index = *next_index;
+            *next_index += 1;
+            FieldType::Unnamed { index }
+        }
+        Fields::Unit => FieldType::Unit,
+    }
+}
+
+/// Writes an optional section if all of the fields captured directly within it are `Some`.
+///
+/// Sections that only contain other optional sections are written if any field within them is
+/// `Some`, and sections without any captures are never written.
+fn write_for_optional(
+    tokens: &[ShadowMatcherToken],
+    fields: &Fields,
+    settings: Settings,
+    next_index: &mut usize,
+) -> TokenStream {
+    let mut index = *next_index;
+    let mut direct = vec![];
+    let mut nested = vec![];
+    for token in tokens {
+        match token {
+            ShadowMatcherToken::Capture(capture) => {
+                direct.push(capture_ident(capture, field_type(fields, &mut index)))
+            }
+            ShadowMatcherToken::Optional(tokens) => {
+                nested.extend(capture_idents(tokens, fields, &mut index))
+            }
+            // The parser doesn't allow a query within an optional section.
+            ShadowMatcherToken::Query { .. }
+            | ShadowMatcherToken::Exact(_)
+            | ShadowMatcherToken::End => {}
+        }
+    }
+
+    let writers = write_for_tokens(tokens, fields, settings, next_index);
+    if !direct.is_empty() {
+        quote! {
+            if let (#(::std::option::Option::Some(#direct),)*) = (#(#direct,)*) {
+                #writers
+            }
+        }
+    } else if !nested.is_empty() {
+        quote! {
+            if #(#nested.is_some())||* {
+                #writers
+            }
+        }
+    } else {
+        quote! {}
+    }
+}
+
+/// Gets the idents of every field captured within the tokens.
+fn capture_idents(
+    tokens: &[ShadowMatcherToken],
+    fields: &Fields,
+    next_index: &mut usize,
+) -> Vec<Ident> {
+    let mut idents = vec![];
+    for token in tokens {
+        match token {
+            ShadowMatcherToken::Capture(capture) => {
+                idents.push(capture_ident(capture, field_type(fields, next_index)))
+            }
+            ShadowMatcherToken::Optional(tokens) => {
+                idents.extend(capture_idents(tokens, fields, next_index))
+            }
+            ShadowMatcherToken::Query { .. }
+            | ShadowMatcherToken::Exact(_)
+            | ShadowMatcherToken::End => {}
+        }
+    }
+    idents
+}
+
+/// Writes the query section.
+///
+/// Optional parameters are only written if their fields are `Some`,
+/// repeated parameters are written once for every item of their fields,
+/// and the `?` is only written if there are any parameters.
+/// The query field is written last, sorted by key, leaving out the keys of the parameters in the
+/// matcher string, because they couldn't be told apart from those parameters.
+fn write_for_query(
+    parameters: &[ShadowQueryParameter],
+    rest: Option<&String>,
+    fields: &Fields,
+    settings: Settings,
+    next_index: &mut usize,
+) -> TokenStream {
+    let mut writers: Vec<TokenStream> = parameters
+        .iter()
+        .map(|parameter| match &parameter.value {
+            ShadowQueryValue::Exact(value) => {
+                let pair = format!("&{}={}", parameter.key, value);
+                quote! {
+                    write!(buf, "{}", #pair).unwrap();
+                }
+            }
+            ShadowQueryValue::Capture(capture) => {
+                let naming_scheme = field_type(fields, next_index);
+                let name = capture_ident(capture, naming_scheme);
+                let key = format!("&{}=", parameter.key);
+                let writer = write_for_capture(capture, &name);
+                if let ShadowCaptureVariant::ManyNamed(_) | ShadowCaptureVariant::ManyUnnamed =
+                    capture
+                {
+                    quote! {
+                        for #name in #name {
+                            write!(buf, "{}", #key).unwrap();
+                            #writer
+                        }
+                    }
+                } else if parameter.optional {
+                    quote! {
+                        if let ::std::option::Option::Some(#name) = #name {
+                            write!(buf, "{}", #key).unwrap();
+                            #writer
+                        }
+                    }
+                } else {
+                    quote! {
+                        write!(buf, "{}", #key).unwrap();
+                        #writer
+                    }
+                }
+            }
+        })
+        .collect();
+    if let Some(rest) = rest {
+        let name = Ident::new(rest, Span::call_site());
+        let keys = parameters.iter().map(|parameter| &parameter.key);
+        let skip_parameter_keys = if parameters.is_empty() {
+            quote! {}
+        } else if settings.case_sensitive {
+            quote! {
+                if [#(#keys),*].contains(&key.as_str()) {
+                    continue;
+                }
+            }
+        } else {
+            quote! {
+                if [#(#keys),*].iter().any(|parameter_key: &&str| parameter_key.eq_ignore_ascii_case(&key)) {
+                    continue;
+                }
+            }
+        };
+        writers.push(quote! {
+            let __rest = ::yew_router::matcher::urlencoded::to_pairs(&#name)
+                .expect("The query field could not be written as query parameters");
+            for (key, value) in __rest {
+                #skip_parameter_keys
+                buf.push('&');
+                ::yew_router::matcher::encoding::encode_into(buf, &key, ::yew_router::matcher::encoding::Encoding::Segment);
+                buf.push('=');
+                ::yew_router::matcher::encoding::encode_into(buf, &value, ::yew_router::matcher::encoding::Encoding::Segment);
+            }
+        });
+    }
+    quote! {
+        let mut __query = ::std::string::String::new();
+        {
+            let buf = &mut __query;
+            #(#writers)*
+        }
+        if !__query.is_empty() {
+            buf.push('?');
+            buf.push_str(&__query[1..]);
+        }
+    }
+}
+
+/// Gets the ident that the field a capture is stored in has been destructured to.
+fn capture_ident(capture: &ShadowCaptureVariant, naming_scheme: FieldType) -> Ident {
+    match naming_scheme {
+        FieldType::Named | FieldType::Unit => match capture_name(capture) {
+            Some(name) => Ident::new(name, Span::call_site()),
+            None => panic!("Unnamed matcher sections not allowed for named field types"),
+        },
+        FieldType::Unnamed { index } => unnamed_field_index_item(index),
+    }
+}
+
 /// This assumes that the variant/struct has been destructured.
 fn write_for_token(token: &ShadowMatcherToken, naming_scheme: FieldType) -> TokenStream {
     match token {
@@ -146,33 +584,141 @@ fn write_for_token(token: &ShadowMatcherToken, naming_scheme: FieldType) -> Toke
                 write!(buf, "{}", #lit).unwrap();
             }
         }
-        ShadowMatcherToken::Capture(capture) => match naming_scheme {
-            FieldType::Named | FieldType::Unit => match &capture {
-                ShadowCaptureVariant::Named(name)
-                | ShadowCaptureVariant::ManyNamed(name)
-                | ShadowCaptureVariant::NumberedNamed { name, .. } => {
-                    let name = Ident::new(&name, Span::call_site());
-                    quote! {
-                        state = state.or_else(|| #name.build_route_section(buf));
-                    }
+        ShadowMatcherToken::Capture(capture) => {
+            write_for_capture(capture, &capture_ident(capture, naming_scheme))
+        }
+        ShadowMatcherToken::End => quote! {},
+        ShadowMatcherToken::Optional(_) => {
+            panic!("Optional sections must be written with write_for_optional")
+        }
+        ShadowMatcherToken::Query { .. } => {
+            panic!("Query sections must be written with write_for_query")
+        }
+    }
+}
+
+/// Writes the field that has been destructured to `name`.
+///
+/// Fields percent-encode themselves if they are converted to and from strings,
+/// while nested `Switch` types write their routes as they are.
+fn write_for_capture(capture: &ShadowCaptureVariant, name: &Ident) -> TokenStream {
+    let encoding = capture_encoding(capture);
+    quote! {
+        state = state.or_else(|| #name.build_route_capture(buf, #encoding));
+    }
+}
+
+/// Selects how a field written for a capture percent-encodes itself.
+///
+/// Captures that span multiple path sections keep their `/`s, the rest escape them.
+fn capture_encoding(capture: &ShadowCaptureVariant) -> TokenStream {
+    match capture {
+        ShadowCaptureVariant::Unnamed | ShadowCaptureVariant::Named { .. } => {
+            quote! {::yew_router::matcher::encoding::Encoding::Segment}
+        }
+        ShadowCaptureVariant::ManyUnnamed
+        | ShadowCaptureVariant::ManyNamed(_)
+        | ShadowCaptureVariant::NumberedUnnamed { .. }
+        | ShadowCaptureVariant::NumberedNamed { .. } => {
+            quote! {::yew_router::matcher::encoding::Encoding::Sections}
+        }
+    }
+}
+
+
+/// Declares a field that must be captured.
+///
+/// `value` evaluates to the captured string, or `None` if nothing was captured for the field.
+pub(crate) fn field_declaration(
+    field_name: &Ident,
+    field_ty: &Type,
+    value: TokenStream,
+) -> TokenStream {
+    quote! {
+        let #field_name = {
+            let (v, s) = match #value {
+                ::std::option::Option::Some(value) => {
+                    <#field_ty as ::yew_router::Switch>::from_route_capture(value, state)
                 }
-                ShadowCaptureVariant::Unnamed
-                | ShadowCaptureVariant::ManyUnnamed
-                | ShadowCaptureVariant::NumberedUnnamed { .. } => {
-                    panic!("Unnamed matcher sections not allowed for named field types")
+                ::std::option::Option::None => {
+                    (<#field_ty as ::yew_router::Switch>::key_not_available(), state)
                 }
-            },
-            FieldType::Unnamed { index } => {
-                let name = unnamed_field_index_item(index);
-                quote! {
-                    state = state.or_else(|| #name.build_route_section(&mut buf));
+            };
+            match v {
+                ::std::option::Option::Some(val) => {
+                    state = s; // Set state for the next var.
+                    val
+                },
+                ::std::option::Option::None => return (::std::option::Option::None, s) // Failed
+            }
+        };
+    }
+}
+
+/// Declares a field that is captured within an optional section.
+///
+/// `value` evaluates to the captured string, or `None` if the section was skipped.
+pub(crate) fn optional_field_declaration(
+    field_name: &Ident,
+    field_ty: &Type,
+    value: TokenStream,
+) -> TokenStream {
+    quote! {
+        let #field_name = match #value {
+            ::std::option::Option::Some(value) => {
+                let (v, s) = <#field_ty as ::yew_router::Switch>::from_route_capture(value, state);
+                match v {
+                    ::std::option::Option::Some(val) => {
+                        state = s; // Set state for the next var.
+                        ::std::option::Option::Some(val)
+                    },
+                    ::std::option::Option::None => return (::std::option::Option::None, s) // Failed
                 }
             }
-        },
-        ShadowMatcherToken::End => quote! {},
+            ::std::option::Option::None => ::std::option::Option::None,
+        };
     }
 }
 
+/// Declares a field that captures every occurrence of a query parameter.
+///
+/// Each value is converted to `item_ty`, and then collected into the field.
+pub(crate) fn repeated_field_declaration(
+    field_name: &Ident,
+    field_ty: &Type,
+    item_ty: &Type,
+    key: &str,
+) -> TokenStream {
+    quote! {
+        let #field_name = {
+            let mut values = ::std::vec::Vec::new();
+            for value in captures.remove_all(#key) {
+                let (v, s) = <#item_ty as ::yew_router::Switch>::from_route_capture(value, state);
+                match v {
+                    ::std::option::Option::Some(val) => {
+                        state = s; // Set state for the next var.
+                        values.push(val)
+                    },
+                    ::std::option::Option::None => return (::std::option::Option::None, s) // Failed
+                }
+            }
+            values.into_iter().collect::<#field_ty>()
+        };
+    }
+}
+
+/// Declares the query field, which is deserialized from the query parameters that aren't part of
+/// the matcher string.
+pub(crate) fn query_field_declaration(field_name: &Ident, field_ty: &Type) -> TokenStream {
+    quote! {
+        let #field_name = match ::yew_router::matcher::urlencoded::from_pairs::<#field_ty>(
+            ::std::mem::replace(&mut captures.unmatched_query, ::std::vec::Vec::new())
+        ) {
+            ::std::result::Result::Ok(val) => val,
+            ::std::result::Result::Err(_) => return (::std::option::Option::None, state) // Failed
+        };
+    }
+}
 
 /// Creates an ident used for destructuring unnamed fields.
 ///
@@ -1,22 +1,31 @@
-use crate::switch::shadow::{ShadowCaptureVariant, ShadowMatcherToken};
-use syn::{spanned::Spanned, Attribute, Lit, Meta, MetaNameValue};
-use yew_router_route_parser::FieldNamingScheme;
+use crate::switch::{
+    constraint::check_regex_constraints,
+    shadow::{ShadowCaptureVariant, ShadowMatcherToken},
+};
+use proc_macro2::Span;
+use std::ops::Range;
+use syn::{spanned::Spanned, Attribute, Lit, LitStr, Meta, MetaNameValue};
+use yew_router_route_parser::{FieldNamingScheme, PrettyParseError};
 
 pub enum AttrToken {
-    To(String),
+    To(LitStr),
     End,
     Rest(Option<String>),
 }
 
 impl AttrToken {
     pub fn convert_attributes_to_tokens(attributes: Vec<Attribute>) -> syn::Result<Vec<Self>> {
-        fn get_meta_name_value_str(mnv: &MetaNameValue) -> syn::Result<String> {
+        fn get_meta_name_value_lit_str(mnv: &MetaNameValue) -> syn::Result<LitStr> {
             match &mnv.lit {
-                Lit::Str(s) => Ok(s.value()),
+                Lit::Str(s) => Ok(s.clone()),
                 lit => Err(syn::Error::new_spanned(lit, "expected a string literal")),
             }
         }
 
+        fn get_meta_name_value_str(mnv: &MetaNameValue) -> syn::Result<String> {
+            get_meta_name_value_lit_str(mnv).map(|s| s.value())
+        }
+
         attributes
             .iter()
             .filter_map(|attr: &Attribute| attr.parse_meta().ok())
@@ -27,7 +36,7 @@ impl AttrToken {
                         mnv.path
                             .get_ident()
                             .and_then(|ident| match ident.to_string().as_str() {
-                                "to" => Some(get_meta_name_value_str(&mnv).map(AttrToken::To)),
+                                "to" => Some(get_meta_name_value_lit_str(&mnv).map(AttrToken::To)),
                                 "rest" => Some(
                                     get_meta_name_value_str(&mnv).map(|s| AttrToken::Rest(Some(s))),
                                 ),
@@ -63,26 +72,65 @@ impl AttrToken {
 
     /// The id is an unique identifier that allows otherwise unnamed captures to still be captured
     /// with unique names.
+    ///
+    /// A matcher string with a syntax error or an invalid regex constraint is reported at the
+    /// string literal, narrowed to the offending part where the compiler allows it.
     pub fn into_shadow_matcher_tokens(
         self,
         id: usize,
         field_naming_scheme: FieldNamingScheme,
-    ) -> Vec<ShadowMatcherToken> {
+    ) -> syn::Result<Vec<ShadowMatcherToken>> {
         match self {
-            AttrToken::To(matcher_string) => {
-                yew_router_route_parser::parse_str_and_optimize_tokens(&matcher_string, field_naming_scheme)
-                    .expect("Invalid Matcher") // This is the point where users should see an error message if their matcher string has some syntax error.
-                    .into_iter()
-                    .map(crate::switch::shadow::ShadowMatcherToken::from)
-                    .collect()
+            AttrToken::To(lit) => {
+                let matcher_string = lit.value();
+                let tokens: Vec<ShadowMatcherToken> =
+                    yew_router_route_parser::parse_str_and_optimize_tokens(
+                        &matcher_string,
+                        field_naming_scheme,
+                    )
+                    .map(|tokens| tokens.into_iter().map(ShadowMatcherToken::from).collect())
+                    .map_err(|error| {
+                        syn::Error::new(error_span(&lit, &error), format!("{:?}", error))
+                    })?;
+                check_regex_constraints(&tokens, &lit)?;
+                Ok(tokens)
             }
-            AttrToken::End => vec![ShadowMatcherToken::End],
-            AttrToken::Rest(Some(capture_name)) => vec![ShadowMatcherToken::Capture(
+            AttrToken::End => Ok(vec![ShadowMatcherToken::End]),
+            AttrToken::Rest(Some(capture_name)) => Ok(vec![ShadowMatcherToken::Capture(
                 ShadowCaptureVariant::ManyNamed(capture_name),
-            )],
-            AttrToken::Rest(None) => vec![ShadowMatcherToken::Capture(
+            )]),
+            AttrToken::Rest(None) => Ok(vec![ShadowMatcherToken::Capture(
                 ShadowCaptureVariant::ManyNamed(id.to_string()),
-            )],
+            )]),
         }
     }
 }
+
+/// Gets the span of the character in the literal at which parsing failed.
+fn error_span(lit: &LitStr, error: &PrettyParseError) -> Span {
+    if error.input != lit.value() {
+        return lit.span();
+    }
+    let start = error.position();
+    let end = match error.input[start..].chars().next() {
+        Some(c) => start + c.len_utf8(),
+        // The error is at the end of the matcher string, so the closing quote is pointed at.
+        None => start + 1,
+    };
+    span_within(lit, start..end)
+}
+
+/// Gets the span of a range of bytes of the matcher string within its literal.
+///
+/// Positions can only be mapped into plain string literals, whose source text is the matcher
+/// string between quotes. Raw strings, escapes, and compilers that can't create spans within a
+/// literal fall back to the span of the whole literal.
+pub fn span_within(lit: &LitStr, range: Range<usize>) -> Span {
+    let token = lit.token();
+    if token.to_string() != format!("\"{}\"", lit.value()) {
+        return lit.span();
+    }
+    token
+        .subspan(range.start + 1..range.end + 1)
+        .unwrap_or_else(|| lit.span())
+}
@@ -0,0 +1,172 @@
+//! Compile time validation of the constraints placed on capture sections.
+use crate::switch::{
+    attribute::span_within,
+    option_inner_type,
+    shadow::{ShadowCaptureConstraint, ShadowCaptureVariant, ShadowMatcherToken, ShadowQueryValue},
+    SwitchItem,
+};
+use syn::{Fields, LitStr, Type};
+use yew_router_route_parser::PrimitiveType;
+
+/// Checks that the regular expressions constraining the captures of a matcher string are valid.
+///
+/// An invalid one is reported at the regular expression within the literal, where the compiler
+/// allows it, or else at the whole literal.
+pub fn check_regex_constraints(tokens: &[ShadowMatcherToken], lit: &LitStr) -> syn::Result<()> {
+    for token in tokens {
+        let capture = match token {
+            ShadowMatcherToken::Capture(capture) => capture,
+            ShadowMatcherToken::Optional(tokens) => {
+                check_regex_constraints(tokens, lit)?;
+                continue;
+            }
+            ShadowMatcherToken::Query { parameters, .. } => {
+                for parameter in parameters {
+                    if let ShadowQueryValue::Capture(capture) = &parameter.value {
+                        check_regex_constraint(capture, lit)?;
+                    }
+                }
+                continue;
+            }
+            ShadowMatcherToken::Exact(_) | ShadowMatcherToken::End => continue,
+        };
+        check_regex_constraint(capture, lit)?;
+    }
+    Ok(())
+}
+
+fn check_regex_constraint(capture: &ShadowCaptureVariant, lit: &LitStr) -> syn::Result<()> {
+    if let ShadowCaptureVariant::Named {
+        name,
+        constraint: Some(ShadowCaptureConstraint::Regex(regex)),
+    } = capture
+    {
+        if let Err(e) = regex::Regex::new(regex) {
+            let span = match lit.value().find(&format!("{{{}:{}}}", name, regex)) {
+                Some(start) => {
+                    let start = start + name.len() + 2;
+                    span_within(lit, start..start + regex.len())
+                }
+                None => lit.span(),
+            };
+            return Err(syn::Error::new(
+                span,
+                format!("Invalid regex constraint for `{}`: {}", name, e),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that any value allowed by a type constraint in the matcher can be converted to the type
+/// of the field it is captured into.
+///
+/// Regular expressions are checked by `check_regex_constraints` when the matcher string is parsed.
+pub fn check_constraints(item: &SwitchItem) -> syn::Result<()> {
+    for (field_index, (capture, _)) in item.captures().into_iter().enumerate() {
+        let (name, constraint) = match capture {
+            ShadowCaptureVariant::Named {
+                name,
+                constraint: Some(constraint),
+            } => (name, constraint),
+            _ => continue,
+        };
+
+        let field_ty: Option<&Type> = match &item.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .find(|field| field.ident.as_ref().map_or(false, |ident| ident == name))
+                .map(|field| &field.ty),
+            Fields::Unnamed(fields) => fields
+                .unnamed
+                .iter()
+                .nth(field_index)
+                .map(|field| &field.ty),
+            Fields::Unit => None,
+        };
+
+        match constraint {
+            ShadowCaptureConstraint::Regex(_) => {}
+            ShadowCaptureConstraint::Type(constraint_ty) => {
+                if let Some(field_ty) = field_ty {
+                    let field_ty = option_inner_type(field_ty).unwrap_or(field_ty);
+                    if let Some(field_primitive) = primitive_type(field_ty) {
+                        if !fits(*constraint_ty, field_primitive) {
+                            return Err(syn::Error::new_spanned(
+                                field_ty,
+                                format!(
+                                    "The `{}` constraint on `{}` allows values that can't be parsed as `{}`",
+                                    constraint_ty.name(),
+                                    name,
+                                    field_primitive.name()
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Gets the primitive type that a field's type refers to, if it is one.
+fn primitive_type(ty: &Type) -> Option<PrimitiveType> {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path
+            .path
+            .get_ident()
+            .and_then(|ident| PrimitiveType::from_name(&ident.to_string())),
+        _ => None,
+    }
+}
+
+/// Whether every string accepted by the `constraint` type can also be parsed as the `field` type.
+///
+/// `usize` and `isize` are assumed to be 64 bits wide when constraining, and 32 bits wide when
+/// being constrained, so the answer holds for every target.
+fn fits(constraint: PrimitiveType, field: PrimitiveType) -> bool {
+    use PrimitiveType as PT;
+    if constraint == field {
+        return true;
+    }
+    match (integer_bounds(constraint, 64), field) {
+        (Some(_), PT::F32) | (Some(_), PT::F64) => true,
+        (Some((constraint_signed, constraint_bits)), _) => match integer_bounds(field, 32) {
+            Some((false, field_bits)) => !constraint_signed && constraint_bits <= field_bits,
+            Some((true, field_bits)) => {
+                if constraint_signed {
+                    constraint_bits <= field_bits
+                } else {
+                    constraint_bits < field_bits
+                }
+            }
+            None => false,
+        },
+        (None, _) => match (constraint, field) {
+            (PT::F32, PT::F64) | (PT::F64, PT::F32) => true,
+            _ => false,
+        },
+    }
+}
+
+/// Gets the signedness and width of integer types.
+fn integer_bounds(ty: PrimitiveType, pointer_width: u32) -> Option<(bool, u32)> {
+    use PrimitiveType as PT;
+    match ty {
+        PT::U8 => Some((false, 8)),
+        PT::U16 => Some((false, 16)),
+        PT::U32 => Some((false, 32)),
+        PT::U64 => Some((false, 64)),
+        PT::U128 => Some((false, 128)),
+        PT::Usize => Some((false, pointer_width)),
+        PT::I8 => Some((true, 8)),
+        PT::I16 => Some((true, 16)),
+        PT::I32 => Some((true, 32)),
+        PT::I64 => Some((true, 64)),
+        PT::I128 => Some((true, 128)),
+        PT::Isize => Some((true, pointer_width)),
+        PT::Bool | PT::Char | PT::F32 | PT::F64 => None,
+    }
+}
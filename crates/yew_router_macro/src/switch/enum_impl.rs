@@ -1,3 +1,4 @@
+use crate::switch::{explain::ExplainRoute, RedirectTrailingSlash};
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 
@@ -11,6 +12,8 @@ mod from_route_part;
 pub struct EnumInner<'a> {
     pub from_route_part: FromRoutePart<'a>,
     pub build_route_section: BuildRouteSection<'a>,
+    pub redirect_trailing_slash: RedirectTrailingSlash<'a>,
+    pub explain_route: ExplainRoute<'a>,
 }
 
 impl<'a> ToTokens for EnumInner<'a> {
@@ -18,10 +21,14 @@ impl<'a> ToTokens for EnumInner<'a> {
         let EnumInner {
             from_route_part,
             build_route_section,
+            redirect_trailing_slash,
+            explain_route,
         } = self;
         tokens.extend(quote! {
             #from_route_part
             #build_route_section
+            #redirect_trailing_slash
+            #explain_route
         });
     }
 }
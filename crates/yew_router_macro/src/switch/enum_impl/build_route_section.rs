@@ -1,6 +1,4 @@
-use crate::switch::{
-    shadow::ShadowMatcherToken, unnamed_field_index_item, write_for_token, FieldType, SwitchItem,
-};
+use crate::switch::{unnamed_field_index_item, write_for_tokens, SwitchItem};
 use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::Fields;
@@ -35,6 +33,7 @@ pub fn build_serializer_for_enum(
             matcher,
             ident,
             fields,
+            settings,
         } = switch_item;
         match fields {
             Fields::Named(fields_named) => {
@@ -42,12 +41,10 @@ pub fn build_serializer_for_enum(
                     .named
                     .iter()
                     .filter_map(|named| named.ident.as_ref());
-                let writers = matcher
-                    .iter()
-                    .map(|token| write_for_token(token, FieldType::Named));
+                let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
                 quote! {
                     #enum_ident::#ident{#(#field_names),*} => {
-                        #(#writers)*
+                        #writers
                     }
                 }
             }
@@ -57,30 +54,18 @@ pub fn build_serializer_for_enum(
                     .iter()
                     .enumerate()
                     .map(|(index, _)| unnamed_field_index_item(index));
-                let mut item_count = 0;
-                let writers = matcher.iter().map(|token| {
-                    if let ShadowMatcherToken::Capture(_) = &token {
-                        let ts = write_for_token(token, FieldType::Unnamed { index: item_count });
-                        item_count += 1;
-                        ts
-                    } else {
-                        // Its either a literal, or something that will panic currently
-                        write_for_token(token, FieldType::Unit)
-                    }
-                });
+                let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
                 quote! {
                     #enum_ident::#ident(#(#field_names),*) => {
-                        #(#writers)*
+                        #writers
                     }
                 }
             }
             Fields::Unit => {
-                let writers = matcher
-                    .iter()
-                    .map(|token| write_for_token(token, FieldType::Unit));
+                let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
                 quote! {
                     #enum_ident::#ident => {
-                        #(#writers)*
+                        #writers
                     }
                 }
             }
@@ -1,4 +1,7 @@
-use crate::switch::SwitchItem;
+use crate::switch::{
+    collection_item_type, field_declaration, optional_field_declaration, query_field_declaration,
+    repeated_field_declaration, SwitchItem,
+};
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{Field, Fields, Type};
@@ -11,76 +14,86 @@ pub struct FromRoutePart<'a> {
 
 impl<'a> ToTokens for FromRoutePart<'a> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let variant_matchers = self.switch_variants.iter().map(|sv| {
-            let SwitchItem {
-                matcher,
-                ident,
-                fields,
-            } = sv;
-            let build_from_captures = build_variant_from_captures(&self.enum_ident, ident, fields);
-            let matcher = super::super::build_matcher_from_tokens(&matcher);
+        let matchers = self
+            .switch_variants
+            .iter()
+            .map(super::super::build_matcher_from_tokens);
+        let variant_matchers = self.switch_variants.iter().enumerate().map(|(index, sv)| {
+            let build_from_captures = build_variant_from_captures(&self.enum_ident, sv);
 
             quote! {
-                #matcher
-                #build_from_captures
+                #index => {
+                    let matcher = &matchers[#index];
+                    #build_from_captures
+                    state
+                }
             }
         });
 
         tokens.extend(quote!{
             fn from_route_part<__T>(route: String, mut state: Option<__T>) -> (::std::option::Option<Self>, ::std::option::Option<__T>) {
-                let route_string = route;
-                #(#variant_matchers)*
+                ::std::thread_local! {
+                    static MATCHERS: (
+                        ::std::vec::Vec<::yew_router::matcher::RouteMatcher>,
+                        ::yew_router::matcher::prefix_tree::PrefixTree,
+                    ) = {
+                        let matchers = ::std::vec![#(#matchers),*];
+                        let prefix_tree = ::yew_router::matcher::prefix_tree::PrefixTree::new(&matchers);
+                        (matchers, prefix_tree)
+                    };
+                }
+                MATCHERS.with(move |(matchers, prefix_tree)| {
+                    let route_string = route;
+                    // Only the variants whose leading literals match the route are tried, in order.
+                    for index in prefix_tree.candidates(&route_string) {
+                        state = match *index {
+                            #(#variant_matchers)*
+                            _ => state,
+                        };
+                    }
 
-                (::std::option::Option::None, state)
+                    (::std::option::Option::None, state)
+                })
             }
         });
     }
 }
 
 /// Once the 'captures' exists, attempt to populate the fields from the list of captures.
-fn build_variant_from_captures(
-    enum_ident: &Ident,
-    variant_ident: &Ident,
-    fields: &Fields,
-) -> TokenStream {
+fn build_variant_from_captures(enum_ident: &Ident, switch_item: &SwitchItem) -> TokenStream {
+    let SwitchItem {
+        ident: variant_ident,
+        fields,
+        ..
+    } = switch_item;
     match fields {
         Fields::Named(named_fields) => {
             let (field_declarations, fields): (Vec<_>, Vec<_>) = named_fields
                 .named
                 .iter()
-                .filter_map(|field: &Field| {
-                    let field_ty: &Type = &field.ty;
-                    field.ident.as_ref().map(|i: &Ident| {
-                        let key = i.to_string();
-                        (i, key, field_ty)
-                    })
+                .enumerate()
+                .filter_map(|(index, field): (usize, &Field)| {
+                    field.ident.as_ref().map(|i: &Ident| (index, field, i, i.to_string()))
                 })
-                .map(|(field_name, key, field_ty): (&Ident, String, &Type)| {
-                    let field_decl = quote! {
-                        let #field_name = {
-                            let (v, s) = match captures.remove(#key) {
-                                ::std::option::Option::Some(value) => {
-                                    <#field_ty as ::yew_router::Switch>::from_route_part(
-                                        value,
-                                        state,
-                                    )
-                                }
-                                ::std::option::Option::None => {
-                                    (
-                                        <#field_ty as ::yew_router::Switch>::key_not_available(),
-                                        state,
-                                    )
-                                }
-                            };
-                            match v {
-                                ::std::option::Option::Some(val) => {
-                                    state = s; // Set state for the next var.
-                                    val
-                                },
-                                ::std::option::Option::None => return (None, s) // Failed
-                            }
-                        };
-                    };
+                .map(|(index, field, field_name, key): (usize, &Field, &Ident, String)| {
+                    let field_ty: &Type = &field.ty;
+                    if switch_item.is_query_field(field) {
+                        return (query_field_declaration(field_name, field_ty), field_name);
+                    }
+                    if switch_item.is_repeated_field(field) {
+                        // The item type has been checked when the query fields were prepared.
+                        let item_ty = collection_item_type(field_ty).unwrap();
+                        let field_decl =
+                            repeated_field_declaration(field_name, field_ty, item_ty, &key);
+                        return (field_decl, field_name);
+                    }
+                    if let Some(field_ty) = switch_item.optional_field_type(index, field) {
+                        let value = quote! {captures.remove_first(#key)};
+                        let field_decl = optional_field_declaration(field_name, field_ty, value);
+                        return (field_decl, field_name);
+                    }
+                    let value = quote! {captures.remove_first(#key)};
+                    let field_decl = field_declaration(field_name, field_ty, value);
 
                     (field_decl, field_name)
                 })
@@ -88,7 +101,7 @@ fn build_variant_from_captures(
 
             quote! {
                 let mut state = if let ::std::option::Option::Some(mut captures) = matcher
-                    .capture_route_into_map(&route_string)
+                    .capture_route_into_multimap(&route_string)
                     .ok()
                     .map(|x| x.1)
                 {
@@ -122,31 +135,14 @@ fn build_variant_from_captures(
                 .map(|(idx, f)| {
                     let field_ty = &f.ty;
                     let field_var_name = Ident::new(&format!("field_{}", idx), Span::call_site());
-                    let field_decl = quote! {
-                        let #field_var_name = {
-                            let (v, s) = match drain.next() {
-                                ::std::option::Option::Some(value) => {
-                                    <#field_ty as ::yew_router::Switch>::from_route_part(
-                                        value,
-                                        state,
-                                    )
-                                },
-                                ::std::option::Option::None => {
-                                    (
-                                        <#field_ty as ::yew_router::Switch>::key_not_available(),
-                                        state,
-                                    )
-                                }
-                            };
-                            match v {
-                                ::std::option::Option::Some(val) => {
-                                    state = s; // Set state for the next var.
-                                    val
-                                },
-                                ::std::option::Option::None => return (None, s) // Failed
-                            }
-                        };
-                    };
+                    if let Some(field_ty) = switch_item.optional_field_type(idx, f) {
+                        let value = quote! {drain.next()};
+                        let field_decl =
+                            optional_field_declaration(&field_var_name, field_ty, value);
+                        return (field_decl, field_var_name);
+                    }
+                    let value = quote! {drain.next()};
+                    let field_decl = field_declaration(&field_var_name, field_ty, value);
 
                     (field_decl, field_var_name)
                 })
@@ -0,0 +1,188 @@
+//! Implements `Switch::explain_route`, which reports how the route is matched by each item.
+use crate::switch::{
+    build_matcher_from_tokens, collection_item_type, field_declaration, optional_field_declaration,
+    query_field_declaration, repeated_field_declaration, SwitchItem,
+};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::Fields;
+
+/// Implements `Switch::explain_route` for the items, which are the variants of an enum,
+/// or a single struct.
+pub struct ExplainRoute<'a> {
+    pub switch_items: &'a [SwitchItem],
+}
+
+impl<'a> ToTokens for ExplainRoute<'a> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let reports = self.switch_items.iter().map(|item| {
+            let matcher = build_matcher_from_tokens(item);
+            let variant = item.ident.to_string();
+            let report = match check_conversions(item) {
+                Some(check_conversions) => quote! {
+                    let mut report = matcher.explain(route);
+                    if report.is_match() {
+                        #check_conversions
+                    }
+                },
+                None => quote! {
+                    let report = matcher.explain(route);
+                },
+            };
+            quote! {
+                {
+                    let matcher = #matcher;
+                    #report
+                    ::yew_router::matcher::explain::VariantReport {
+                        variant: #variant,
+                        report,
+                    }
+                }
+            }
+        });
+        tokens.extend(quote! {
+            fn explain_route(
+                route: &str
+            ) -> ::std::vec::Vec<::yew_router::matcher::explain::VariantReport> {
+                ::std::vec![#(#reports),*]
+            }
+        });
+    }
+}
+
+/// Converts the captures of a matched route into the fields, in the same way as
+/// `from_route_part`, and reports the field that couldn't be converted.
+///
+/// Returns `None` if there are no fields to convert.
+fn check_conversions(item: &SwitchItem) -> Option<TokenStream> {
+    let (captures, conversions): (TokenStream, Vec<TokenStream>) = match &item.fields {
+        Fields::Named(fields) => {
+            let conversions = fields
+                .named
+                .iter()
+                .enumerate()
+                .filter_map(|(index, field)| {
+                    field.ident.as_ref().map(|ident| (index, field, ident))
+                })
+                .map(|(index, field, field_name)| {
+                    let field_ty = &field.ty;
+                    let key = field_name.to_string();
+                    let captured_value = quote! {
+                        report
+                            .captures
+                            .iter()
+                            .find(|(key, _)| key == #key)
+                            .map(|(_, value)| ::std::clone::Clone::clone(value))
+                    };
+                    let (field_decl, value) = if item.is_query_field(field) {
+                        let field_decl = query_field_declaration(field_name, field_ty);
+                        (field_decl, quote! {::std::option::Option::None})
+                    } else if item.is_repeated_field(field) {
+                        // The item type has been checked when the query fields were prepared.
+                        let item_ty = collection_item_type(field_ty).unwrap();
+                        let field_decl =
+                            repeated_field_declaration(field_name, field_ty, item_ty, &key);
+                        (field_decl, quote! {::std::option::Option::None})
+                    } else if let Some(field_ty) = item.optional_field_type(index, field) {
+                        let value = quote! {captures.remove_first(#key)};
+                        let field_decl = optional_field_declaration(field_name, field_ty, value);
+                        (field_decl, captured_value)
+                    } else {
+                        let value = quote! {captures.remove_first(#key)};
+                        (
+                            field_declaration(field_name, field_ty, value),
+                            captured_value,
+                        )
+                    };
+                    conversion(&key, value, field_decl, field_name)
+                })
+                .collect();
+            let captures = quote! {
+                let mut captures = matcher
+                    .capture_route_into_multimap(route)
+                    .map(|x| x.1)
+                    .unwrap_or_default();
+            };
+            (captures, conversions)
+        }
+        Fields::Unnamed(fields) => {
+            let conversions = fields
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let field_ty = &field.ty;
+                    let field_var_name = Ident::new(&format!("field_{}", index), Span::call_site());
+                    let value = quote! {drain.next()};
+                    let field_decl = match item.optional_field_type(index, field) {
+                        Some(field_ty) => {
+                            optional_field_declaration(&field_var_name, field_ty, value)
+                        }
+                        None => field_declaration(&field_var_name, field_ty, value),
+                    };
+                    let captured_value = quote! {
+                        report
+                            .captures
+                            .get(#index)
+                            .map(|(_, value)| ::std::clone::Clone::clone(value))
+                    };
+                    conversion(
+                        &index.to_string(),
+                        captured_value,
+                        field_decl,
+                        &field_var_name,
+                    )
+                })
+                .collect();
+            let captures = quote! {
+                let mut captures = matcher
+                    .capture_route_into_vec(route)
+                    .map(|x| x.1)
+                    .unwrap_or_default();
+                let mut drain = captures.drain(..);
+            };
+            (captures, conversions)
+        }
+        Fields::Unit => return None,
+    };
+    if conversions.is_empty() {
+        return None;
+    }
+    Some(quote! {
+        #captures
+        let mut state: ::std::option::Option<()> = ::std::option::Option::None;
+        let mut failed_field = ::std::option::Option::None;
+        let mut convert = || {
+            #(#conversions)*
+            (::std::option::Option::Some(()), state)
+        };
+        if convert().0.is_none() {
+            if let ::std::option::Option::Some((field, value)) = failed_field {
+                report.mismatch = ::std::option::Option::Some(
+                    ::yew_router::matcher::explain::Mismatch {
+                        token: ::std::option::Option::None,
+                        offset: route.len() - report.unmatched.len(),
+                        reason: ::yew_router::matcher::explain::MismatchReason::Conversion {
+                            field: ::std::string::ToString::to_string(field),
+                            value,
+                        },
+                    }
+                );
+            }
+        }
+    })
+}
+
+/// Notes the field that is about to be converted, so that it is known if the conversion fails.
+fn conversion(
+    field: &str,
+    value: TokenStream,
+    field_decl: TokenStream,
+    field_name: &Ident,
+) -> TokenStream {
+    quote! {
+        failed_field = ::std::option::Option::Some((#field, #value));
+        #field_decl
+        let _ = #field_name;
+    }
+}
@@ -0,0 +1,120 @@
+//! Compile time validation of optional sections.
+use crate::switch::{
+    capture_name, option_inner_type,
+    shadow::{ShadowCaptureVariant, ShadowMatcherToken, ShadowQueryValue},
+    SwitchItem,
+};
+use syn::{Field, Fields};
+
+/// Marks the query parameters that are captured into `Option` fields as optional,
+/// so they can be omitted from the route.
+pub fn mark_optional_query_parameters(item: &mut SwitchItem) {
+    let fields: Vec<Field> = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().cloned().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().cloned().collect(),
+        Fields::Unit => return,
+    };
+    let mut index = 0;
+    mark_tokens(&mut item.matcher, &fields, &mut index);
+}
+
+fn mark_tokens(tokens: &mut [ShadowMatcherToken], fields: &[Field], index: &mut usize) {
+    for token in tokens {
+        match token {
+            ShadowMatcherToken::Capture(_) => *index += 1,
+            ShadowMatcherToken::Optional(tokens) => mark_tokens(tokens, fields, index),
+            ShadowMatcherToken::Query { parameters, .. } => {
+                for parameter in parameters {
+                    if let ShadowQueryValue::Capture(capture) = &parameter.value {
+                        if let ShadowCaptureVariant::ManyNamed(_)
+                        | ShadowCaptureVariant::ManyUnnamed = capture
+                        {
+                            // Repeated parameters are collected, so they are never missing.
+                            *index += 1;
+                            continue;
+                        }
+                        let field = match capture_name(capture) {
+                            Some(name) => fields.iter().find(|field| {
+                                field.ident.as_ref().map_or(false, |ident| ident == name)
+                            }),
+                            None => fields.get(*index),
+                        };
+                        if let Some(field) = field {
+                            parameter.optional = option_inner_type(&field.ty).is_some();
+                        }
+                        *index += 1;
+                    }
+                }
+            }
+            ShadowMatcherToken::Exact(_) | ShadowMatcherToken::End => {}
+        }
+    }
+}
+
+/// Checks that every field captured within an optional section is an `Option`.
+///
+/// The captures of unnamed fields are positional, so for those, it also checks that no capture
+/// follows an optional section that contains captures.
+/// Otherwise skipping the section would shift the following captures into the wrong fields.
+pub fn check_optional_sections(item: &SwitchItem) -> syn::Result<()> {
+    let fields: Vec<_> = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => return Ok(()),
+    };
+    for (index, field) in fields.into_iter().enumerate() {
+        if item.is_optional_field(index, field) && option_inner_type(&field.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "Fields captured within an optional section must be an `Option`",
+            ));
+        }
+    }
+
+    if let Fields::Unnamed(_) = &item.fields {
+        let mut after_optional_capture = false;
+        for token in &item.matcher {
+            match token {
+                ShadowMatcherToken::Capture(_) if after_optional_capture => {
+                    return Err(syn::Error::new_spanned(
+                        &item.ident,
+                        "Unnamed fields can't be captured after an optional section that contains captures, use named fields instead",
+                    ));
+                }
+                ShadowMatcherToken::Optional(tokens) if contains_capture(tokens) => {
+                    after_optional_capture = true
+                }
+                ShadowMatcherToken::Query { parameters, .. } => {
+                    for parameter in parameters {
+                        if let ShadowQueryValue::Capture(_) = parameter.value {
+                            if after_optional_capture {
+                                return Err(syn::Error::new_spanned(
+                                    &item.ident,
+                                    "Unnamed fields can't be captured after an optional query parameter, use named fields instead",
+                                ));
+                            }
+                            after_optional_capture |= parameter.optional;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether any of the tokens, or those nested within them, are captures.
+fn contains_capture(tokens: &[ShadowMatcherToken]) -> bool {
+    tokens.iter().any(|token| match token {
+        ShadowMatcherToken::Capture(_) => true,
+        ShadowMatcherToken::Optional(tokens) => contains_capture(tokens),
+        ShadowMatcherToken::Query { parameters, .. } => {
+            parameters.iter().any(|parameter| match parameter.value {
+                ShadowQueryValue::Capture(_) => true,
+                _ => false,
+            })
+        }
+        ShadowMatcherToken::Exact(_) | ShadowMatcherToken::End => false,
+    })
+}
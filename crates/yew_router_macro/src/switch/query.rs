@@ -0,0 +1,141 @@
+//! Compile time validation of query fields and repeated query parameters.
+use crate::switch::{
+    collection_item_type,
+    shadow::{ShadowCaptureVariant, ShadowMatcherToken, ShadowQueryValue},
+    SwitchItem,
+};
+use syn::{Field, Fields};
+
+/// Determines if the field is marked with `#[query]`.
+fn has_query_attribute(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| attr.path.is_ident("query"))
+}
+
+/// Checks that query fields and fields capturing repeated query parameters can be populated.
+///
+/// A query field is either named by a `{?name}` section, or marked with `#[query]`.
+/// The name of a field marked with `#[query]` is set as the rest of the query section,
+/// which is added to the matcher if it doesn't have one.
+pub fn prepare_query_fields(item: &mut SwitchItem) -> syn::Result<()> {
+    let query_fields: Vec<&Field> = item
+        .fields
+        .iter()
+        .filter(|f| has_query_attribute(f))
+        .collect();
+    let rest = item.matcher.iter().find_map(|token| match token {
+        ShadowMatcherToken::Query { rest, .. } => rest.as_ref(),
+        _ => None,
+    });
+    if let Fields::Named(_) = item.fields {
+    } else {
+        if let Some(field) = query_fields.first() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`#[query]` can only be used on named fields",
+            ));
+        }
+        if rest.is_some() {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                "The query section can only be captured into named fields",
+            ));
+        }
+        if has_repeated_capture(&item.matcher) {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                "Repeated query parameters can only be captured into named fields",
+            ));
+        }
+        return Ok(());
+    }
+    if let Some(field) = query_fields.get(1) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "Only one field can be marked with `#[query]`",
+        ));
+    }
+
+    for field in item.fields.iter() {
+        if item.is_repeated_field(field) && collection_item_type(&field.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "Fields capturing a repeated query parameter must be a collection, such as `Vec<T>`",
+            ));
+        }
+    }
+
+    let field = match (query_fields.first(), rest) {
+        (None, None) => return Ok(()),
+        (None, Some(rest)) => {
+            if !item
+                .fields
+                .iter()
+                .any(|field| field.ident.as_ref().map_or(false, |ident| ident == rest))
+            {
+                return Err(syn::Error::new_spanned(
+                    &item.ident,
+                    format!(
+                        "There is no field named `{}` to capture the query into",
+                        rest
+                    ),
+                ));
+            }
+            return Ok(());
+        }
+        (Some(field), Some(_)) => {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`#[query]` can't be used when the query section is captured with `{?name}`",
+            ))
+        }
+        (Some(field), None) => field.ident.as_ref().map(ToString::to_string),
+    };
+
+    if let Some(ShadowMatcherToken::Query {
+        rest, deny_unknown, ..
+    }) = item.matcher.iter_mut().find(|token| match token {
+        ShadowMatcherToken::Query { .. } => true,
+        _ => false,
+    }) {
+        *rest = field;
+        // The parameters that would be denied are collected instead.
+        *deny_unknown = false;
+        return Ok(());
+    }
+    let has_fragment = item.matcher.iter().any(|token| match token {
+        ShadowMatcherToken::Exact(literal) => literal.contains('#'),
+        _ => false,
+    });
+    if has_fragment {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "A `#[query]` field requires a query section in a matcher string with a fragment",
+        ));
+    }
+    let index = match item.matcher.last() {
+        Some(ShadowMatcherToken::End) => item.matcher.len() - 1,
+        _ => item.matcher.len(),
+    };
+    item.matcher.insert(
+        index,
+        ShadowMatcherToken::Query {
+            parameters: vec![],
+            deny_unknown: false,
+            rest: field,
+        },
+    );
+    Ok(())
+}
+
+fn has_repeated_capture(tokens: &[ShadowMatcherToken]) -> bool {
+    tokens.iter().any(|token| match token {
+        ShadowMatcherToken::Query { parameters, .. } => {
+            parameters.iter().any(|parameter| match parameter.value {
+                ShadowQueryValue::Capture(ShadowCaptureVariant::ManyNamed(_))
+                | ShadowQueryValue::Capture(ShadowCaptureVariant::ManyUnnamed) => true,
+                _ => false,
+            })
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,197 @@
+//! Compile time detection of enum variants that can never be matched.
+//!
+//! Variants are tried in order, and unless a matcher string ends with `!`, it only has to match
+//! the start of a route. So an earlier variant can match every route that a later one would,
+//! leaving the later one unreachable.
+//!
+//! Only cases that can be proven from the matcher strings are detected: an earlier variant made
+//! up of literals, optionally followed by a single capture into a `String`.
+use crate::switch::{
+    settings::TrailingSlash,
+    shadow::{ShadowCaptureVariant, ShadowMatcherToken},
+    SwitchItem,
+};
+use syn::{Fields, Type};
+
+/// Characters that can't be captured within a single section, as in the matcher.
+const INVALID_CHARACTERS: &str = " */#&?{}=";
+/// Characters that can't be captured across many sections, as in the matcher.
+const INVALID_MANY_CHARACTERS: &str = " #&?=";
+
+/// The routes an earlier variant is known to match.
+enum Pattern {
+    /// Routes starting with the literal, or only the literal if `end` is set.
+    Literal { literal: String, end: bool },
+    /// Routes starting with the literal, followed by anything the capture accepts.
+    Capture { literal: String, many: bool },
+    /// Can't be reasoned about, because the match depends on captures or the query.
+    Unknown,
+}
+
+/// Checks that no variant is shadowed by one before it.
+pub fn check_unreachable_variants(variants: &[SwitchItem]) -> syn::Result<()> {
+    for (index, later) in variants.iter().enumerate() {
+        let earlier = variants[..index]
+            .iter()
+            .find(|earlier| subsumes(earlier, later));
+        if let Some(earlier) = earlier {
+            let mut message = format!(
+                "`{}` can never be matched, because every route it matches is matched by `{}`, which comes before it",
+                later.ident, earlier.ident
+            );
+            if let Pattern::Literal {
+                literal,
+                end: false,
+            } = pattern(earlier)
+            {
+                message += &format!(
+                    ". If `{}` should only match `{}` exactly, end its matcher string with `!`",
+                    earlier.ident, literal
+                );
+            }
+            return Err(syn::Error::new_spanned(&later.ident, message));
+        }
+    }
+    Ok(())
+}
+
+fn pattern(item: &SwitchItem) -> Pattern {
+    let (literal, rest) = leading_literal(&item.matcher);
+    match rest {
+        [] if field_count(&item.fields) == 0 => Pattern::Literal {
+            literal,
+            end: false,
+        },
+        [ShadowMatcherToken::End] if field_count(&item.fields) == 0 => {
+            Pattern::Literal { literal, end: true }
+        }
+        [ShadowMatcherToken::Capture(capture)] if is_single_string_field(&item.fields) => {
+            match capture {
+                ShadowCaptureVariant::Unnamed
+                | ShadowCaptureVariant::Named {
+                    constraint: None, ..
+                } => Pattern::Capture {
+                    literal,
+                    many: false,
+                },
+                ShadowCaptureVariant::ManyUnnamed | ShadowCaptureVariant::ManyNamed(_) => {
+                    Pattern::Capture {
+                        literal,
+                        many: true,
+                    }
+                }
+                _ => Pattern::Unknown,
+            }
+        }
+        _ => Pattern::Unknown,
+    }
+}
+
+/// Determines if every route matched by the later variant is matched by the earlier one.
+fn subsumes(earlier: &SwitchItem, later: &SwitchItem) -> bool {
+    let settings = earlier.settings;
+    if settings.case_sensitive && !later.settings.case_sensitive {
+        return false;
+    }
+    if settings.trailing_slash == TrailingSlash::Require
+        && later.settings.trailing_slash != TrailingSlash::Require
+    {
+        return false;
+    }
+    let (mut later_literal, later_rest) = leading_literal(&later.matcher);
+    // The route may not have the trailing slash.
+    if later.settings.trailing_slash != TrailingSlash::Strict
+        && later_literal.len() > 1
+        && later_literal.ends_with('/')
+    {
+        later_literal.pop();
+    }
+    let starts_with = |literal: &str| {
+        later_literal.len() >= literal.len()
+            && later_literal.is_char_boundary(literal.len())
+            && eq(
+                &later_literal[..literal.len()],
+                literal,
+                settings.case_sensitive,
+            )
+    };
+    match pattern(earlier) {
+        Pattern::Literal {
+            literal,
+            end: false,
+        } => starts_with(&literal),
+        Pattern::Literal { literal, end: true } => {
+            is_end(later_rest)
+                && settings.trailing_slash == later.settings.trailing_slash
+                && eq(&later_literal, &literal, settings.case_sensitive)
+        }
+        Pattern::Capture { literal, many } => {
+            if !starts_with(&literal) {
+                return false;
+            }
+            let invalid_characters = if many {
+                INVALID_MANY_CHARACTERS
+            } else {
+                INVALID_CHARACTERS
+            };
+            match later_literal[literal.len()..].chars().next() {
+                Some(c) => !invalid_characters.contains(c),
+                // A capture across many sections also matches nothing at all.
+                None => many && (later_rest.is_empty() || is_end(later_rest)),
+            }
+        }
+        Pattern::Unknown => false,
+    }
+}
+
+/// Splits the tokens into the literal they start with, and the tokens that follow it.
+fn leading_literal(tokens: &[ShadowMatcherToken]) -> (String, &[ShadowMatcherToken]) {
+    let mut literal = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            ShadowMatcherToken::Exact(exact) => literal += exact,
+            _ => return (literal, &tokens[index..]),
+        }
+    }
+    (literal, &[])
+}
+
+/// Determines if the tokens only consist of the end of the matcher.
+fn is_end(tokens: &[ShadowMatcherToken]) -> bool {
+    match tokens {
+        [ShadowMatcherToken::End] => true,
+        _ => false,
+    }
+}
+
+fn eq(a: &str, b: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        a.eq_ignore_ascii_case(b)
+    }
+}
+
+fn field_count(fields: &Fields) -> usize {
+    match fields {
+        Fields::Named(fields) => fields.named.len(),
+        Fields::Unnamed(fields) => fields.unnamed.len(),
+        Fields::Unit => 0,
+    }
+}
+
+/// Whether there is exactly one field, and it is a `String`, which can hold any captured value.
+fn is_single_string_field(fields: &Fields) -> bool {
+    let field = match fields {
+        Fields::Named(fields) if fields.named.len() == 1 => &fields.named[0],
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0],
+        _ => return false,
+    };
+    match &field.ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path
+            .path
+            .get_ident()
+            .map_or(false, |ident| ident == "String"),
+        _ => false,
+    }
+}
@@ -0,0 +1,99 @@
+//! The `#[switch(...)]` attribute, which configures how the matcher of a type or variant behaves.
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Attribute, Lit, Meta, NestedMeta};
+
+/// Settings of the matcher generated for a struct or enum variant.
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub case_sensitive: bool,
+    pub trailing_slash: TrailingSlash,
+}
+
+/// Mirrors `yew_router::matcher::TrailingSlash`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrailingSlash {
+    Strict,
+    Ignore,
+    Require,
+    Redirect,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            case_sensitive: false,
+            trailing_slash: TrailingSlash::Strict,
+        }
+    }
+}
+
+impl Settings {
+    /// Applies the settings in any `#[switch(...)]` attributes on top of these ones.
+    ///
+    /// This allows variants to override the settings of their enum.
+    pub fn with_attributes(mut self, attributes: &[Attribute]) -> syn::Result<Self> {
+        for attribute in attributes {
+            if !attribute.path.is_ident("switch") {
+                continue;
+            }
+            let list = match attribute.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `#[switch(case_sensitive)]` or `#[switch(trailing_slash = \"...\")]`",
+                )),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("case_sensitive") => {
+                        self.case_sensitive = true;
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("case_insensitive") => {
+                        self.case_sensitive = false;
+                    }
+                    NestedMeta::Meta(Meta::NameValue(mnv))
+                        if mnv.path.is_ident("trailing_slash") =>
+                    {
+                        self.trailing_slash = match &mnv.lit {
+                            Lit::Str(s) if s.value() == "strict" => TrailingSlash::Strict,
+                            Lit::Str(s) if s.value() == "ignore" => TrailingSlash::Ignore,
+                            Lit::Str(s) if s.value() == "require" => TrailingSlash::Require,
+                            Lit::Str(s) if s.value() == "redirect" => TrailingSlash::Redirect,
+                            lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "expected \"strict\", \"ignore\", \"require\" or \"redirect\"",
+                                ))
+                            }
+                        };
+                    }
+                    nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "unknown setting, expected `case_sensitive`, `case_insensitive` or `trailing_slash`",
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl ToTokens for Settings {
+    fn to_tokens(&self, ts: &mut TokenStream) {
+        let case_insensitive = !self.case_sensitive;
+        let trailing_slash = match self.trailing_slash {
+            TrailingSlash::Strict => quote! {Strict},
+            TrailingSlash::Ignore => quote! {Ignore},
+            TrailingSlash::Require => quote! {Require},
+            TrailingSlash::Redirect => quote! {Redirect},
+        };
+        ts.extend(quote! {
+            ::yew_router::matcher::MatcherSettings::default()
+                .with_case_insensitive(#case_insensitive)
+                .with_trailing_slash(::yew_router::matcher::TrailingSlash::#trailing_slash)
+        })
+    }
+}
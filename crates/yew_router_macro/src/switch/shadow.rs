@@ -1,6 +1,8 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
-use yew_router_route_parser::{CaptureVariant, MatcherToken};
+use yew_router_route_parser::{
+    CaptureConstraint, CaptureVariant, MatcherToken, PrimitiveType, QueryParameter, QueryValue,
+};
 
 impl ToTokens for ShadowMatcherToken {
     fn to_tokens(&self, ts: &mut TokenStream) {
@@ -15,6 +17,26 @@ impl ToTokens for ShadowMatcherToken {
             SOT::End => quote! {
                 ::yew_router::matcher::MatcherToken::End
             },
+            SOT::Optional(tokens) => quote! {
+                ::yew_router::matcher::MatcherToken::Optional(::std::vec![#(#tokens),*])
+            },
+            SOT::Query {
+                parameters,
+                deny_unknown,
+                rest,
+            } => {
+                let rest = match rest {
+                    Some(rest) => quote! {::std::option::Option::Some(#rest.to_string())},
+                    None => quote! {::std::option::Option::None},
+                };
+                quote! {
+                    ::yew_router::matcher::MatcherToken::Query {
+                        parameters: ::std::vec![#(#parameters),*],
+                        deny_unknown: #deny_unknown,
+                        rest: #rest,
+                    }
+                }
+            }
         };
         ts.extend(t)
     }
@@ -26,6 +48,50 @@ pub enum ShadowMatcherToken {
     Exact(String),
     Capture(ShadowCaptureVariant),
     End,
+    Optional(Vec<ShadowMatcherToken>),
+    Query {
+        parameters: Vec<ShadowQueryParameter>,
+        deny_unknown: bool,
+        rest: Option<String>,
+    },
+}
+
+/// A shadow of the QueryParameter type.
+pub struct ShadowQueryParameter {
+    pub key: String,
+    pub value: ShadowQueryValue,
+    pub optional: bool,
+}
+
+/// A shadow of the QueryValue type.
+pub enum ShadowQueryValue {
+    Exact(String),
+    Capture(ShadowCaptureVariant),
+}
+
+impl ToTokens for ShadowQueryParameter {
+    fn to_tokens(&self, ts: &mut TokenStream) {
+        let ShadowQueryParameter {
+            key,
+            value,
+            optional,
+        } = self;
+        let value = match value {
+            ShadowQueryValue::Exact(s) => {
+                quote! {::yew_router::matcher::QueryValue::Exact(#s.to_string())}
+            }
+            ShadowQueryValue::Capture(variant) => {
+                quote! {::yew_router::matcher::QueryValue::Capture(#variant)}
+            }
+        };
+        ts.extend(quote! {
+            ::yew_router::matcher::QueryParameter {
+                key: #key.to_string(),
+                value: #value,
+                optional: #optional,
+            }
+        })
+    }
 }
 
 pub enum ShadowCaptureVariant {
@@ -39,7 +105,10 @@ pub enum ShadowCaptureVariant {
         sections: usize,
     },
     /// {name} - captures a section and adds it to the map with a given name
-    Named(String),
+    Named {
+        name: String,
+        constraint: Option<ShadowCaptureConstraint>,
+    },
     /// {*:name} - captures over many sections and adds it to the map with a given name.
     ManyNamed(String),
     /// {2:name} - captures a fixed number of sections with a given name.
@@ -49,8 +118,12 @@ pub enum ShadowCaptureVariant {
 impl ToTokens for ShadowCaptureVariant {
     fn to_tokens(&self, ts: &mut TokenStream) {
         let t = match self {
-            ShadowCaptureVariant::Named(name) => {
-                quote! {::yew_router::matcher::CaptureVariant::Named(#name.to_string())}
+            ShadowCaptureVariant::Named { name, constraint } => {
+                let constraint = match constraint {
+                    Some(constraint) => quote! {::std::option::Option::Some(#constraint)},
+                    None => quote! {::std::option::Option::None},
+                };
+                quote! {::yew_router::matcher::CaptureVariant::Named{name: #name.to_string(), constraint: #constraint}}
             }
             ShadowCaptureVariant::ManyNamed(name) => {
                 quote! {::yew_router::matcher::CaptureVariant::ManyNamed(#name.to_string())}
@@ -80,6 +153,32 @@ impl From<MatcherToken> for ShadowMatcherToken {
             MT::Exact(s) => SOT::Exact(s),
             MT::Capture(capture) => SOT::Capture(capture.into()),
             MT::End => SOT::End,
+            MT::Optional(tokens) => SOT::Optional(tokens.into_iter().map(SOT::from).collect()),
+            MT::Query {
+                parameters,
+                deny_unknown,
+                rest,
+            } => SOT::Query {
+                parameters: parameters
+                    .into_iter()
+                    .map(ShadowQueryParameter::from)
+                    .collect(),
+                deny_unknown,
+                rest,
+            },
+        }
+    }
+}
+
+impl From<QueryParameter> for ShadowQueryParameter {
+    fn from(qp: QueryParameter) -> Self {
+        ShadowQueryParameter {
+            key: qp.key,
+            value: match qp.value {
+                QueryValue::Exact(s) => ShadowQueryValue::Exact(s),
+                QueryValue::Capture(capture) => ShadowQueryValue::Capture(capture.into()),
+            },
+            optional: qp.optional,
         }
     }
 }
@@ -88,7 +187,10 @@ impl From<CaptureVariant> for ShadowCaptureVariant {
     fn from(cv: CaptureVariant) -> Self {
         use ShadowCaptureVariant as SCV;
         match cv {
-            CaptureVariant::Named(name) => SCV::Named(name),
+            CaptureVariant::Named { name, constraint } => SCV::Named {
+                name,
+                constraint: constraint.map(ShadowCaptureConstraint::from),
+            },
             CaptureVariant::ManyNamed(name) => SCV::ManyNamed(name),
             CaptureVariant::NumberedNamed { sections, name } => {
                 SCV::NumberedNamed { sections, name }
@@ -99,3 +201,35 @@ impl From<CaptureVariant> for ShadowCaptureVariant {
         }
     }
 }
+
+/// A shadow of the CaptureConstraint type.
+pub enum ShadowCaptureConstraint {
+    /// {name:u32}
+    Type(PrimitiveType),
+    /// {name:[a-z]+}
+    Regex(String),
+}
+
+impl ToTokens for ShadowCaptureConstraint {
+    fn to_tokens(&self, ts: &mut TokenStream) {
+        let t = match self {
+            ShadowCaptureConstraint::Type(ty) => {
+                let variant = Ident::new(&format!("{:?}", ty), Span::call_site());
+                quote! {::yew_router::matcher::CaptureConstraint::Type(::yew_router::matcher::PrimitiveType::#variant)}
+            }
+            ShadowCaptureConstraint::Regex(regex) => {
+                quote! {::yew_router::matcher::CaptureConstraint::Regex(#regex.to_string())}
+            }
+        };
+        ts.extend(t)
+    }
+}
+
+impl From<CaptureConstraint> for ShadowCaptureConstraint {
+    fn from(cc: CaptureConstraint) -> Self {
+        match cc {
+            CaptureConstraint::Type(ty) => ShadowCaptureConstraint::Type(ty),
+            CaptureConstraint::Regex(regex) => ShadowCaptureConstraint::Regex(regex),
+        }
+    }
+}
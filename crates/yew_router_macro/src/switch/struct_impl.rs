@@ -1,4 +1,5 @@
 pub use self::{build_route_section::BuildRouteSection, from_route_part::FromRoutePart};
+use crate::switch::{explain::ExplainRoute, RedirectTrailingSlash};
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 
@@ -9,6 +10,8 @@ mod from_route_part;
 pub struct StructInner<'a> {
     pub from_route_part: FromRoutePart<'a>,
     pub build_route_section: BuildRouteSection<'a>,
+    pub redirect_trailing_slash: RedirectTrailingSlash<'a>,
+    pub explain_route: ExplainRoute<'a>,
 }
 
 impl<'a> ToTokens for StructInner<'a> {
@@ -16,10 +19,14 @@ impl<'a> ToTokens for StructInner<'a> {
         let StructInner {
             from_route_part,
             build_route_section,
+            redirect_trailing_slash,
+            explain_route,
         } = self;
         tokens.extend(quote! {
              #from_route_part
              #build_route_section
+             #redirect_trailing_slash
+             #explain_route
         })
     }
 }
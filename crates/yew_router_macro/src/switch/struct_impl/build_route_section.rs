@@ -1,6 +1,4 @@
-use crate::switch::{
-    shadow::ShadowMatcherToken, unnamed_field_index_item, write_for_token, FieldType, SwitchItem,
-};
+use crate::switch::{unnamed_field_index_item, write_for_tokens, SwitchItem};
 use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::Fields;
@@ -27,6 +25,7 @@ pub fn build_serializer_for_struct(switch_item: &SwitchItem, item: &Ident) -> To
         matcher,
         ident,
         fields,
+        settings,
     } = switch_item;
     let destructor_and_writers = match fields {
         Fields::Named(fields_named) => {
@@ -34,12 +33,10 @@ pub fn build_serializer_for_struct(switch_item: &SwitchItem, item: &Ident) -> To
                 .named
                 .iter()
                 .filter_map(|named| named.ident.as_ref());
-            let writers = matcher
-                .iter()
-                .map(|token| write_for_token(token, FieldType::Named));
+            let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
             quote! {
                 let #ident{#(#field_names),*} = #item;
-                #(#writers)*
+                #writers
             }
         }
         Fields::Unnamed(fields_unnamed) => {
@@ -48,28 +45,16 @@ pub fn build_serializer_for_struct(switch_item: &SwitchItem, item: &Ident) -> To
                 .iter()
                 .enumerate()
                 .map(|(index, _)| unnamed_field_index_item(index));
-            let mut item_count = 0;
-            let writers = matcher.iter().map(|token| {
-                if let ShadowMatcherToken::Capture(_) = &token {
-                    let ts = write_for_token(token, FieldType::Unnamed { index: item_count });
-                    item_count += 1;
-                    ts
-                } else {
-                    // Its either a literal, or something that will panic currently
-                    write_for_token(token, FieldType::Unit)
-                }
-            });
+            let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
             quote! {
                 let #ident(#(#field_names),*) = #item;
-                #(#writers)*
+                #writers
             }
         }
         Fields::Unit => {
-            let writers = matcher
-                .iter()
-                .map(|token| write_for_token(token, FieldType::Unit));
+            let writers = write_for_tokens(matcher, fields, *settings, &mut 0);
             quote! {
-                #(#writers)*
+                #writers
             }
         }
     };
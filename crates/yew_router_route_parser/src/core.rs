@@ -10,9 +10,9 @@ use nom::{
         complete::{char, digit1},
         is_digit,
     },
-    combinator::{map, map_parser},
+    combinator::{map, map_parser, opt},
     error::ErrorKind,
-    sequence::{delimited, separated_pair},
+    sequence::{delimited, pair, preceded, separated_pair},
     IResult,
 };
 
@@ -63,6 +63,20 @@ fn get_open_bracket(i: &str) -> IResult<&str, (), ParseError> {
     })
 }
 
+/// Returns an OptionalBegin variant if the next character is a '['.
+pub fn get_open_optional(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+    map(char('['), |_: char| RouteParserToken::OptionalBegin)(i).map_err(|_: nom::Err<()>| {
+        nom::Err::Error(ParseError::expected(ExpectedToken::OptionalBegin))
+    })
+}
+
+/// Returns an OptionalEnd variant if the next character is a ']'.
+pub fn get_close_optional(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+    map(char(']'), |_: char| RouteParserToken::OptionalEnd)(i).map_err(|_: nom::Err<()>| {
+        nom::Err::Error(ParseError::expected(ExpectedToken::OptionalEnd))
+    })
+}
+
 fn get_close_bracket(i: &str) -> IResult<&str, (), ParseError> {
     map(char('}'), |_: char| ())(i).map_err(|_: nom::Err<()>| {
         nom::Err::Error(ParseError::expected(ExpectedToken::CloseBracket))
@@ -86,9 +100,9 @@ fn get_colon(i: &str) -> IResult<&str, (), ParseError> {
 
 fn rust_ident(i: &str) -> IResult<&str, &str, ParseError> {
     let invalid_ident_chars = r##" \|/{[]()?+=-!@#$%^&*~`'";:"##;
-    // Detect an ident by first reading until a } is found,
+    // Detect an ident by first reading until a } or : is found,
     // then validating the captured section against invalid characters that can't be in rust idents.
-    map_parser(take_till1(move |c| c == '}'), move |i: &str| {
+    map_parser(take_till1(move |c| c == '}' || c == ':'), move |i: &str| {
         match take_till1::<_, _, ()>(|c| invalid_ident_chars.contains(c))(i) {
             Ok((remain, got)) => {
                 // Detects if the first character is a digit.
@@ -115,14 +129,56 @@ fn rust_ident(i: &str) -> IResult<&str, &str, ParseError> {
     })(i)
 }
 
-/// Matches escaped items
+/// Matches the text of a constraint, up until the `}` that closes the capture section.
+///
+/// Brackets within the constraint must be balanced, unless they are escaped with a `\`,
+/// so that regex quantifiers like `\d{4}` can be used.
+fn constraint(i: &str) -> IResult<&str, &str, ParseError> {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (index, c) in i.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                return if index == 0 {
+                    Err(nom::Err::Failure(ParseError {
+                        reason: Some(ParserErrorReason::EmptyConstraint),
+                        expected: vec![ExpectedToken::Constraint],
+                        offset: 1,
+                    }))
+                } else {
+                    Ok((&i[index..], &i[..index]))
+                };
+            }
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(nom::Err::Failure(ParseError {
+        reason: None,
+        expected: vec![ExpectedToken::CloseBracket],
+        offset: 1 + i.len(),
+    }))
+}
+
+/// Matches escaped items.
+///
+/// Special characters are escaped by doubling them, so `!!`, `{{`, `}}`, `&&` and `==` match a
+/// literal `!`, `{`, `}`, `&` and `=` respectively.
 fn escaped_item_impl(i: &str) -> IResult<&str, &str> {
-    map(alt((tag("!!"), tag("{{"), tag("}}"))), |s| match s {
-        "!!" => "!",
-        "}}" => "}",
-        "{{" => "{",
-        _ => unreachable!(),
-    })(i)
+    map(
+        alt((tag("!!"), tag("{{"), tag("}}"), tag("&&"), tag("=="))),
+        |s| match s {
+            "!!" => "!",
+            "}}" => "}",
+            "{{" => "{",
+            "&&" => "&",
+            "==" => "=",
+            _ => unreachable!(),
+        },
+    )(i)
 }
 
 /// Matches "".
@@ -142,6 +198,8 @@ pub fn nothing(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
 ///
 /// Due to escaped character parser, the list of special characters MUST contain the characters:
 /// "!{}" within it.
+/// `&` and `=` only need to be escaped where they are special characters,
+/// elsewhere their doubled forms are taken literally.
 fn exact_impl(special_chars: &'static str) -> impl Fn(&str) -> IResult<&str, &str, ParseError> {
     // Detect either an exact ident, or an escaped item.
     // At higher levels, this can be called multiple times in a row,
@@ -165,7 +223,7 @@ fn exact_impl(special_chars: &'static str) -> impl Fn(&str) -> IResult<&str, &st
     }
 }
 
-const SPECIAL_CHARS: &str = r##"/?&#={}!"##;
+const SPECIAL_CHARS: &str = r##"/?&#={}[]!"##;
 const FRAGMENT_SPECIAL_CHARS: &str = r##"{}!"##;
 
 pub fn exact(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
@@ -185,31 +243,6 @@ pub fn capture<'a>(
 }
 
 
-fn capture_single_impl<'a>(
-    field_naming_scheme: FieldNamingScheme,
-) -> impl Fn(&'a str) -> IResult<&'a str, RefCaptureVariant<'a>, ParseError> {
-    move |i: &str| match field_naming_scheme {
-        FieldNamingScheme::Named => delimited(
-            get_open_bracket,
-            named::single_capture_impl,
-            get_close_bracket,
-        )(i),
-        FieldNamingScheme::Unnamed => delimited(
-            get_open_bracket,
-            alt((named::single_capture_impl, unnamed::single_capture_impl)),
-            get_close_bracket,
-        )(i),
-        FieldNamingScheme::Unit => {
-            println!("Unit encountered, erroring in capture single");
-            Err(nom::Err::Failure(ParseError {
-                reason: Some(ParserErrorReason::CapturesInUnit),
-                expected: vec![],
-                offset: 0,
-            }))
-        }
-    }
-}
-
 /// Captures {ident}, {*:ident}, {<number>:ident}
 ///
 /// Depending on the provided field naming, it may also match {}, {*}, and {<number>} for unnamed fields, or none at all for units.
@@ -247,7 +280,13 @@ fn capture_impl<'a>(
 mod named {
     use super::*;
     pub fn single_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
-        map(rust_ident, |key| RefCaptureVariant::Named(key))(i)
+        map(
+            pair(rust_ident, opt(preceded(get_colon, constraint))),
+            |(key, constraint)| RefCaptureVariant::Named {
+                name: key,
+                constraint,
+            },
+        )(i)
     }
 
     pub fn many_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
@@ -288,6 +327,57 @@ mod unnamed {
     }
 }
 
+/// Captures {ident} or {*:ident}, the latter capturing every occurrence of a query parameter.
+///
+/// Depending on the provided field naming, it may also match {} and {*} for unnamed fields.
+fn query_capture_impl<'a>(
+    field_naming_scheme: FieldNamingScheme,
+) -> impl Fn(&'a str) -> IResult<&'a str, RefCaptureVariant<'a>, ParseError> {
+    move |i: &str| match field_naming_scheme {
+        FieldNamingScheme::Named => delimited(
+            get_open_bracket,
+            alt((named::many_capture_impl, named::single_capture_impl)),
+            get_close_bracket,
+        )(i),
+        FieldNamingScheme::Unnamed => delimited(
+            get_open_bracket,
+            alt((
+                named::many_capture_impl,
+                unnamed::many_capture_impl,
+                named::single_capture_impl,
+                unnamed::single_capture_impl,
+            )),
+            get_close_bracket,
+        )(i),
+        FieldNamingScheme::Unit => Err(nom::Err::Failure(ParseError {
+            reason: Some(ParserErrorReason::CapturesInUnit),
+            expected: vec![],
+            offset: 0,
+        })),
+    }
+}
+
+/// Captures {?ident}, which collects the whole query section into one field.
+pub fn query_capture<'a>(
+    field_naming_scheme: FieldNamingScheme,
+) -> impl Fn(&'a str) -> IResult<&'a str, RouteParserToken<'a>, ParseError> {
+    move |i: &str| match field_naming_scheme {
+        FieldNamingScheme::Named | FieldNamingScheme::Unnamed => map(
+            delimited(
+                get_open_bracket,
+                preceded(get_question, rust_ident),
+                get_close_bracket,
+            ),
+            RouteParserToken::QueryCapture,
+        )(i),
+        FieldNamingScheme::Unit => Err(nom::Err::Error(ParseError {
+            reason: Some(ParserErrorReason::CapturesInUnit),
+            expected: vec![],
+            offset: 0,
+        })),
+    }
+}
+
 /// Gets a capture or exact, mapping it to the CaptureOrExact enum - to provide a limited subset.
 fn cap_or_exact<'a>(
     field_naming_scheme: FieldNamingScheme,
@@ -295,7 +385,7 @@ fn cap_or_exact<'a>(
     move |i: &str| {
         alt((
             map(
-                capture_single_impl(field_naming_scheme),
+                query_capture_impl(field_naming_scheme),
                 CaptureOrExact::Capture,
             ),
             map(exact_impl(SPECIAL_CHARS), CaptureOrExact::Exact),
@@ -341,6 +431,21 @@ mod test {
         cap_or_exact(FieldNamingScheme::Named)("{lorem}").expect("Should parse");
     }
 
+    #[test]
+    fn cap_or_exact_match_many_cap() {
+        let x = cap_or_exact(FieldNamingScheme::Named)("{*:lorem}").expect("Should parse");
+        assert_eq!(
+            x.1,
+            CaptureOrExact::Capture(RefCaptureVariant::ManyNamed("lorem"))
+        )
+    }
+
+    #[test]
+    fn query_capture_section() {
+        let x = query_capture(FieldNamingScheme::Named)("{?query}").expect("Should parse");
+        assert_eq!(x.1, RouteParserToken::QueryCapture("query"))
+    }
+
     #[test]
     fn query_section_exact() {
         query(FieldNamingScheme::Named)("lorem=ipsum").expect("should parse");
@@ -359,6 +464,59 @@ mod test {
         query(FieldNamingScheme::Unnamed)("lorem={}").expect("should parse");
     }
 
+    #[test]
+    fn capture_type_constraint() {
+        let (_, token) = capture(FieldNamingScheme::Named)("{id:u32}").expect("should parse");
+        assert_eq!(
+            token,
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "id",
+                constraint: Some("u32")
+            })
+        )
+    }
+
+    #[test]
+    fn capture_regex_constraint_with_brackets() {
+        let (remaining, token) =
+            capture(FieldNamingScheme::Named)(r"{date:\d{4}-\d{2}-\d{2}}/rest")
+                .expect("should parse");
+        assert_eq!(
+            token,
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "date",
+                constraint: Some(r"\d{4}-\d{2}-\d{2}")
+            })
+        );
+        assert_eq!(remaining, "/rest")
+    }
+
+    #[test]
+    fn capture_regex_constraint_with_escaped_bracket() {
+        let (_, token) = capture(FieldNamingScheme::Named)(r"{x:a\}}").expect("should parse");
+        assert_eq!(
+            token,
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "x",
+                constraint: Some(r"a\}")
+            })
+        )
+    }
+
+    #[test]
+    fn capture_empty_constraint_fails() {
+        let mut e = capture(FieldNamingScheme::Named)("{id:}").expect_err("should not parse");
+        assert_eq!(
+            *crate::error::get_reason(&mut e),
+            Some(ParserErrorReason::EmptyConstraint)
+        )
+    }
+
+    #[test]
+    fn capture_unbalanced_constraint_fails() {
+        capture(FieldNamingScheme::Named)("{id:a{2}").expect_err("should not parse");
+    }
+
     #[test]
     fn non_leading_numbers_in_ident() {
         rust_ident("hello5").expect("sholud parse");
@@ -0,0 +1,152 @@
+//! Turns matcher tokens back into a matcher string.
+use crate::{CaptureConstraint, CaptureVariant, MatcherToken, QueryParameter, QueryValue};
+use std::fmt::{self, Write};
+
+/// Characters that have to be doubled to appear in a literal in the path section.
+const ESCAPED_CHARS: &str = "!{}&=";
+/// Characters that have to be doubled to appear in a literal in the fragment section.
+const FRAGMENT_ESCAPED_CHARS: &str = "!{}";
+
+/// Writes the canonical matcher string that the tokens were parsed from.
+///
+/// Parsing the string again with `parse_str_and_optimize_tokens` produces the same tokens,
+/// as long as they were produced by parsing a matcher string.
+/// The `optional` flag of query parameters can't be written in a matcher string,
+/// so it is lost.
+pub fn matcher_string(tokens: &[MatcherToken]) -> String {
+    let mut string = String::new();
+    let mut in_fragment = false;
+    write_tokens(&mut string, tokens, &mut in_fragment)
+        .expect("writing to a String should not fail");
+    string
+}
+
+fn write_tokens(w: &mut String, tokens: &[MatcherToken], in_fragment: &mut bool) -> fmt::Result {
+    for token in tokens {
+        match token {
+            MatcherToken::Exact(literal) => {
+                for c in literal.chars() {
+                    if !*in_fragment && c == '#' {
+                        *in_fragment = true;
+                        w.push(c);
+                    } else if *in_fragment {
+                        write_char(w, c, FRAGMENT_ESCAPED_CHARS);
+                    } else {
+                        write_char(w, c, ESCAPED_CHARS);
+                    }
+                }
+            }
+            MatcherToken::Capture(capture) => write_capture(w, capture)?,
+            MatcherToken::Optional(tokens) => {
+                w.push('[');
+                write_tokens(w, tokens, in_fragment)?;
+                w.push(']');
+            }
+            MatcherToken::Query {
+                parameters, rest, ..
+            } => {
+                if parameters.is_empty() && rest.is_none() {
+                    w.push('?');
+                }
+                for (index, QueryParameter { key, value, .. }) in parameters.iter().enumerate() {
+                    w.push(if index == 0 { '?' } else { '&' });
+                    // Query keys and values can't contain special characters, escaped or not.
+                    w.push_str(key);
+                    w.push('=');
+                    match value {
+                        QueryValue::Exact(literal) => w.push_str(literal),
+                        QueryValue::Capture(capture) => write_capture(w, capture)?,
+                    }
+                }
+                if let Some(rest) = rest {
+                    write!(w, "{{?{}}}", rest)?;
+                }
+            }
+            // A `!` directly after the query section sets `deny_unknown` on it.
+            MatcherToken::End => w.push('!'),
+        }
+    }
+    Ok(())
+}
+
+/// Writes the character, doubling it if it is one of the `escaped_chars`.
+fn write_char(w: &mut String, c: char, escaped_chars: &str) {
+    if escaped_chars.contains(c) {
+        w.push(c);
+    }
+    w.push(c);
+}
+
+fn write_capture(w: &mut String, capture: &CaptureVariant) -> fmt::Result {
+    match capture {
+        CaptureVariant::Unnamed => w.write_str("{}"),
+        CaptureVariant::ManyUnnamed => w.write_str("{*}"),
+        CaptureVariant::NumberedUnnamed { sections } => write!(w, "{{{}}}", sections),
+        CaptureVariant::Named {
+            name,
+            constraint: None,
+        } => write!(w, "{{{}}}", name),
+        CaptureVariant::Named {
+            name,
+            constraint: Some(CaptureConstraint::Type(ty)),
+        } => write!(w, "{{{}:{}}}", name, ty.name()),
+        CaptureVariant::Named {
+            name,
+            constraint: Some(CaptureConstraint::Regex(pattern)),
+        } => write!(w, "{{{}:{}}}", name, pattern),
+        CaptureVariant::ManyNamed(name) => write!(w, "{{*:{}}}", name),
+        CaptureVariant::NumberedNamed { sections, name } => {
+            write!(w, "{{{}:{}}}", sections, name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_str_and_optimize_tokens, FieldNamingScheme};
+
+    fn round_trip(matcher: &str) -> String {
+        let tokens = parse_str_and_optimize_tokens(matcher, FieldNamingScheme::Unnamed)
+            .expect("Should parse");
+        let string = matcher_string(&tokens);
+        let reparsed = parse_str_and_optimize_tokens(&string, FieldNamingScheme::Unnamed)
+            .expect("Should parse again");
+        assert_eq!(tokens, reparsed);
+        string
+    }
+
+    #[test]
+    fn matcher_strings_round_trip() {
+        let matchers = [
+            "/",
+            "/lorem/{ipsum}/{*:dolor}/{3:sit}",
+            "/{}/{*}/{2}",
+            r"/post/{date:\d{4}-\d{2}-\d{2}}/{id:u32}",
+            "/posts[/page/{n}[/{m}]]!",
+            "/search?q={query}&sort=asc!",
+            "/search?q={*:query}",
+            "/items{?filter}",
+            "/a#fragment/{b}",
+            "/files/{stem}.{ext}",
+        ];
+        for matcher in matchers.iter() {
+            assert_eq!(&round_trip(matcher), matcher);
+        }
+    }
+
+    #[test]
+    fn literals_are_escaped() {
+        assert_eq!(round_trip("/wow!!/{{x}}&&y==z!"), "/wow!!/{{x}}&&y==z!");
+    }
+
+    #[test]
+    fn fragment_literals_are_escaped() {
+        assert_eq!(round_trip("#a!!b&c=d{{e}}"), "#a!!b&c=d{{e}}");
+    }
+
+    #[test]
+    fn query_continuation_is_canonical() {
+        assert_eq!(round_trip("&lorem=ipsum"), "?lorem=ipsum");
+    }
+}
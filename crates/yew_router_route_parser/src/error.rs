@@ -17,6 +17,13 @@ fn offset(input: &str, substring: &str) -> usize {
     input.len() - substring.len()
 }
 
+impl<'a> PrettyParseError<'a> {
+    /// The byte offset within the input at which the error occurred.
+    pub fn position(&self) -> usize {
+        offset(self.input, self.remaining) + self.error.offset
+    }
+}
+
 impl<'a> fmt::Debug for PrettyParseError<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("Could not parse route.")?;
@@ -27,9 +34,7 @@ impl<'a> fmt::Debug for PrettyParseError<'a> {
         f.write_str(self.input)?;
         f.write_str("\n")?;
 
-        let offset = offset(self.input, self.remaining);
-        let offset = offset + self.error.offset;
-        let pad = (0..offset + route_str.len())
+        let pad = (0..self.position() + route_str.len())
             .map(|_| '-')
             .collect::<String>();
         f.write_str(&format!("{}^", pad))?;
@@ -128,12 +133,18 @@ pub enum ExpectedToken {
     OpenBracket,
     /// }
     CloseBracket,
+    /// [
+    OptionalBegin,
+    /// ]
+    OptionalEnd,
     /// =
     Equals,
     /// *
     Star,
     /// :
     Colon,
+    /// type or regular expression after a :
+    Constraint,
 }
 
 impl fmt::Display for ExpectedToken {
@@ -148,9 +159,12 @@ impl fmt::Display for ExpectedToken {
             ExpectedToken::Ident => f.write_str("<ident>"),
             ExpectedToken::OpenBracket => f.write_str("{"),
             ExpectedToken::CloseBracket => f.write_str("}"),
+            ExpectedToken::OptionalBegin => f.write_str("["),
+            ExpectedToken::OptionalEnd => f.write_str("]"),
             ExpectedToken::Equals => f.write_str("="),
             ExpectedToken::Star => f.write_str("*"),
             ExpectedToken::Colon => f.write_str(":"),
+            ExpectedToken::Constraint => f.write_str("<constraint>"),
         }
     }
 }
@@ -178,6 +192,12 @@ pub enum ParserErrorReason {
     InvalidState,
     /// Can't have capture sections for unit structs/variants
     CapturesInUnit,
+    /// A : within a capture section must be followed by a constraint
+    EmptyConstraint,
+    /// An optional section was opened with a [, but never closed with a ]
+    UnclosedOptional,
+    /// A ] appeared without a [ before it
+    UnopenedOptional,
     /// Internal check on valid state transitions
     /// This should never actually be created.
     NotAllowedStateTransition,
@@ -227,6 +247,19 @@ impl fmt::Display for ParserErrorReason {
             ParserErrorReason::CapturesInUnit => {
                 f.write_str("Cannot have a capture section for a unit struct or variant.")?;
             }
+            ParserErrorReason::UnclosedOptional => {
+                f.write_str(
+                    "An optional section ([) must be closed (]) before the end of the path.",
+                )?;
+            }
+            ParserErrorReason::UnopenedOptional => {
+                f.write_str(
+                    "A ']' can only close an optional section that was opened with a '['.",
+                )?;
+            }
+            ParserErrorReason::EmptyConstraint => {
+                f.write_str("A ':' within a capture section must be followed by a type or a regular expression.")?;
+            }
         }
         Ok(())
     }
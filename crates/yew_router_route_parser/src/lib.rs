@@ -14,9 +14,11 @@
 )]
 
 mod core;
+mod display;
 mod error;
 pub mod parser;
 pub use crate::core::FieldNamingScheme;
+pub use display::matcher_string;
 pub use error::{ParseError, PrettyParseError};
 mod optimizer;
 pub use optimizer::{convert_tokens, parse_str_and_optimize_tokens};
@@ -30,11 +32,30 @@ pub type Captures<'a> = HashMap<&'a str, String>;
 
 /// Tokens used to determine how to match and capture sections from a URL.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MatcherToken {
     /// Section-related tokens can be condensed into a match.
     Exact(String),
     /// Capture section.
     Capture(CaptureVariant),
+    /// A sequence of tokens that is matched if possible, and skipped otherwise.
+    ///
+    /// Created from a `[...]` section of the matcher string.
+    Optional(Vec<MatcherToken>),
+    /// The query section, whose parameters are matched by key, regardless of the order they appear in.
+    Query {
+        /// Parameters that are matched.
+        parameters: Vec<QueryParameter>,
+        /// If parameters that aren't in `parameters` cause the match to fail,
+        /// instead of being ignored.
+        ///
+        /// This is set when a `!` immediately follows the query section of the matcher string.
+        deny_unknown: bool,
+        /// The name that the parameters that aren't in `parameters` are captured with.
+        ///
+        /// This is set by a `{?name}` query section.
+        rest: Option<String>,
+    },
     /// End token - if the string hasn't been consumed entirely, then the parse will fail.
     /// This is useful for being able to specify more general matchers for variants that would
     /// otherwise match above more specific variants.
@@ -43,6 +64,7 @@ pub enum MatcherToken {
 
 /// Variants that indicate how part of a string should be captured.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CaptureVariant {
     /// {}
     Unnamed,
@@ -54,7 +76,14 @@ pub enum CaptureVariant {
         sections: usize,
     },
     /// {name} - captures a section and adds it to the map with a given name.
-    Named(String),
+    ///
+    /// {name:constraint} - additionally requires the captured section to satisfy a constraint.
+    Named {
+        /// The key to be entered in the `Matches` map.
+        name: String,
+        /// Restriction on what the section is allowed to contain.
+        constraint: Option<CaptureConstraint>,
+    },
     /// {*:name} - captures over many sections and adds it to the map with a given name.
     ManyNamed(String),
     /// {2:name} - captures a fixed number of sections with a given name.
@@ -65,3 +94,164 @@ pub enum CaptureVariant {
         name: String,
     },
 }
+
+/// A `key=value` pair within the query section.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryParameter {
+    /// The key of the parameter.
+    pub key: String,
+    /// How the value of the parameter is matched.
+    pub value: QueryValue,
+    /// If the match may succeed without this parameter being present.
+    ///
+    /// This only applies to captures, and can't be specified in the matcher string.
+    pub optional: bool,
+}
+
+/// The value of a query parameter.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueryValue {
+    /// Match a specific string.
+    Exact(String),
+    /// Capture the value.
+    Capture(CaptureVariant),
+}
+
+/// Restricts what a named capture section is allowed to match.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptureConstraint {
+    /// {name:u32} - the captured section must parse as the given primitive type.
+    Type(PrimitiveType),
+    /// {name:[a-z]+} - the captured section must be matched in its entirety by the regular expression.
+    Regex(String),
+}
+
+impl CaptureConstraint {
+    /// Interprets the text after the `:` in a capture section.
+    ///
+    /// Names of primitive types produce a `Type` constraint, anything else is a `Regex`.
+    pub fn new(constraint: &str) -> Self {
+        PrimitiveType::from_name(constraint)
+            .map(CaptureConstraint::Type)
+            .unwrap_or_else(|| CaptureConstraint::Regex(constraint.to_string()))
+    }
+}
+
+/// Primitive types that can be used as a capture constraint.
+#[allow(missing_docs)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrimitiveType {
+    Bool,
+    Char,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    F32,
+    F64,
+}
+
+impl PrimitiveType {
+    /// All primitive types.
+    pub const ALL: [PrimitiveType; 16] = [
+        PrimitiveType::Bool,
+        PrimitiveType::Char,
+        PrimitiveType::U8,
+        PrimitiveType::U16,
+        PrimitiveType::U32,
+        PrimitiveType::U64,
+        PrimitiveType::U128,
+        PrimitiveType::Usize,
+        PrimitiveType::I8,
+        PrimitiveType::I16,
+        PrimitiveType::I32,
+        PrimitiveType::I64,
+        PrimitiveType::I128,
+        PrimitiveType::Isize,
+        PrimitiveType::F32,
+        PrimitiveType::F64,
+    ];
+
+    /// Gets the type corresponding to a Rust type name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|ty| ty.name() == name)
+    }
+
+    /// The Rust name of the type.
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::Char => "char",
+            PrimitiveType::U8 => "u8",
+            PrimitiveType::U16 => "u16",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::U128 => "u128",
+            PrimitiveType::Usize => "usize",
+            PrimitiveType::I8 => "i8",
+            PrimitiveType::I16 => "i16",
+            PrimitiveType::I32 => "i32",
+            PrimitiveType::I64 => "i64",
+            PrimitiveType::I128 => "i128",
+            PrimitiveType::Isize => "isize",
+            PrimitiveType::F32 => "f32",
+            PrimitiveType::F64 => "f64",
+        }
+    }
+
+    /// Determines if the string can be parsed as this type.
+    pub fn accepts(self, value: &str) -> bool {
+        use std::str::FromStr;
+        fn parses<T: FromStr>(value: &str) -> bool {
+            T::from_str(value).is_ok()
+        }
+        match self {
+            PrimitiveType::Bool => parses::<bool>(value),
+            PrimitiveType::Char => parses::<char>(value),
+            PrimitiveType::U8 => parses::<u8>(value),
+            PrimitiveType::U16 => parses::<u16>(value),
+            PrimitiveType::U32 => parses::<u32>(value),
+            PrimitiveType::U64 => parses::<u64>(value),
+            PrimitiveType::U128 => parses::<u128>(value),
+            PrimitiveType::Usize => parses::<usize>(value),
+            PrimitiveType::I8 => parses::<i8>(value),
+            PrimitiveType::I16 => parses::<i16>(value),
+            PrimitiveType::I32 => parses::<i32>(value),
+            PrimitiveType::I64 => parses::<i64>(value),
+            PrimitiveType::I128 => parses::<i128>(value),
+            PrimitiveType::Isize => parses::<isize>(value),
+            PrimitiveType::F32 => parses::<f32>(value),
+            PrimitiveType::F64 => parses::<f64>(value),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_round_trip_through_json() {
+        let tokens = parse_str_and_optimize_tokens(
+            "/lorem/{id:u32}[/{*:ipsum}]?q={q}&x=y#dolor!",
+            FieldNamingScheme::Named,
+        )
+        .expect("Should parse");
+        let json = serde_json::to_string(&tokens).expect("Should serialize");
+        let deserialized: Vec<MatcherToken> =
+            serde_json::from_str(&json).expect("Should deserialize");
+        assert_eq!(tokens, deserialized);
+    }
+}
@@ -3,12 +3,18 @@ use crate::{
     parser::{parse, CaptureOrExact, RefCaptureVariant, RouteParserToken},
 };
 
-use crate::{core::FieldNamingScheme, CaptureVariant, MatcherToken};
+use crate::{
+    core::FieldNamingScheme, CaptureConstraint, CaptureVariant, MatcherToken, QueryParameter,
+    QueryValue,
+};
 
 impl<'a> From<RefCaptureVariant<'a>> for CaptureVariant {
     fn from(v: RefCaptureVariant<'a>) -> Self {
         match v {
-            RefCaptureVariant::Named(s) => CaptureVariant::Named(s.to_string()),
+            RefCaptureVariant::Named { name, constraint } => CaptureVariant::Named {
+                name: name.to_string(),
+                constraint: constraint.map(CaptureConstraint::new),
+            },
             RefCaptureVariant::ManyNamed(s) => CaptureVariant::ManyNamed(s.to_string()),
             RefCaptureVariant::NumberedNamed { sections, name } => CaptureVariant::NumberedNamed {
                 sections,
@@ -32,6 +38,15 @@ impl<'a> From<CaptureOrExact<'a>> for MatcherToken {
     }
 }
 
+impl<'a> From<CaptureOrExact<'a>> for QueryValue {
+    fn from(value: CaptureOrExact<'a>) -> Self {
+        match value {
+            CaptureOrExact::Exact(m) => QueryValue::Exact(m.to_string()),
+            CaptureOrExact::Capture(v) => QueryValue::Capture(v.into()),
+        }
+    }
+}
+
 impl<'a> RouteParserToken<'a> {
     fn as_str(&self) -> &str {
         match self {
@@ -42,6 +57,9 @@ impl<'a> RouteParserToken<'a> {
             RouteParserToken::FragmentBegin => "#",
             RouteParserToken::Nothing
             | RouteParserToken::Capture { .. }
+            | RouteParserToken::QueryCapture(_)
+            | RouteParserToken::OptionalBegin
+            | RouteParserToken::OptionalEnd
             | RouteParserToken::Query { .. }
             | RouteParserToken::End => unreachable!(),
         }
@@ -61,9 +79,16 @@ pub fn parse_str_and_optimize_tokens(
 ///
 /// In the process of converting the tokens, this function will condense multiple RouteParserTokens
 /// that represent literals into one Exact variant if multiple reducible tokens happen to occur in a row.
+///
+/// Tokens between an OptionalBegin and its OptionalEnd are collected into a nested Optional variant.
+/// Literals are never condensed across the boundaries of an optional section.
+///
+/// The whole query section is collected into one Query variant, including a `{?name}` section.
 pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
     let mut new_tokens: Vec<MatcherToken> = vec![];
     let mut run: Vec<RouteParserToken> = vec![];
+    // The tokens of the sections enclosing the optional section currently being converted.
+    let mut enclosing: Vec<Vec<MatcherToken>> = vec![];
 
     fn empty_run(run: &mut Vec<RouteParserToken>) -> Option<MatcherToken> {
         let segment = run.iter().map(RouteParserToken::as_str).collect::<String>();
@@ -76,28 +101,36 @@ pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
         }
     }
 
-    fn empty_run_with_query_cap_at_end(
-        run: &mut Vec<RouteParserToken>,
-        query_lhs: &str,
-    ) -> MatcherToken {
-        let segment = run
-            .iter()
-            .map(RouteParserToken::as_str)
-            .chain(Some(query_lhs))
-            .chain(Some("="))
-            .collect::<String>();
-        run.clear();
-
-        MatcherToken::Exact(segment)
-    }
-
     for token in tokens.iter() {
         match token {
-            RouteParserToken::QueryBegin
-            | RouteParserToken::FragmentBegin
+            RouteParserToken::FragmentBegin
             | RouteParserToken::Separator
-            | RouteParserToken::QuerySeparator
             | RouteParserToken::Exact(_) => run.push(*token),
+            // The separators are implied by the Query variant.
+            // A matcher string may begin with a `&` in order to match the remainder of a query.
+            RouteParserToken::QueryBegin | RouteParserToken::QuerySeparator => {
+                if let Some(MatcherToken::Query { .. }) = new_tokens.last() {
+                } else {
+                    if let Some(current_run) = empty_run(&mut run) {
+                        new_tokens.push(current_run);
+                    }
+                    new_tokens.push(MatcherToken::Query {
+                        parameters: vec![],
+                        deny_unknown: false,
+                        rest: None,
+                    })
+                }
+            }
+            RouteParserToken::QueryCapture(name) => {
+                if let Some(current_run) = empty_run(&mut run) {
+                    new_tokens.push(current_run);
+                }
+                new_tokens.push(MatcherToken::Query {
+                    parameters: vec![],
+                    deny_unknown: false,
+                    rest: Some(name.to_string()),
+                })
+            }
             RouteParserToken::Capture(cap) => {
                 if let Some(current_run) = empty_run(&mut run) {
                     new_tokens.push(current_run);
@@ -107,21 +140,42 @@ pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
             RouteParserToken::Query {
                 ident,
                 capture_or_exact,
-            } => match capture_or_exact {
-                CaptureOrExact::Exact(s) => {
-                    run.push(RouteParserToken::Exact(ident));
-                    run.push(RouteParserToken::Exact("="));
-                    run.push(RouteParserToken::Exact(s));
+            } => {
+                if let Some(MatcherToken::Query { parameters, .. }) = new_tokens.last_mut() {
+                    parameters.push(QueryParameter {
+                        key: ident.to_string(),
+                        value: QueryValue::from(*capture_or_exact),
+                        optional: false,
+                    })
                 }
-                CaptureOrExact::Capture(cap) => {
-                    new_tokens.push(empty_run_with_query_cap_at_end(&mut run, *ident));
-                    new_tokens.push(MatcherToken::Capture(CaptureVariant::from(*cap)))
+            }
+            RouteParserToken::OptionalBegin => {
+                if let Some(current_run) = empty_run(&mut run) {
+                    new_tokens.push(current_run);
                 }
-            },
+                enclosing.push(std::mem::replace(&mut new_tokens, vec![]));
+            }
+            RouteParserToken::OptionalEnd => {
+                if let Some(current_run) = empty_run(&mut run) {
+                    new_tokens.push(current_run);
+                }
+                let optional = MatcherToken::Optional(new_tokens);
+                new_tokens = enclosing.pop().expect("Unbalanced optional sections");
+                new_tokens.push(optional);
+            }
             RouteParserToken::End => {
                 if let Some(current_run) = empty_run(&mut run) {
                     new_tokens.push(current_run);
                 }
+                // Unknown parameters are captured when the query section has a rest.
+                if let Some(MatcherToken::Query {
+                    deny_unknown,
+                    rest: None,
+                    ..
+                }) = new_tokens.last_mut()
+                {
+                    *deny_unknown = true;
+                }
                 new_tokens.push(MatcherToken::End);
             }
             RouteParserToken::Nothing => {}
@@ -148,4 +202,99 @@ mod tests {
         let tokens = parse_str_and_optimize_tokens("", FieldNamingScheme::Unit).unwrap();
         assert_eq!(tokens, vec![])
     }
+
+    #[test]
+    fn escaped_literals_are_condensed() {
+        let tokens =
+            parse_str_and_optimize_tokens("/wow!!/{{a}}&&b==c", FieldNamingScheme::Unit).unwrap();
+        assert_eq!(
+            tokens,
+            vec![MatcherToken::Exact("/wow!/{a}&b=c".to_string())]
+        )
+    }
+
+    #[test]
+    fn query_section_is_collected() {
+        let tokens =
+            parse_str_and_optimize_tokens("/a?b=c&d={d}!", FieldNamingScheme::Named).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                MatcherToken::Exact("/a".to_string()),
+                MatcherToken::Query {
+                    parameters: vec![
+                        QueryParameter {
+                            key: "b".to_string(),
+                            value: QueryValue::Exact("c".to_string()),
+                            optional: false,
+                        },
+                        QueryParameter {
+                            key: "d".to_string(),
+                            value: QueryValue::Capture(CaptureVariant::Named {
+                                name: "d".to_string(),
+                                constraint: None
+                            }),
+                            optional: false,
+                        },
+                    ],
+                    deny_unknown: true,
+                    rest: None,
+                },
+                MatcherToken::End,
+            ]
+        )
+    }
+
+    #[test]
+    fn query_capture_is_rest_of_query_section() {
+        let tokens =
+            parse_str_and_optimize_tokens("/a{?query}!", FieldNamingScheme::Named).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                MatcherToken::Exact("/a".to_string()),
+                MatcherToken::Query {
+                    parameters: vec![],
+                    deny_unknown: false,
+                    rest: Some("query".to_string()),
+                },
+                MatcherToken::End,
+            ]
+        )
+    }
+
+    #[test]
+    fn optional_section_is_nested() {
+        let tokens =
+            parse_str_and_optimize_tokens("/posts[/page/{n}]/x", FieldNamingScheme::Named).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                MatcherToken::Exact("/posts".to_string()),
+                MatcherToken::Optional(vec![
+                    MatcherToken::Exact("/page/".to_string()),
+                    MatcherToken::Capture(CaptureVariant::Named {
+                        name: "n".to_string(),
+                        constraint: None
+                    }),
+                ]),
+                MatcherToken::Exact("/x".to_string()),
+            ]
+        )
+    }
+
+    #[test]
+    fn nested_optional_sections() {
+        let tokens = parse_str_and_optimize_tokens("/a[/b[/c]]", FieldNamingScheme::Unit).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                MatcherToken::Exact("/a".to_string()),
+                MatcherToken::Optional(vec![
+                    MatcherToken::Exact("/b".to_string()),
+                    MatcherToken::Optional(vec![MatcherToken::Exact("/c".to_string())]),
+                ]),
+            ]
+        )
+    }
 }
@@ -1,14 +1,13 @@
 //! Parser that consumes a string and produces the first representation of the matcher.
 use crate::{
     core::{
-        capture, exact, fragment_exact, get_and, get_end, get_hash, get_question, get_slash,
-        nothing, query,
+        capture, exact, fragment_exact, get_and, get_close_optional, get_end, get_hash,
+        get_open_optional, get_question, get_slash, nothing, query, query_capture,
     },
     error::{get_reason, ParseError, ParserErrorReason, PrettyParseError},
     FieldNamingScheme,
 };
 use nom::{branch::alt, IResult};
-// use crate::core::escaped_item;
 
 /// Tokens generated from parsing a route matcher string.
 /// They will be optimized to another token type that is used to match URLs.
@@ -22,6 +21,10 @@ pub enum RouteParserToken<'a> {
     Exact(&'a str),
     /// Match {_}. See `RefCaptureVariant` for more.
     Capture(RefCaptureVariant<'a>),
+    /// Match [ - begins a section of the path that may be absent.
+    OptionalBegin,
+    /// Match ] - ends a section of the path that may be absent.
+    OptionalEnd,
     /// Match ?
     QueryBegin,
     /// Match &
@@ -33,6 +36,8 @@ pub enum RouteParserToken<'a> {
         /// Capture or match
         capture_or_exact: CaptureOrExact<'a>,
     },
+    /// Match {?name} - captures the whole query section with the given name.
+    QueryCapture(&'a str),
     /// Match \#
     FragmentBegin,
     /// Match !
@@ -58,7 +63,14 @@ pub enum RefCaptureVariant<'a> {
         sections: usize,
     },
     /// {name} - captures a section and adds it to the map with a given name.
-    Named(&'a str),
+    ///
+    /// {name:constraint} - additionally requires the captured section to satisfy a constraint.
+    Named {
+        /// The key to be entered in the `Matches` map.
+        name: &'a str,
+        /// The unparsed text of the constraint.
+        constraint: Option<&'a str>,
+    },
     /// {*:name} - captures over many sections and adds it to the map with a given name.
     ManyNamed(&'a str),
     /// {2:name} - captures a fixed number of sections with a given name.
@@ -101,8 +113,12 @@ impl<'a> ParserState<'a> {
             ParserState::None => match token {
                 RouteParserToken::Separator
                 | RouteParserToken::Exact(_)
-                | RouteParserToken::Capture(_) => Ok(ParserState::Path { prev_token: token }),
-                RouteParserToken::QueryBegin => Ok(ParserState::FirstQuery { prev_token: token }),
+                | RouteParserToken::Capture(_)
+                | RouteParserToken::OptionalBegin => Ok(ParserState::Path { prev_token: token }),
+                RouteParserToken::OptionalEnd => Err(ParserErrorReason::NotAllowedStateTransition),
+                RouteParserToken::QueryBegin | RouteParserToken::QueryCapture(_) => {
+                    Ok(ParserState::FirstQuery { prev_token: token })
+                }
                 RouteParserToken::QuerySeparator => Ok(ParserState::NthQuery { prev_token: token }),
                 RouteParserToken::Query { .. } => Err(ParserErrorReason::NotAllowedStateTransition),
                 RouteParserToken::FragmentBegin => Ok(ParserState::Fragment { prev_token: token }),
@@ -111,10 +127,13 @@ impl<'a> ParserState<'a> {
             ParserState::Path { prev_token } => {
                 match prev_token {
                     RouteParserToken::Separator => match token {
-                        RouteParserToken::Exact(_) | RouteParserToken::Capture(_) => {
+                        RouteParserToken::Exact(_)
+                        | RouteParserToken::Capture(_)
+                        | RouteParserToken::OptionalBegin
+                        | RouteParserToken::OptionalEnd => {
                             Ok(ParserState::Path { prev_token: token })
                         }
-                        RouteParserToken::QueryBegin => {
+                        RouteParserToken::QueryBegin | RouteParserToken::QueryCapture(_) => {
                             Ok(ParserState::FirstQuery { prev_token: token })
                         }
                         RouteParserToken::FragmentBegin => {
@@ -126,10 +145,12 @@ impl<'a> ParserState<'a> {
                     RouteParserToken::Exact(_) => match token {
                         RouteParserToken::Exact(_)
                         | RouteParserToken::Separator
-                        | RouteParserToken::Capture(_) => {
+                        | RouteParserToken::Capture(_)
+                        | RouteParserToken::OptionalBegin
+                        | RouteParserToken::OptionalEnd => {
                             Ok(ParserState::Path { prev_token: token })
                         }
-                        RouteParserToken::QueryBegin => {
+                        RouteParserToken::QueryBegin | RouteParserToken::QueryCapture(_) => {
                             Ok(ParserState::FirstQuery { prev_token: token })
                         }
                         RouteParserToken::FragmentBegin => {
@@ -139,10 +160,37 @@ impl<'a> ParserState<'a> {
                         _ => Err(ParserErrorReason::NotAllowedStateTransition),
                     },
                     RouteParserToken::Capture(_) => match token {
-                        RouteParserToken::Separator | RouteParserToken::Exact(_) => {
+                        RouteParserToken::Separator
+                        | RouteParserToken::Exact(_)
+                        | RouteParserToken::OptionalBegin
+                        | RouteParserToken::OptionalEnd => {
+                            Ok(ParserState::Path { prev_token: token })
+                        }
+                        RouteParserToken::QueryBegin | RouteParserToken::QueryCapture(_) => {
+                            Ok(ParserState::FirstQuery { prev_token: token })
+                        }
+                        RouteParserToken::FragmentBegin => {
+                            Ok(ParserState::Fragment { prev_token: token })
+                        }
+                        RouteParserToken::End => Ok(ParserState::End),
+                        _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                    },
+                    RouteParserToken::OptionalBegin => match token {
+                        RouteParserToken::Separator
+                        | RouteParserToken::Exact(_)
+                        | RouteParserToken::OptionalBegin => {
+                            Ok(ParserState::Path { prev_token: token })
+                        }
+                        _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                    },
+                    RouteParserToken::OptionalEnd => match token {
+                        RouteParserToken::Separator
+                        | RouteParserToken::Exact(_)
+                        | RouteParserToken::OptionalBegin
+                        | RouteParserToken::OptionalEnd => {
                             Ok(ParserState::Path { prev_token: token })
                         }
-                        RouteParserToken::QueryBegin => {
+                        RouteParserToken::QueryBegin | RouteParserToken::QueryCapture(_) => {
                             Ok(ParserState::FirstQuery { prev_token: token })
                         }
                         RouteParserToken::FragmentBegin => {
@@ -172,6 +220,13 @@ impl<'a> ParserState<'a> {
                     RouteParserToken::End => Ok(ParserState::End),
                     _ => Err(ParserErrorReason::NotAllowedStateTransition),
                 },
+                RouteParserToken::QueryCapture(_) => match token {
+                    RouteParserToken::FragmentBegin => {
+                        Ok(ParserState::Fragment { prev_token: token })
+                    }
+                    RouteParserToken::End => Ok(ParserState::End),
+                    _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                },
                 _ => Err(ParserErrorReason::InvalidState),
             },
             ParserState::NthQuery { prev_token } => match prev_token {
@@ -213,6 +268,9 @@ impl<'a> ParserState<'a> {
 /// due to the fact that erroneous tokens can't be fed into the transition function.
 ///
 /// This continues until the string is exhausted, or none of the parsers for the current state can parse the current input.
+///
+/// The state machine only knows about the previous token, so the nesting depth of optional sections
+/// is tracked alongside it, in order to ensure that every `[` is closed by a `]` before the path ends.
 pub fn parse(
     mut i: &str,
     field_naming_scheme: FieldNamingScheme,
@@ -220,6 +278,17 @@ pub fn parse(
     let input = i;
     let mut tokens: Vec<RouteParserToken> = vec![];
     let mut state = ParserState::None;
+    let mut optional_depth: usize = 0;
+
+    let optional_error = |reason: ParserErrorReason, remaining| PrettyParseError {
+        error: ParseError {
+            reason: Some(reason),
+            expected: vec![],
+            offset: 0,
+        },
+        input,
+        remaining,
+    };
 
     loop {
         let (ii, token) = parse_impl(i, &state, field_naming_scheme).map_err(|e| match e {
@@ -230,6 +299,23 @@ pub fn parse(
             },
             _ => panic!("parser should not be incomplete"),
         })?;
+        match token {
+            RouteParserToken::OptionalBegin => optional_depth += 1,
+            RouteParserToken::OptionalEnd if optional_depth == 0 => {
+                return Err(optional_error(ParserErrorReason::UnopenedOptional, i));
+            }
+            RouteParserToken::OptionalEnd => optional_depth -= 1,
+            RouteParserToken::QueryBegin
+            | RouteParserToken::QuerySeparator
+            | RouteParserToken::QueryCapture(_)
+            | RouteParserToken::FragmentBegin
+            | RouteParserToken::End
+                if optional_depth > 0 =>
+            {
+                return Err(optional_error(ParserErrorReason::UnclosedOptional, i));
+            }
+            _ => {}
+        }
         i = ii;
         state = state.transition(token.clone()).map_err(|reason| {
             let error = ParseError {
@@ -250,6 +336,9 @@ pub fn parse(
             break;
         }
     }
+    if optional_depth > 0 {
+        return Err(optional_error(ParserErrorReason::UnclosedOptional, i));
+    }
     Ok(tokens)
 }
 
@@ -262,10 +351,12 @@ fn parse_impl<'a>(
         ParserState::None => alt((
             get_slash,
             get_question,
+            exact, // Escaped items come before captures and query separators, so `{{` and `&&` work.
             get_and,
             get_hash,
+            query_capture(field_naming_scheme),
             capture(field_naming_scheme),
-            exact,
+            get_open_optional,
             get_end,
             nothing,
         ))(i),
@@ -273,7 +364,10 @@ fn parse_impl<'a>(
             RouteParserToken::Separator => {
                 alt((
                     exact,
+                    query_capture(field_naming_scheme),
                     capture(field_naming_scheme),
+                    get_open_optional,
+                    get_close_optional,
                     get_question,
                     get_hash,
                     get_end,
@@ -293,7 +387,10 @@ fn parse_impl<'a>(
                 alt((
                     get_slash,
                     exact, // This will handle escaped items
+                    query_capture(field_naming_scheme),
                     capture(field_naming_scheme),
+                    get_open_optional,
+                    get_close_optional,
                     get_question,
                     get_hash,
                     get_end,
@@ -309,19 +406,62 @@ fn parse_impl<'a>(
                 })
             }
             RouteParserToken::Capture(_) => {
-                alt((get_slash, exact, get_question, get_hash, get_end))(i).map_err(
+                alt((
+                    get_slash,
+                    exact,
+                    get_open_optional,
+                    get_close_optional,
+                    get_question,
+                    query_capture(field_naming_scheme),
+                    get_hash,
+                    get_end,
+                ))(i)
+                .map_err(|mut e: nom::Err<ParseError>| {
+                    // Detect likely failures if the above failed to match.
+                    let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
+                    *reason = capture(field_naming_scheme)(i)
+                        .map(|_| ParserErrorReason::AdjacentCaptures)
+                        .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
+                        .ok()
+                        .or(*reason);
+                    e
+                })
+            }
+            RouteParserToken::OptionalBegin => {
+                alt((get_slash, exact, get_open_optional))(i).map_err(
                     |mut e: nom::Err<ParseError>| {
-                        // Detect likely failures if the above failed to match.
+                        // Optional sections can't begin with a capture, as it could end up next to
+                        // a capture preceding the optional section.
                         let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
                         *reason = capture(field_naming_scheme)(i)
                             .map(|_| ParserErrorReason::AdjacentCaptures)
-                            .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
                             .ok()
                             .or(*reason);
                         e
                     },
                 )
             }
+            RouteParserToken::OptionalEnd => {
+                alt((
+                    get_slash,
+                    exact,
+                    get_open_optional,
+                    get_close_optional,
+                    get_question,
+                    query_capture(field_naming_scheme),
+                    get_hash,
+                    get_end,
+                ))(i)
+                .map_err(|mut e: nom::Err<ParseError>| {
+                    // Detect likely failures if the above failed to match.
+                    let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
+                    *reason = capture(field_naming_scheme)(i)
+                        .map(|_| ParserErrorReason::AdjacentCaptures)
+                        .ok()
+                        .or(*reason);
+                    e
+                })
+            }
             _ => Err(nom::Err::Failure(ParseError {
                 reason: Some(ParserErrorReason::InvalidState),
                 expected: vec![],
@@ -351,6 +491,7 @@ fn parse_impl<'a>(
                     e
                 })
             }
+            RouteParserToken::QueryCapture(_) => alt((get_hash, get_end))(i),
             _ => Err(nom::Err::Failure(ParseError {
                 reason: Some(ParserErrorReason::InvalidState),
                 expected: vec![],
@@ -390,7 +531,9 @@ fn parse_impl<'a>(
             RouteParserToken::FragmentBegin => {
                 alt((fragment_exact, capture(field_naming_scheme), get_end))(i)
             }
-            RouteParserToken::Exact(_) => alt((capture(field_naming_scheme), get_end))(i),
+            RouteParserToken::Exact(_) => {
+                alt((fragment_exact, capture(field_naming_scheme), get_end))(i)
+            }
             RouteParserToken::Capture(_) => alt((fragment_exact, get_end))(i),
             _ => Err(nom::Err::Failure(ParseError {
                 reason: Some(ParserErrorReason::InvalidState),
@@ -447,6 +590,26 @@ mod test {
             parse("/lorem/{ipsum}").expect("should parse");
         }
 
+        #[test]
+        fn constrained_capture_in_path() {
+            parse(r"/lorem/{ipsum:u32}/{dolor:[a-z]\d{2}}/sit").expect("should parse");
+        }
+
+        #[test]
+        fn optional_in_path() {
+            parse("/lorem[/ipsum/{dolor}]").expect("should parse");
+        }
+
+        #[test]
+        fn optional_after_capture() {
+            parse("/{lorem}[/ipsum]?dolor=sit").expect("should parse");
+        }
+
+        #[test]
+        fn nested_optional() {
+            parse("[/lorem[/ipsum]]/dolor").expect("should parse");
+        }
+
         #[test]
         fn capture_rest_in_path() {
             parse("/lorem/{*:ipsum}").expect("should parse");
@@ -482,6 +645,16 @@ mod test {
             parse("?lorem=ipsum&dolor=sit").expect("should parse");
         }
 
+        #[test]
+        fn query_capture() {
+            parse("/lorem{?ipsum}").expect("should parse");
+        }
+
+        #[test]
+        fn query_capture_and_fragment() {
+            parse("/lorem{?ipsum}#dolor!").expect("should parse");
+        }
+
         #[test]
         fn query_and_exact_fragment() {
             parse("?lorem=ipsum#dolor").expect("should parse");
@@ -571,6 +744,47 @@ mod test {
             )
         }
 
+        #[test]
+        fn error_position() {
+            let x = parse("/{lor#m}").expect_err("Should not parse");
+            assert_eq!(x.position(), 5);
+            let x = parse("/lorem//ipsum").expect_err("Should not parse");
+            assert_eq!(x.position(), 7);
+        }
+
+
+        #[test]
+        fn unclosed_optional() {
+            let x = parse("/lorem[/ipsum").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::UnclosedOptional));
+        }
+
+        #[test]
+        fn query_within_optional() {
+            let x = parse("/lorem[/ipsum?dolor=sit]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::UnclosedOptional));
+        }
+
+        #[test]
+        fn query_after_query_capture() {
+            let x = parse("/lorem{?ipsum}&dolor=sit").expect_err("Should not parse");
+            assert_eq!(
+                x.error.expected,
+                vec![ExpectedToken::FragmentBegin, ExpectedToken::End]
+            )
+        }
+
+        #[test]
+        fn capture_begins_optional() {
+            let x = parse("/{lorem}[{ipsum}]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::AdjacentCaptures));
+        }
+
+        #[test]
+        fn unopened_optional() {
+            let x = parse("/lorem]/ipsum").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::UnopenedOptional));
+        }
 
         #[test]
         fn after_end() {
@@ -583,6 +797,20 @@ mod test {
         use super::*;
         use crate::parser::{CaptureOrExact, RefCaptureVariant};
 
+        #[test]
+        fn optional_path() {
+            let parsed = parse("/lorem[/{}]").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact("lorem"),
+                RouteParserToken::OptionalBegin,
+                RouteParserToken::Separator,
+                RouteParserToken::Capture(RefCaptureVariant::Unnamed),
+                RouteParserToken::OptionalEnd,
+            ];
+            assert_eq!(parsed, expected);
+        }
+
         #[test]
         fn starting_literal() {
             let parsed = parse("lorem").unwrap();
@@ -590,6 +818,76 @@ mod test {
             assert_eq!(parsed, expected);
         }
 
+        #[test]
+        fn captures_within_section() {
+            let parsed = parse("/{stem}.{ext}").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "stem",
+                    constraint: None,
+                }),
+                RouteParserToken::Exact("."),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "ext",
+                    constraint: None,
+                }),
+            ];
+            assert_eq!(parsed, expected);
+        }
+
+        #[test]
+        fn escaped_literals() {
+            let parsed = parse("/wow!!/a&&b==c").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact("wow"),
+                RouteParserToken::Exact("!"),
+                RouteParserToken::Separator,
+                RouteParserToken::Exact("a"),
+                RouteParserToken::Exact("&"),
+                RouteParserToken::Exact("b"),
+                RouteParserToken::Exact("="),
+                RouteParserToken::Exact("c"),
+            ];
+            assert_eq!(parsed, expected);
+        }
+
+        #[test]
+        fn escaped_literal_at_start() {
+            let parsed = parse("{{lorem}}").unwrap();
+            let expected = vec![
+                RouteParserToken::Exact("{"),
+                RouteParserToken::Exact("lorem"),
+                RouteParserToken::Exact("}"),
+            ];
+            assert_eq!(parsed, expected);
+        }
+
+        #[test]
+        fn escaped_literal_in_fragment() {
+            let parsed = parse("#lorem!!ipsum").unwrap();
+            let expected = vec![
+                RouteParserToken::FragmentBegin,
+                RouteParserToken::Exact("lorem"),
+                RouteParserToken::Exact("!"),
+                RouteParserToken::Exact("ipsum"),
+            ];
+            assert_eq!(parsed, expected);
+        }
+
+        #[test]
+        fn escaped_literal_before_end() {
+            let parsed = parse("/wow!!!").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact("wow"),
+                RouteParserToken::Exact("!"),
+                RouteParserToken::End,
+            ];
+            assert_eq!(parsed, expected);
+        }
+
         #[test]
         fn minimal_path() {
             let parsed = parse("/lorem").unwrap();
@@ -621,9 +919,15 @@ mod test {
             let parsed = parse("/{lorem}/{ipsum}").unwrap();
             let expected = vec![
                 RouteParserToken::Separator,
-                RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "lorem",
+                    constraint: None,
+                }),
                 RouteParserToken::Separator,
-                RouteParserToken::Capture(RefCaptureVariant::Named("ipsum")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "ipsum",
+                    constraint: None,
+                }),
             ];
             assert_eq!(parsed, expected);
         }
@@ -697,7 +1001,10 @@ mod test {
             let parsed = parse("#{lorem}").unwrap();
             let expected = vec![
                 RouteParserToken::FragmentBegin,
-                RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "lorem",
+                    constraint: None,
+                }),
             ];
             assert_eq!(parsed, expected);
         }
@@ -707,9 +1014,15 @@ mod test {
             let parsed = parse("#{lorem}ipsum{dolor}").unwrap();
             let expected = vec![
                 RouteParserToken::FragmentBegin,
-                RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "lorem",
+                    constraint: None,
+                }),
                 RouteParserToken::Exact("ipsum"),
-                RouteParserToken::Capture(RefCaptureVariant::Named("dolor")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "dolor",
+                    constraint: None,
+                }),
             ];
             assert_eq!(parsed, expected);
         }
@@ -744,7 +1057,10 @@ mod test {
                 RouteParserToken::Separator,
                 RouteParserToken::Exact("lorem"),
                 RouteParserToken::Separator,
-                RouteParserToken::Capture(RefCaptureVariant::Named("cap")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "cap",
+                    constraint: None,
+                }),
                 RouteParserToken::End,
             ];
             assert_eq!(parsed, expected);
@@ -757,7 +1073,10 @@ mod test {
                 RouteParserToken::QueryBegin,
                 RouteParserToken::Query {
                     ident: "lorem",
-                    capture_or_exact: CaptureOrExact::Capture(RefCaptureVariant::Named("cap")),
+                    capture_or_exact: CaptureOrExact::Capture(RefCaptureVariant::Named {
+                        name: "cap",
+                        constraint: None,
+                    }),
                 },
                 RouteParserToken::End,
             ];
@@ -769,7 +1088,10 @@ mod test {
             let parsed = parse("#{cap}!").unwrap();
             let expected = vec![
                 RouteParserToken::FragmentBegin,
-                RouteParserToken::Capture(RefCaptureVariant::Named("cap")),
+                RouteParserToken::Capture(RefCaptureVariant::Named {
+                    name: "cap",
+                    constraint: None,
+                }),
                 RouteParserToken::End,
             ];
             assert_eq!(parsed, expected);
@@ -3,7 +3,11 @@ use crate::{
     page::{Page, PageProps},
 };
 use yew::{html::ChildrenWithProps, prelude::*, virtual_dom::VNode, Properties};
-use yew_router::{agent::RouteRequest::GetCurrentRoute, matcher::RouteMatcher, prelude::*};
+use yew_router::{
+    agent::{RouteRequest::GetCurrentRoute, RouteResponse},
+    matcher::RouteMatcher,
+    prelude::*,
+};
 
 pub struct Guide {
     router_agent: Box<dyn Bridge<RouteAgent>>,
@@ -17,7 +21,7 @@ pub struct GuideProps {
 }
 
 pub enum Msg {
-    UpdateRoute(Route),
+    UpdateRoute(RouteResponse),
 }
 
 impl Component for Guide {
@@ -41,9 +45,10 @@ impl Component for Guide {
 
     fn update(&mut self, msg: Self::Message) -> bool {
         match msg {
-            Msg::UpdateRoute(route) => {
+            Msg::UpdateRoute(RouteResponse::Route(route)) => {
                 self.route = Some(route);
             }
+            Msg::UpdateRoute(_) => return false,
         }
         true
     }
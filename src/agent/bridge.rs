@@ -1,5 +1,8 @@
 //! Bridge to RouteAgent.
-use crate::{agent::RouteAgent, route::Route, RouteState};
+use crate::{
+    agent::{RouteAgent, RouteResponse},
+    RouteState,
+};
 use std::{
     fmt::{Debug, Error as FmtError, Formatter},
     ops::{Deref, DerefMut},
@@ -21,7 +24,7 @@ where
     STATE: RouteState,
 {
     /// Creates a new bridge.
-    pub fn new(callback: Callback<Route<STATE>>) -> Self {
+    pub fn new(callback: Callback<RouteResponse<STATE>>) -> Self {
         let router_agent = RouteAgent::bridge(callback);
         RouteAgentBridge(router_agent)
     }
@@ -29,7 +32,7 @@ where
     /// Experimental, may be removed
     ///
     /// Directly spawn a new Router
-    pub fn spawn(callback: Callback<Route<STATE>>) -> Self {
+    pub fn spawn(callback: Callback<RouteResponse<STATE>>) -> Self {
         use yew::agent::Discoverer;
         let router_agent = Context::spawn_or_join(Some(callback));
         RouteAgentBridge(router_agent)
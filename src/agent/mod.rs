@@ -2,17 +2,14 @@
 //!
 //! It wraps a route service and allows calls to be sent to it to update every subscriber,
 //! or just the element that made the request.
-use crate::service::RouteService;
+use crate::history::create_history_backend;
 
 use yew::prelude::worker::*;
 
-use std::collections::HashSet;
-
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Error as FmtError, Formatter};
 
 use crate::route::{Route, RouteState};
-use log::trace;
 
 mod bridge;
 pub use bridge::RouteAgentBridge;
@@ -20,6 +17,8 @@ pub use bridge::RouteAgentBridge;
 mod dispatcher;
 pub use dispatcher::RouteAgentDispatcher;
 
+mod navigator;
+use navigator::Navigator;
 
 /// Internal Message used for the RouteAgent.
 #[derive(Debug)]
@@ -38,15 +37,102 @@ pub enum RouteRequest<T = ()> {
     /// the route change.
     ReplaceRouteNoBroadcast(Route<T>),
     /// Changes the route using a Route struct and alerts connected components to the route change.
+    ///
+    /// The page is scrolled to the element named by the fragment of the route, or to the top.
     ChangeRoute(Route<T>),
+    /// Changes the route using a Route struct and alerts connected components to the route change,
+    /// but leaves the page scrolled where it is.
+    ChangeRouteNoScroll(Route<T>),
     /// Changes the route using a Route struct, but does not alert connected components to the
     /// route change.
     ChangeRouteNoBroadcast(Route<T>),
     /// Gets the current route.
     GetCurrentRoute,
+    /// Moves back to the previous route, like the browser's back button.
+    ///
+    /// The route that is moved to is broadcast to all connected components.
+    /// If there is no previous route, `RouteResponse::NoHistoryEntry` is sent to the component
+    /// that made the request instead.
+    Back,
+    /// Moves forward to the next route, like the browser's forward button.
+    ///
+    /// Like `Back`, `RouteResponse::NoHistoryEntry` is sent to the component that made the
+    /// request if there is no next route.
+    Forward,
+    /// Moves the given number of routes forward, or backwards if it is negative.
+    ///
+    /// Like `Back`, `RouteResponse::NoHistoryEntry` is sent to the component that made the
+    /// request if there is no route that far away.
+    Go(i32),
+    /// Blocks navigation, like when the component that made the request has unsaved changes,
+    /// until it sends `UnregisterBlocker` or is disconnected.
+    ///
+    /// While navigation is blocked, route changes, replacements and moves through the history are
+    /// held instead of being carried out, and are sent to the blocking components as
+    /// `RouteResponse::Held`.
+    /// When the browser's buttons are pressed, the browser is moved back to the current route
+    /// while the move is held.
+    ///
+    /// The held navigation is carried out with `ConfirmNavigation`, or dropped with
+    /// `CancelNavigation`.
+    /// Only the latest navigation is held.
+    ///
+    /// The browser also asks the user to confirm closing or reloading the page, with the given
+    /// prompt if it shows one.
+    RegisterBlocker(String),
+    /// Stops the component that made the request from blocking navigation.
+    ///
+    /// When no component blocks navigation anymore, the held navigation is dropped.
+    UnregisterBlocker,
+    /// Carries out the navigation that is held while navigation is blocked.
+    ConfirmNavigation,
+    /// Drops the navigation that is held while navigation is blocked.
+    CancelNavigation,
+}
+
+/// Output message type of the `RouteAgent`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RouteResponse<T = ()> {
+    /// The current route.
+    ///
+    /// It is broadcast to all connected components when it changes, and sent to the component
+    /// that made a `GetCurrentRoute` request.
+    Route(Route<T>),
+    /// A `Back`, `Forward` or `Go` request with the given number of routes couldn't be carried
+    /// out, because the app's history has no route that far away.
+    ///
+    /// It is sent to the component that made the request.
+    NoHistoryEntry(i32),
+    /// A navigation that is held because navigation is blocked.
+    ///
+    /// It is sent to the components that block navigation.
+    Held(HeldNavigation<T>),
 }
 
-/// The RouteAgent holds on to the RouteService singleton and mediates access to it.
+/// A navigation that is held while navigation is blocked, see `RouteRequest::RegisterBlocker`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum HeldNavigation<T = ()> {
+    /// A change or replacement of the route.
+    Route(Route<T>),
+    /// A move through the history by the given number of routes, requested with `Back`,
+    /// `Forward` or `Go`.
+    ///
+    /// The route that would be moved to isn't known until the move is carried out.
+    Go(i32),
+    /// A move through the history by the given number of routes to the route,
+    /// made with the browser's buttons.
+    Popped {
+        /// The route the browser moved to.
+        route: Route<T>,
+        /// How many routes the browser moved forward, or backwards if it is negative.
+        delta: i32,
+    },
+}
+
+/// The RouteAgent holds on to the history singleton and mediates access to it.
+///
+/// The history is the browser's, unless another one is set with
+/// [`set_history_backend`](../history/fn.set_history_backend.html).
 ///
 /// It serves as a means to propagate messages to components interested in the state of the current
 /// route.
@@ -63,97 +149,65 @@ where
     // In order to have the AgentLink<Self> below, apparently T must be constrained like this.
     // Unfortunately, this means that everything related to an agent requires this constraint.
     link: AgentLink<RouteAgent<STATE>>,
-    /// The service through which communication with the browser happens.
-    route_service: RouteService<STATE>,
-    /// A list of all entities connected to the router.
-    /// When a route changes, either initiated by the browser or by the app,
-    /// the route change will be broadcast to all listening entities.
-    subscribers: HashSet<HandlerId>,
+    /// Carries out the requests on the history, and collects the outputs to respond with.
+    navigator: Navigator<STATE, HandlerId>,
 }
 
 impl<STATE: RouteState> Debug for RouteAgent<STATE> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
         f.debug_struct("RouteAgent")
             .field("link", &"-")
-            .field("route_service", &self.route_service)
-            .field("subscribers", &self.subscribers.len())
+            .field("navigator", &self.navigator)
             .finish()
     }
 }
 
+impl<STATE: RouteState> RouteAgent<STATE> {
+    fn respond(&mut self) {
+        for (id, output) in self.navigator.take_outputs() {
+            self.link.respond(id, output);
+        }
+    }
+}
+
 impl<STATE> Agent for RouteAgent<STATE>
 where
     STATE: RouteState,
 {
     type Input = RouteRequest<STATE>;
     type Message = Msg<STATE>;
-    type Output = Route<STATE>;
+    type Output = RouteResponse<STATE>;
     type Reach = Context;
 
     fn create(link: AgentLink<RouteAgent<STATE>>) -> Self {
         let callback = link.callback(Msg::BrowserNavigationRouteChanged);
-        let mut route_service = RouteService::new();
-        route_service.register_callback(callback);
+        let mut history = create_history_backend();
+        history.subscribe(callback);
 
         RouteAgent {
             link,
-            route_service,
-            subscribers: HashSet::new(),
+            navigator: Navigator::new(history),
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         match msg {
-            Msg::BrowserNavigationRouteChanged(route) => {
-                trace!("Browser navigated");
-                for sub in &self.subscribers {
-                    self.link.respond(*sub, route.clone());
-                }
-            }
+            Msg::BrowserNavigationRouteChanged(route) => self.navigator.history_moved(route),
         }
+        self.respond();
     }
 
     fn connected(&mut self, id: HandlerId) {
-        self.subscribers.insert(id);
+        self.navigator.connected(id);
     }
 
     fn handle_input(&mut self, msg: Self::Input, who: HandlerId) {
-        match msg {
-            RouteRequest::ReplaceRoute(route) => {
-                let route_string: String = route.to_string();
-                self.route_service.replace_route(&route_string, route.state);
-                let route = self.route_service.get_route();
-                for sub in &self.subscribers {
-                    self.link.respond(*sub, route.clone());
-                }
-            }
-            RouteRequest::ReplaceRouteNoBroadcast(route) => {
-                let route_string: String = route.to_string();
-                self.route_service.replace_route(&route_string, route.state);
-            }
-            RouteRequest::ChangeRoute(route) => {
-                let route_string: String = route.to_string();
-                // set the route
-                self.route_service.set_route(&route_string, route.state);
-                // get the new route.
-                let route = self.route_service.get_route();
-                // broadcast it to all listening components
-                for sub in &self.subscribers {
-                    self.link.respond(*sub, route.clone());
-                }
-            }
-            RouteRequest::ChangeRouteNoBroadcast(route) => {
-                let route_string: String = route.to_string();
-                self.route_service.set_route(&route_string, route.state);
-            }
-            RouteRequest::GetCurrentRoute => {
-                let route = self.route_service.get_route();
-                self.link.respond(who, route);
-            }
-        }
+        self.navigator.handle_input(msg, who);
+        self.respond();
     }
 
     fn disconnected(&mut self, id: HandlerId) {
-        self.subscribers.remove(&id);
+        self.navigator.disconnected(id);
+        self.respond();
     }
 }
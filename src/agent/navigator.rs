@@ -0,0 +1,587 @@
+//! The decisions of the route agent, apart from the agent's link.
+use crate::{
+    agent::{HeldNavigation, RouteRequest, RouteResponse},
+    history::HistoryBackend,
+    route::{Route, RouteState},
+};
+use log::trace;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+};
+
+/// A navigation that can be held while navigation is blocked.
+#[derive(Debug)]
+enum Navigation<STATE, ID> {
+    Change {
+        route: Route<STATE>,
+        broadcast: bool,
+        scroll: bool,
+    },
+    Replace {
+        route: Route<STATE>,
+        broadcast: bool,
+    },
+    /// A move through the history, requested by the entity.
+    Go(i32, ID),
+    /// The browser moved to the route by the number of entries, and was moved back to the route
+    /// it came from.
+    Popped { route: Route<STATE>, delta: i32 },
+}
+
+/// A move through the history that the navigator made itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExpectedMove {
+    /// A move to the entry with the index that was requested or confirmed, so it isn't blocked.
+    Confirmed(usize),
+    /// A move back to the current route, after a move with the browser's buttons was blocked.
+    Reverted,
+}
+
+/// Carries out the requests sent to the route agent on its history, and collects the outputs
+/// that the agent sends to its connected entities.
+///
+/// The `ID` identifies the connected entities, which is a `HandlerId` for the agent.
+#[derive(Debug)]
+pub(crate) struct Navigator<STATE, ID> {
+    /// The history through which communication with the browser happens.
+    history: Box<dyn HistoryBackend<STATE>>,
+    /// A list of all entities connected to the router.
+    /// When a route changes, either initiated by the browser or by the app,
+    /// the route change will be broadcast to all listening entities.
+    subscribers: HashSet<ID>,
+    /// The entities that block navigation, with the prompts they block with.
+    blockers: HashMap<ID, String>,
+    /// The navigation that is held while navigation is blocked.
+    pending: Option<Navigation<STATE, ID>>,
+    /// The route the history is at, which the browser is moved back to when its navigation is
+    /// blocked.
+    current: Route<STATE>,
+    /// The index of the history's entry for the current route.
+    index: usize,
+    /// The move through the history that the history will notify the navigator of next.
+    ///
+    /// Moves that don't land where the expected one would are handled like the browser's
+    /// buttons were pressed, since the expected move may never happen.
+    expected_move: Option<ExpectedMove>,
+    /// The outputs that haven't been sent yet, with the entities they are sent to.
+    outputs: Vec<(ID, RouteResponse<STATE>)>,
+}
+
+impl<STATE, ID> Navigator<STATE, ID>
+where
+    STATE: RouteState,
+    ID: Copy + Eq + Hash + Debug,
+{
+    /// Creates a navigator for the history.
+    ///
+    /// The history's subscribers should pass the routes that it moves to on to `history_moved`.
+    pub fn new(history: Box<dyn HistoryBackend<STATE>>) -> Self {
+        let current = history.current();
+        let index = history.index();
+        Navigator {
+            history,
+            subscribers: HashSet::new(),
+            blockers: HashMap::new(),
+            pending: None,
+            current,
+            index,
+            expected_move: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Takes the outputs that were collected since this was last called.
+    pub fn take_outputs(&mut self) -> Vec<(ID, RouteResponse<STATE>)> {
+        std::mem::replace(&mut self.outputs, Vec::new())
+    }
+
+    pub fn connected(&mut self, id: ID) {
+        self.subscribers.insert(id);
+    }
+
+    pub fn disconnected(&mut self, id: ID) {
+        self.subscribers.remove(&id);
+        self.unregister_blocker(id);
+    }
+
+    /// Handles the history moving to the route, like when the browser's buttons are pressed.
+    pub fn history_moved(&mut self, route: Route<STATE>) {
+        trace!("Browser navigated");
+        let index = self.history.index();
+        let delta = index as i32 - self.index as i32;
+        match self.expected_move.take() {
+            // The browser is back at the current route.
+            Some(ExpectedMove::Reverted) if delta == 0 => return,
+            Some(ExpectedMove::Confirmed(target)) if index == target => {}
+            // A move that stays at the same entry can't be reverted.
+            _ if self.blockers.is_empty() || delta == 0 => {}
+            _ => {
+                // Move the browser back to the route it came from.
+                self.expected_move = Some(ExpectedMove::Reverted);
+                self.history.go(-delta);
+                let who = *self.blockers.keys().next().expect("there is a blocker");
+                self.navigate(Navigation::Popped { route, delta }, who);
+                return;
+            }
+        }
+        self.history.restore_scroll(&route.route);
+        self.current = route;
+        self.index = index;
+        self.broadcast();
+    }
+
+    pub fn handle_input(&mut self, msg: RouteRequest<STATE>, who: ID) {
+        match msg {
+            RouteRequest::ReplaceRoute(route) => self.navigate(
+                Navigation::Replace {
+                    route,
+                    broadcast: true,
+                },
+                who,
+            ),
+            // Routes that aren't broadcast don't lead away from the components that block
+            // navigation, so they aren't blocked.
+            RouteRequest::ReplaceRouteNoBroadcast(route) => self.carry_out(
+                Navigation::Replace {
+                    route,
+                    broadcast: false,
+                },
+                who,
+            ),
+            RouteRequest::ChangeRoute(route) => self.navigate(
+                Navigation::Change {
+                    route,
+                    broadcast: true,
+                    scroll: true,
+                },
+                who,
+            ),
+            RouteRequest::ChangeRouteNoScroll(route) => self.navigate(
+                Navigation::Change {
+                    route,
+                    broadcast: true,
+                    scroll: false,
+                },
+                who,
+            ),
+            RouteRequest::ChangeRouteNoBroadcast(route) => self.carry_out(
+                Navigation::Change {
+                    route,
+                    broadcast: false,
+                    scroll: false,
+                },
+                who,
+            ),
+            RouteRequest::GetCurrentRoute => {
+                let route = self.history.current();
+                self.outputs.push((who, RouteResponse::Route(route)));
+            }
+            RouteRequest::Back => self.navigate(Navigation::Go(-1, who), who),
+            RouteRequest::Forward => self.navigate(Navigation::Go(1, who), who),
+            RouteRequest::Go(delta) => self.navigate(Navigation::Go(delta, who), who),
+            RouteRequest::RegisterBlocker(prompt) => {
+                self.history.set_unload_prompt(Some(prompt.clone()));
+                self.blockers.insert(who, prompt);
+            }
+            RouteRequest::UnregisterBlocker => self.unregister_blocker(who),
+            RouteRequest::ConfirmNavigation => {
+                if let Some(navigation) = self.pending.take() {
+                    self.carry_out(navigation, who);
+                }
+            }
+            RouteRequest::CancelNavigation => self.pending = None,
+        }
+    }
+
+    fn broadcast(&mut self) {
+        for sub in &self.subscribers {
+            self.outputs
+                .push((*sub, RouteResponse::Route(self.current.clone())));
+        }
+    }
+
+    /// Carries out the navigation, or holds it if navigation is blocked.
+    fn navigate(&mut self, navigation: Navigation<STATE, ID>, who: ID) {
+        if self.blockers.is_empty() {
+            self.carry_out(navigation, who);
+            return;
+        }
+        let held = match &navigation {
+            Navigation::Change { route, .. } | Navigation::Replace { route, .. } => {
+                HeldNavigation::Route(route.clone())
+            }
+            Navigation::Go(delta, _) => HeldNavigation::Go(*delta),
+            Navigation::Popped { route, delta } => HeldNavigation::Popped {
+                route: route.clone(),
+                delta: *delta,
+            },
+        };
+        trace!("Navigation is held: {:?}", held);
+        self.pending = Some(navigation);
+        for blocker in self.blockers.keys() {
+            self.outputs
+                .push((*blocker, RouteResponse::Held(held.clone())));
+        }
+    }
+
+    fn carry_out(&mut self, navigation: Navigation<STATE, ID>, who: ID) {
+        match navigation {
+            Navigation::Change {
+                route,
+                broadcast,
+                scroll,
+            } => {
+                let route_string: String = route.to_string();
+                // set the route
+                self.history.push(&route_string, route.state);
+                // get the new route.
+                self.current = self.history.current();
+                self.index = self.history.index();
+                // broadcast it to all listening components
+                if broadcast {
+                    self.broadcast();
+                }
+                if scroll {
+                    self.history.scroll_to_route(&self.current.route);
+                }
+            }
+            Navigation::Replace { route, broadcast } => {
+                let route_string: String = route.to_string();
+                self.history.replace(&route_string, route.state);
+                self.current = self.history.current();
+                if broadcast {
+                    self.broadcast();
+                }
+            }
+            Navigation::Go(delta, requester) => self.go(delta, requester),
+            // The browser was moved back to the route it came from, so it moves to the route
+            // again.
+            Navigation::Popped { delta, .. } => self.go(delta, who),
+        }
+    }
+
+    /// Moves through the history.
+    ///
+    /// The route that is moved to is broadcast once the history notifies the agent of it,
+    /// just like when the browser's buttons are pressed.
+    /// If there is no route to move to, the entity that requested the move is told so.
+    fn go(&mut self, delta: i32, requester: ID) {
+        let target = self.index as i64 + i64::from(delta);
+        if target >= 0 {
+            self.expected_move = Some(ExpectedMove::Confirmed(target as usize));
+        }
+        if target < 0 || !self.history.go(delta) {
+            self.expected_move = None;
+            log::warn!("There is no route {} routes away in the history", delta);
+            self.outputs
+                .push((requester, RouteResponse::NoHistoryEntry(delta)));
+        }
+    }
+
+    fn unregister_blocker(&mut self, id: ID) {
+        if self.blockers.remove(&id).is_none() {
+            return;
+        }
+        if self.blockers.is_empty() {
+            self.pending = None;
+        }
+        let prompt = self.blockers.values().next().cloned();
+        self.history.set_unload_prompt(prompt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::MemoryHistory;
+    use std::{cell::RefCell, rc::Rc};
+    use yew::Callback;
+
+    const COMPONENT: usize = 1;
+    const FORM: usize = 2;
+
+    /// A navigator over a memory history, that passes the moves of the history on to the
+    /// navigator like the agent's messages do.
+    struct Harness {
+        navigator: Navigator<(), usize>,
+        history: MemoryHistory<()>,
+        moves: Rc<RefCell<Vec<Route<()>>>>,
+    }
+
+    impl Harness {
+        fn new(routes: &[&str]) -> Self {
+            let mut history = MemoryHistory::new(routes[0]);
+            for route in &routes[1..] {
+                history.push(route, ());
+            }
+            let moves = Rc::new(RefCell::new(Vec::new()));
+            let moved = moves.clone();
+            history.subscribe(Callback::from(move |route| moved.borrow_mut().push(route)));
+            let mut navigator = Navigator::new(Box::new(history.clone()));
+            navigator.connected(COMPONENT);
+            navigator.connected(FORM);
+            Harness {
+                navigator,
+                history,
+                moves,
+            }
+        }
+
+        fn settle(&mut self) {
+            loop {
+                let moves = std::mem::replace(&mut *self.moves.borrow_mut(), Vec::new());
+                if moves.is_empty() {
+                    break;
+                }
+                for route in moves {
+                    self.navigator.history_moved(route);
+                }
+            }
+        }
+
+        fn send(&mut self, msg: RouteRequest<()>, who: usize) -> Vec<(usize, RouteResponse<()>)> {
+            self.navigator.handle_input(msg, who);
+            self.settle();
+            self.outputs()
+        }
+
+        /// Presses the browser's back or forward buttons.
+        fn press(&mut self, delta: i32) -> Vec<(usize, RouteResponse<()>)> {
+            assert!(self.history.go(delta));
+            self.settle();
+            self.outputs()
+        }
+
+        fn outputs(&mut self) -> Vec<(usize, RouteResponse<()>)> {
+            let mut outputs: Vec<_> = self.navigator.take_outputs().into_iter().collect();
+            outputs.sort_by_key(|(id, _)| *id);
+            outputs
+        }
+
+        fn route(&self) -> String {
+            self.history.current().route
+        }
+    }
+
+    /// A history that claims to move, but stays where it is, like the browser does when it is
+    /// asked to move to an entry that doesn't exist.
+    #[derive(Debug)]
+    struct StuckHistory(MemoryHistory<()>);
+
+    impl HistoryBackend<()> for StuckHistory {
+        fn push(&mut self, route: &str, state: ()) {
+            self.0.push(route, state)
+        }
+
+        fn replace(&mut self, route: &str, state: ()) {
+            self.0.replace(route, state)
+        }
+
+        fn current(&self) -> Route<()> {
+            self.0.current()
+        }
+
+        fn index(&self) -> usize {
+            self.0.index()
+        }
+
+        fn subscribe(&mut self, callback: Callback<Route<()>>) {
+            self.0.subscribe(callback)
+        }
+
+        fn go(&mut self, _delta: i32) -> bool {
+            true
+        }
+    }
+
+    fn route(route: &str) -> Route<()> {
+        Route {
+            route: route.to_string(),
+            state: (),
+        }
+    }
+
+    fn routed(id: usize, to: &str) -> (usize, RouteResponse<()>) {
+        (id, RouteResponse::Route(route(to)))
+    }
+
+    fn held(navigation: HeldNavigation<()>) -> (usize, RouteResponse<()>) {
+        (FORM, RouteResponse::Held(navigation))
+    }
+
+    fn both(to: &str) -> Vec<(usize, RouteResponse<()>)> {
+        vec![routed(COMPONENT, to), routed(FORM, to)]
+    }
+
+    #[test]
+    fn changes_are_broadcast() {
+        let mut harness = Harness::new(&["/"]);
+        assert_eq!(
+            harness.send(RouteRequest::ChangeRoute(route("/a")), COMPONENT),
+            both("/a")
+        );
+        assert_eq!(
+            harness.send(RouteRequest::ChangeRouteNoBroadcast(route("/b")), COMPONENT),
+            vec![]
+        );
+        assert_eq!(harness.history.len(), 3);
+    }
+
+    #[test]
+    fn moves_are_broadcast() {
+        let mut harness = Harness::new(&["/", "/a", "/b"]);
+        assert_eq!(harness.send(RouteRequest::Go(-2), COMPONENT), both("/"));
+        assert_eq!(harness.send(RouteRequest::Forward, COMPONENT), both("/a"));
+        assert_eq!(harness.press(1), both("/b"));
+    }
+
+    #[test]
+    fn missing_entries_are_reported_to_the_requester() {
+        let mut harness = Harness::new(&["/", "/a"]);
+        assert_eq!(
+            harness.send(RouteRequest::Forward, COMPONENT),
+            vec![(COMPONENT, RouteResponse::NoHistoryEntry(1))]
+        );
+        assert_eq!(
+            harness.send(RouteRequest::Go(-2), FORM),
+            vec![(FORM, RouteResponse::NoHistoryEntry(-2))]
+        );
+        assert_eq!(harness.route(), "/a");
+    }
+
+    #[test]
+    fn blocked_changes_are_held() {
+        let mut harness = Harness::new(&["/"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        assert_eq!(
+            harness.send(RouteRequest::ChangeRoute(route("/a")), COMPONENT),
+            vec![held(HeldNavigation::Route(route("/a")))]
+        );
+        assert_eq!(harness.route(), "/");
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, FORM),
+            both("/a")
+        );
+        assert_eq!(harness.route(), "/a");
+    }
+
+    #[test]
+    fn cancelled_changes_are_dropped() {
+        let mut harness = Harness::new(&["/"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        harness.send(RouteRequest::ChangeRoute(route("/a")), COMPONENT);
+        harness.send(RouteRequest::CancelNavigation, FORM);
+        assert_eq!(harness.send(RouteRequest::ConfirmNavigation, FORM), vec![]);
+        assert_eq!(harness.route(), "/");
+    }
+
+    #[test]
+    fn blocked_moves_are_held() {
+        let mut harness = Harness::new(&["/", "/a"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        assert_eq!(
+            harness.send(RouteRequest::Back, COMPONENT),
+            vec![held(HeldNavigation::Go(-1))]
+        );
+        assert_eq!(harness.route(), "/a");
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, FORM),
+            both("/")
+        );
+    }
+
+    #[test]
+    fn blocked_browser_moves_are_reverted_and_confirmed() {
+        let mut harness = Harness::new(&["/", "/a", "/b"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+
+        assert_eq!(
+            harness.press(-2),
+            vec![held(HeldNavigation::Popped {
+                route: route("/"),
+                delta: -2
+            })]
+        );
+        assert_eq!(harness.route(), "/b");
+        assert_eq!(harness.history.len(), 3);
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, FORM),
+            both("/")
+        );
+        assert_eq!(harness.history.index(), 0);
+
+        assert_eq!(
+            harness.press(1),
+            vec![held(HeldNavigation::Popped {
+                route: route("/a"),
+                delta: 1
+            })]
+        );
+        assert_eq!(harness.route(), "/");
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, FORM),
+            both("/a")
+        );
+        assert_eq!(harness.history.index(), 1);
+        assert_eq!(harness.history.len(), 3);
+    }
+
+    #[test]
+    fn cancelled_browser_moves_stay_reverted() {
+        let mut harness = Harness::new(&["/", "/a"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        harness.press(-1);
+        assert_eq!(harness.send(RouteRequest::CancelNavigation, FORM), vec![]);
+        assert_eq!(harness.route(), "/a");
+        harness.send(RouteRequest::UnregisterBlocker, FORM);
+        assert_eq!(harness.press(-1), both("/"));
+    }
+
+    #[test]
+    fn missing_entries_of_held_moves_are_reported_to_the_requester() {
+        let mut harness = Harness::new(&["/"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        harness.send(RouteRequest::Back, COMPONENT);
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, FORM),
+            vec![(COMPONENT, RouteResponse::NoHistoryEntry(-1))]
+        );
+    }
+
+    #[test]
+    fn moves_that_never_happen_dont_confirm_later_ones() {
+        let mut history = MemoryHistory::new("/");
+        history.push("/a", ());
+        let mut navigator = Navigator::new(Box::new(StuckHistory(history.clone())));
+        navigator.handle_input(RouteRequest::Forward, COMPONENT);
+        navigator.handle_input(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        // The back button is pressed.
+        history.go(-1);
+        navigator.history_moved(history.current());
+        assert_eq!(
+            navigator.take_outputs(),
+            vec![held(HeldNavigation::Popped {
+                route: route("/"),
+                delta: -1
+            })]
+        );
+    }
+
+    #[test]
+    fn unregistering_the_last_blocker_drops_the_held_navigation() {
+        let mut harness = Harness::new(&["/"]);
+        harness.send(RouteRequest::RegisterBlocker("Unsaved".to_string()), FORM);
+        harness.send(RouteRequest::ChangeRoute(route("/a")), COMPONENT);
+        harness.navigator.disconnected(FORM);
+        assert_eq!(
+            harness.send(RouteRequest::ConfirmNavigation, COMPONENT),
+            vec![]
+        );
+        assert_eq!(
+            harness.send(RouteRequest::ChangeRoute(route("/b")), COMPONENT),
+            vec![routed(COMPONENT, "/b")]
+        );
+    }
+}
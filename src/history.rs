@@ -0,0 +1,399 @@
+//! Histories that the route agent navigates through.
+//!
+//! The browser's history is used by default, but a `MemoryHistory` can be set instead, so that
+//! navigation can be driven and inspected outside of a browser.
+use crate::{
+    route::{Route, RouteState},
+    service::{routing_mode, RouteService, RoutingMode},
+};
+use std::{any::Any, cell::RefCell, fmt::Debug, rc::Rc};
+use yew::callback::Callback;
+
+/// A history of routes with a current entry, that can be navigated through.
+pub trait HistoryBackend<STATE>: Debug {
+    /// Adds an entry for the route after the current one, and makes it the current entry.
+    ///
+    /// Entries after the current one are removed.
+    /// Subscribers aren't notified.
+    fn push(&mut self, route: &str, state: STATE);
+
+    /// Replaces the current entry with one for the route.
+    ///
+    /// Subscribers aren't notified.
+    fn replace(&mut self, route: &str, state: STATE);
+
+    /// Gets the route and state of the current entry.
+    fn current(&self) -> Route<STATE>;
+
+    /// Gets the position of the current entry, which tells how far a move through the entries
+    /// went.
+    fn index(&self) -> usize;
+
+    /// Registers a callback that is called with the current route when it is changed by
+    /// something other than `push` or `replace`, like going back.
+    fn subscribe(&mut self, callback: Callback<Route<STATE>>);
+
+    /// Moves `delta` entries forward, or backwards if it is negative,
+    /// and notifies the subscribers of the route that it moved to.
+    ///
+    /// Returns false if there is no entry to move to.
+    /// Nothing happens in that case.
+    fn go(&mut self, delta: i32) -> bool;
+
+    /// Asks the user to confirm leaving the page with the prompt, until this is called with
+    /// `None`.
+    ///
+    /// Histories that aren't kept in the browser ignore this.
+    fn set_unload_prompt(&mut self, _prompt: Option<String>) {}
+
+    /// Scrolls to the element named by the fragment of the route, or to the top of the page,
+    /// once the app has rendered the route.
+    ///
+    /// Histories that aren't kept in the browser ignore this.
+    fn scroll_to_route(&mut self, _route: &str) {}
+
+    /// Scrolls to where the page was scrolled when the entry that the history is at was last
+    /// left, once the app has rendered the route.
+    ///
+    /// Histories that aren't kept in the browser ignore this.
+    fn restore_scroll(&mut self, _route: &str) {}
+}
+
+/// Keeps routes in the path of the browser's URL, like `/posts?page=2`.
+#[derive(Debug)]
+pub struct BrowserHistory<STATE = ()>(RouteService<STATE>);
+
+impl<STATE> BrowserHistory<STATE> {
+    /// Creates a history on top of the browser's history.
+    ///
+    /// It restores how far the page is scrolled when the browser moves back or forward, see
+    /// [`RouteService::enable_scroll_restoration`](../service/struct.RouteService.html#method.enable_scroll_restoration).
+    pub fn new() -> Self {
+        let service = RouteService::with_routing_mode(RoutingMode::History);
+        service.enable_scroll_restoration();
+        BrowserHistory(service)
+    }
+}
+
+impl<STATE> Default for BrowserHistory<STATE> {
+    fn default() -> Self {
+        BrowserHistory::new()
+    }
+}
+
+/// Keeps routes in the fragment of the browser's URL, like `/#/posts?page=2`.
+#[derive(Debug)]
+pub struct HashHistory<STATE = ()>(RouteService<STATE>);
+
+impl<STATE> HashHistory<STATE> {
+    /// Creates a history on top of the browser's history.
+    ///
+    /// It restores how far the page is scrolled when the browser moves back or forward, see
+    /// [`RouteService::enable_scroll_restoration`](../service/struct.RouteService.html#method.enable_scroll_restoration).
+    pub fn new() -> Self {
+        let service = RouteService::with_routing_mode(RoutingMode::Hash);
+        service.enable_scroll_restoration();
+        HashHistory(service)
+    }
+}
+
+impl<STATE> Default for HashHistory<STATE> {
+    fn default() -> Self {
+        HashHistory::new()
+    }
+}
+
+macro_rules! impl_route_service_backend {
+    ($history:ident) => {
+        impl<STATE: RouteState> HistoryBackend<STATE> for $history<STATE> {
+            fn push(&mut self, route: &str, state: STATE) {
+                self.0.set_route(route, state)
+            }
+
+            fn replace(&mut self, route: &str, state: STATE) {
+                self.0.replace_route(route, state)
+            }
+
+            fn current(&self) -> Route<STATE> {
+                self.0.get_route()
+            }
+
+            /// The index of the entry that is recorded in its state.
+            fn index(&self) -> usize {
+                self.0.entry().index() as usize
+            }
+
+            /// Replaces the previously registered callback.
+            fn subscribe(&mut self, callback: Callback<Route<STATE>>) {
+                self.0.register_callback(callback)
+            }
+
+            /// See `RouteService::go` for the entries that can be detected as missing.
+            fn go(&mut self, delta: i32) -> bool {
+                self.0.go(delta)
+            }
+
+            fn set_unload_prompt(&mut self, prompt: Option<String>) {
+                self.0.set_unload_prompt(prompt)
+            }
+
+            fn scroll_to_route(&mut self, route: &str) {
+                self.0.scroll_to_route(route)
+            }
+
+            fn restore_scroll(&mut self, route: &str) {
+                self.0.restore_scroll(route)
+            }
+        }
+    };
+}
+
+impl_route_service_backend!(BrowserHistory);
+impl_route_service_backend!(HashHistory);
+
+/// Keeps routes in memory, as a stack of entries and the index of the current one.
+///
+/// Clones share the same history, so a clone can be kept to inspect or drive the history
+/// that a route agent navigates through.
+///
+/// # Example
+/// ```
+/// use yew_router::history::{HistoryBackend, MemoryHistory};
+///
+/// let mut history = MemoryHistory::<()>::new("/");
+/// history.push("/posts", ());
+/// history.push("/posts/1", ());
+/// history.go(-2);
+/// assert_eq!(history.current().route, "/");
+/// history.push("/about", ());
+/// assert_eq!(history.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryHistory<STATE = ()> {
+    inner: Rc<RefCell<MemoryHistoryInner<STATE>>>,
+}
+
+#[derive(Debug)]
+struct MemoryHistoryInner<STATE> {
+    entries: Vec<Route<STATE>>,
+    index: usize,
+    subscribers: Vec<Callback<Route<STATE>>>,
+}
+
+impl<STATE: Default> MemoryHistory<STATE> {
+    /// Creates a history with a single entry for the route.
+    pub fn new(route: &str) -> Self {
+        MemoryHistory {
+            inner: Rc::new(RefCell::new(MemoryHistoryInner {
+                entries: vec![Route {
+                    route: route.to_string(),
+                    state: STATE::default(),
+                }],
+                index: 0,
+                subscribers: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl<STATE: Default> Default for MemoryHistory<STATE> {
+    fn default() -> Self {
+        MemoryHistory::new("/")
+    }
+}
+
+impl<STATE: Clone> MemoryHistory<STATE> {
+    /// Gets all entries, from the oldest to the newest.
+    pub fn entries(&self) -> Vec<Route<STATE>> {
+        self.inner.borrow().entries.clone()
+    }
+
+    /// Gets the index of the current entry.
+    pub fn index(&self) -> usize {
+        self.inner.borrow().index
+    }
+
+    /// Gets the number of entries.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Whether there are no entries, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().entries.is_empty()
+    }
+}
+
+impl<STATE: RouteState> HistoryBackend<STATE> for MemoryHistory<STATE> {
+    fn push(&mut self, route: &str, state: STATE) {
+        let mut inner = self.inner.borrow_mut();
+        let index = inner.index + 1;
+        inner.entries.truncate(index);
+        inner.entries.push(Route {
+            route: route.to_string(),
+            state,
+        });
+        inner.index = index;
+    }
+
+    fn replace(&mut self, route: &str, state: STATE) {
+        let mut inner = self.inner.borrow_mut();
+        let index = inner.index;
+        inner.entries[index] = Route {
+            route: route.to_string(),
+            state,
+        };
+    }
+
+    fn current(&self) -> Route<STATE> {
+        let inner = self.inner.borrow();
+        inner.entries[inner.index].clone()
+    }
+
+    fn index(&self) -> usize {
+        self.inner.borrow().index
+    }
+
+    fn subscribe(&mut self, callback: Callback<Route<STATE>>) {
+        self.inner.borrow_mut().subscribers.push(callback);
+    }
+
+    fn go(&mut self, delta: i32) -> bool {
+        let (route, subscribers) = {
+            let mut inner = self.inner.borrow_mut();
+            let index = inner.index as isize + delta as isize;
+            if delta == 0 || index < 0 || index as usize >= inner.entries.len() {
+                return false;
+            }
+            inner.index = index as usize;
+            (
+                inner.entries[inner.index].clone(),
+                inner.subscribers.clone(),
+            )
+        };
+        // The history isn't borrowed while the subscribers run, so they can navigate further.
+        for subscriber in subscribers {
+            subscriber.emit(route.clone());
+        }
+        true
+    }
+}
+
+type BackendFactory<STATE> = Rc<dyn Fn() -> Box<dyn HistoryBackend<STATE>>>;
+
+thread_local! {
+    static BACKEND_FACTORY: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+/// Sets how route agents create the history that they navigate through.
+///
+/// This must be called before the first route agent is created, which happens when the first
+/// `Router` or `RouteAgent` bridge is created.
+/// If it isn't called, agents navigate through a `BrowserHistory` or a `HashHistory`, depending
+/// on the [routing mode](../service/fn.set_routing_mode.html).
+/// They also do if their state type differs from `STATE`, which is logged as an error.
+pub fn set_history_backend<STATE, H, F>(factory: F)
+where
+    STATE: RouteState,
+    H: HistoryBackend<STATE> + 'static,
+    F: Fn() -> H + 'static,
+{
+    let factory: BackendFactory<STATE> = Rc::new(move || Box::new(factory()));
+    BACKEND_FACTORY.with(|cell| *cell.borrow_mut() = Some(Box::new(factory)));
+}
+
+/// Creates the history that route agents navigate through.
+///
+/// See `set_history_backend`.
+pub fn create_history_backend<STATE: RouteState>() -> Box<dyn HistoryBackend<STATE>> {
+    let (is_set, factory) = BACKEND_FACTORY.with(|cell| {
+        let factory = cell.borrow();
+        let factory = factory.as_ref();
+        (
+            factory.is_some(),
+            factory
+                .and_then(|factory| factory.downcast_ref::<BackendFactory<STATE>>())
+                .cloned(),
+        )
+    });
+    if let Some(factory) = factory {
+        return factory();
+    }
+    if is_set {
+        log::error!(
+            "The history backend was set for another state type than `{}`, \
+             so the browser's history is used instead",
+            std::any::type_name::<STATE>()
+        );
+    }
+    match routing_mode() {
+        RoutingMode::History => Box::new(BrowserHistory::new()),
+        RoutingMode::Hash => Box::new(HashHistory::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes(history: &MemoryHistory<()>) -> Vec<String> {
+        history
+            .entries()
+            .into_iter()
+            .map(|route| route.route)
+            .collect()
+    }
+
+    #[test]
+    fn push_removes_forward_entries() {
+        let mut history = MemoryHistory::new("/");
+        history.push("/a", ());
+        history.push("/b", ());
+        assert!(history.go(-1));
+        history.push("/c", ());
+        assert_eq!(routes(&history), vec!["/", "/a", "/c"]);
+        assert_eq!(history.index(), 2);
+    }
+
+    #[test]
+    fn replace_keeps_index() {
+        let mut history = MemoryHistory::new("/");
+        history.push("/a", ());
+        history.replace("/b", ());
+        assert_eq!(routes(&history), vec!["/", "/b"]);
+        assert_eq!(history.current().route, "/b");
+    }
+
+    #[test]
+    fn go_out_of_range_does_nothing() {
+        let mut history = MemoryHistory::new("/");
+        history.push("/a", ());
+        assert!(!history.go(-2));
+        assert!(!history.go(1));
+        assert!(!history.go(0));
+        assert_eq!(history.current().route, "/a");
+    }
+
+    #[test]
+    fn go_notifies_subscribers() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut history = MemoryHistory::new("/");
+        let seen_by_callback = seen.clone();
+        history.subscribe(Callback::from(move |route: Route<()>| {
+            seen_by_callback.borrow_mut().push(route.route)
+        }));
+        history.push("/a", ());
+        history.push("/b", ());
+        history.go(-2);
+        history.go(1);
+        assert_eq!(*seen.borrow(), vec!["/", "/a"]);
+    }
+
+    #[test]
+    fn clones_share_entries() {
+        let history = MemoryHistory::new("/");
+        let mut clone = history.clone();
+        clone.push("/a", ());
+        assert_eq!(history.current().route, "/a");
+    }
+}
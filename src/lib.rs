@@ -63,6 +63,12 @@ mod alias;
 #[cfg(feature = "service")]
 pub mod service;
 
+#[cfg(feature = "service")]
+pub mod history;
+
+#[cfg(feature = "service")]
+pub mod state_codec;
+
 #[cfg(feature = "agent")]
 pub mod agent;
 
@@ -0,0 +1,113 @@
+//! Percent-encoding and decoding of captured route sections.
+//!
+//! Captured sections are decoded by `Switch::from_route_capture`,
+//! and fields are encoded when they are written by `Switch::build_route_capture`,
+//! so that any string survives a round trip through a route.
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Everything besides the RFC 3986 unreserved characters and the sub-delimiters that have no
+/// meaning within a matcher string are escaped.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b',')
+    .remove(b';')
+    .remove(b':')
+    .remove(b'@');
+
+/// Same as `SEGMENT`, but `/` is left alone so values can span multiple path sections.
+const SECTIONS: &AsciiSet = &SEGMENT.remove(b'/');
+
+/// Determines which characters are escaped when a value is written into a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// The value must stay within one path section, so `/` is escaped.
+    ///
+    /// Used for `{}` and `{name}` captures.
+    Segment,
+    /// The value may span multiple path sections, so `/` is kept.
+    ///
+    /// Used for `{*}`, `{*:name}`, `{4}` and `{4:name}` captures.
+    Sections,
+}
+
+/// Percent-encodes `value` and appends it to `buf`.
+pub fn encode_into(buf: &mut String, value: &str, encoding: Encoding) {
+    let set = match encoding {
+        Encoding::Segment => SEGMENT,
+        Encoding::Sections => SECTIONS,
+    };
+    buf.extend(utf8_percent_encode(value, set));
+}
+
+/// Decodes a percent-encoded section of a route.
+///
+/// Sequences that don't decode to valid UTF-8 are replaced with `U+FFFD`.
+pub fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+/// Decodes a key or value of the query of a route, where a `+` stands for a space,
+/// as in form-encoded queries.
+pub fn decode_query(value: &str) -> String {
+    decode(&value.replace('+', " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &str, encoding: Encoding) -> String {
+        let mut buf = String::new();
+        encode_into(&mut buf, value, encoding);
+        buf
+    }
+
+    #[test]
+    fn segment_escapes_separators() {
+        assert_eq!(
+            encode("a/b?c#d&e=f g", Encoding::Segment),
+            "a%2Fb%3Fc%23d%26e%3Df%20g"
+        );
+    }
+
+    #[test]
+    fn sections_keep_slash() {
+        assert_eq!(encode("a/b?c", Encoding::Sections), "a/b%3Fc");
+    }
+
+    #[test]
+    fn unreserved_are_untouched() {
+        assert_eq!(encode("aZ09-._~", Encoding::Segment), "aZ09-._~");
+    }
+
+    #[test]
+    fn escapes_percent() {
+        assert_eq!(encode("100%", Encoding::Segment), "100%25");
+    }
+
+    #[test]
+    fn decodes_utf8() {
+        assert_eq!(decode("J%C3%BCrgen"), "Jürgen");
+    }
+
+    #[test]
+    fn decodes_plus_in_query() {
+        assert_eq!(decode_query("a+b%2Bc"), "a b+c");
+        assert_eq!(decode("a+b"), "a+b");
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = "Jürgen / {weird} ?query=#frag% *";
+        assert_eq!(decode(&encode(value, Encoding::Segment)), value);
+        assert_eq!(decode(&encode(value, Encoding::Sections)), value);
+    }
+}
@@ -0,0 +1,151 @@
+//! Reports explaining why a route did or didn't match.
+//!
+//! These are returned by `RouteMatcher::explain` and `Switch::explain_route`,
+//! and are meant for debugging routes, either in tests or in logs.
+use std::fmt;
+use yew_router_route_parser::MatcherToken;
+
+/// Explains how a route was matched by a `RouteMatcher`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchReport {
+    /// Why the route didn't match, or `None` if it did.
+    pub mismatch: Option<Mismatch>,
+    /// The captured keys and values, in the order they were captured.
+    ///
+    /// Unnamed captures have an empty key. This is empty if the route didn't match.
+    pub captures: Vec<(String, String)>,
+    /// The end of the route that was left over after matching.
+    ///
+    /// It is ignored, because the matcher string doesn't end with `!`.
+    /// This is empty if the route didn't match.
+    pub unmatched: String,
+}
+
+impl MatchReport {
+    /// Whether the route matched.
+    pub fn is_match(&self) -> bool {
+        self.mismatch.is_none()
+    }
+}
+
+/// The point at which a route failed to match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The token that failed to match.
+    ///
+    /// This is `None` if the route was rejected as a whole,
+    /// either by the trailing slash setting, or because a captured value couldn't be converted.
+    pub token: Option<MatcherToken>,
+    /// The byte offset within the route at which matching failed.
+    ///
+    /// When a matcher has optional sections, this is the furthest point that any attempt reached.
+    /// Conversions happen after the whole route was matched,
+    /// so for those this is the offset up to which the route was matched.
+    pub offset: usize,
+    /// Why the token failed to match.
+    pub reason: MismatchReason,
+}
+
+/// Why a route failed to match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MismatchReason {
+    /// The route doesn't continue with the literal.
+    Literal,
+    /// Nothing that the capture accepts was found.
+    Capture,
+    /// The captured value doesn't satisfy the constraint of the capture.
+    Constraint {
+        /// The captured value, after percent-decoding.
+        value: String,
+    },
+    /// The route doesn't continue with a query section.
+    Query,
+    /// A query parameter is missing, or doesn't have the value in the matcher string.
+    QueryParameter {
+        /// The key of the parameter.
+        key: String,
+    },
+    /// The query has a parameter that isn't in the matcher string,
+    /// which isn't allowed because the query is followed by `!`.
+    UnknownQueryParameter {
+        /// The key of the parameter.
+        key: String,
+    },
+    /// Part of the route was left over where the matcher string ends with `!`.
+    LeftoverInput {
+        /// The part of the route that was left over.
+        remaining: String,
+    },
+    /// The path doesn't end with a slash, which is required by the trailing slash setting.
+    TrailingSlashRequired,
+    /// The route matched, but a captured value couldn't be converted into the type of its field,
+    /// for example because its `FromStr` implementation rejected it.
+    Conversion {
+        /// The name of the field, or its index for fields without names.
+        field: String,
+        /// The captured value, if the field holds a single value.
+        value: Option<String>,
+    },
+}
+
+/// Explains how a route was matched by a variant of a derived `Switch`,
+/// as returned by `Switch::explain_route`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantReport {
+    /// The name of the variant, or of the struct.
+    pub variant: &'static str,
+    /// How the route was matched.
+    pub report: MatchReport,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.reason, self.offset)?;
+        if let Some(token) = &self.token {
+            write!(f, " (token: {:?})", token)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MismatchReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MismatchReason::Literal => f.write_str("The route doesn't continue with the literal"),
+            MismatchReason::Capture => f.write_str("Nothing could be captured"),
+            MismatchReason::Constraint { value } => write!(
+                f,
+                "The captured value '{}' doesn't satisfy the constraint",
+                value
+            ),
+            MismatchReason::Query => f.write_str("The route doesn't continue with a query"),
+            MismatchReason::QueryParameter { key } => {
+                write!(f, "The query parameter '{}' is missing or differs", key)
+            }
+            MismatchReason::UnknownQueryParameter { key } => write!(
+                f,
+                "The query parameter '{}' isn't allowed after the query's '!'",
+                key
+            ),
+            MismatchReason::LeftoverInput { remaining } => {
+                write!(f, "'{}' is left over at the '!'", remaining)
+            }
+            MismatchReason::TrailingSlashRequired => {
+                f.write_str("The path must end with a trailing slash")
+            }
+            MismatchReason::Conversion {
+                field,
+                value: Some(value),
+            } => write!(
+                f,
+                "The captured value '{}' couldn't be converted for the field '{}'",
+                value, field
+            ),
+            MismatchReason::Conversion { field, value: None } => write!(
+                f,
+                "The captured values couldn't be converted for the field '{}'",
+                field
+            ),
+        }
+    }
+}
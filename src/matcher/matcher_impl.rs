@@ -1,23 +1,40 @@
 use crate::matcher::{
-    util::{consume_until, next_delimiter, tag_possibly_case_sensitive},
-    Captures, MatcherSettings,
+    encoding::{decode, decode_query},
+    explain::{MatchReport, Mismatch, MismatchReason},
+    util::{next_delimiter, satisfies_constraint, tag_possibly_case_sensitive, TokenCursor},
+    Captures, MatcherSettings, MultiCaptures, TrailingSlash,
 };
 use log::trace;
 use nom::{
     bytes::complete::{is_not, tag},
-    combinator::map,
     error::ErrorKind,
     sequence::terminated,
     IResult,
 };
-use std::{iter::Peekable, slice::Iter};
-use yew_router_route_parser::{CaptureVariant, MatcherToken};
+use std::{cell::RefCell, iter::once};
+use yew_router_route_parser::{
+    CaptureConstraint, CaptureVariant, MatcherToken, QueryParameter, QueryValue,
+};
 
-/// Allows abstracting over capturing into a HashMap (Captures) or a Vec.
+/// Allows abstracting over capturing into a HashMap (Captures), a MultiCaptures, or a Vec.
 trait CaptureCollection<'a> {
     fn new2() -> Self;
     fn insert2(&mut self, key: &'a str, value: String);
     fn extend2(&mut self, other: Self);
+
+    /// Inserts every value captured for a repeated query parameter.
+    ///
+    /// Collections that only hold one value per capture keep the first one.
+    fn insert_many2(&mut self, key: &'a str, values: Vec<String>) {
+        if let Some(value) = values.into_iter().next() {
+            self.insert2(key, value)
+        }
+    }
+
+    /// Inserts a query parameter that isn't part of the matcher.
+    ///
+    /// Most collections have no use for these, so they are discarded by default.
+    fn insert_unmatched_query2(&mut self, _key: String, _value: String) {}
 }
 
 impl<'a> CaptureCollection<'a> for Captures<'a> {
@@ -34,6 +51,49 @@ impl<'a> CaptureCollection<'a> for Captures<'a> {
     }
 }
 
+impl<'a> CaptureCollection<'a> for MultiCaptures<'a> {
+    fn new2() -> Self {
+        MultiCaptures::default()
+    }
+
+    fn insert2(&mut self, key: &'a str, value: String) {
+        self.captures.entry(key).or_default().push(value);
+    }
+
+    fn extend2(&mut self, other: Self) {
+        for (key, values) in other.captures {
+            self.captures.entry(key).or_default().extend(values);
+        }
+        self.unmatched_query.extend(other.unmatched_query);
+    }
+
+    fn insert_many2(&mut self, key: &'a str, values: Vec<String>) {
+        self.captures.entry(key).or_default().extend(values);
+    }
+
+    fn insert_unmatched_query2(&mut self, key: String, value: String) {
+        self.unmatched_query.push((key, value));
+    }
+}
+
+impl<'a> CaptureCollection<'a> for Vec<(&'a str, String)> {
+    fn new2() -> Self {
+        Vec::new()
+    }
+
+    fn insert2(&mut self, key: &'a str, value: String) {
+        self.push((key, value))
+    }
+
+    fn extend2(&mut self, other: Self) {
+        self.extend(other)
+    }
+
+    fn insert_many2(&mut self, key: &'a str, values: Vec<String>) {
+        self.extend(values.into_iter().map(|value| (key, value)))
+    }
+}
+
 impl<'a> CaptureCollection<'a> for Vec<String> {
     fn new2() -> Self {
         Vec::new()
@@ -56,6 +116,14 @@ pub(super) fn match_into_map<'a, 'b: 'a>(
     move |i: &str| matcher_impl(tokens, *settings, i)
 }
 
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub(super) fn match_into_multimap<'a, 'b: 'a>(
+    tokens: &'b [MatcherToken],
+    settings: &'b MatcherSettings,
+) -> impl Fn(&'a str) -> IResult<&'a str, MultiCaptures<'b>> {
+    move |i: &str| matcher_impl(tokens, *settings, i)
+}
+
 #[allow(clippy::trivially_copy_pass_by_ref)]
 pub(super) fn match_into_vec<'a, 'b: 'a>(
     tokens: &'b [MatcherToken],
@@ -67,147 +135,563 @@ pub(super) fn match_into_vec<'a, 'b: 'a>(
 fn matcher_impl<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
     tokens: &'b [MatcherToken],
     settings: MatcherSettings,
-    mut i: &'a str,
+    i: &'a str,
 ) -> IResult<&'a str, CAP> {
-    trace!("Attempting to match route: {:?} using: {:?}", i, tokens);
+    match_route(tokens, settings, i, None)
+}
+
+/// Matches the route, explaining where and why it failed to match.
+pub(super) fn explain(
+    tokens: &[MatcherToken],
+    settings: MatcherSettings,
+    route: &str,
+) -> MatchReport {
+    let log = FailureLog {
+        route,
+        furthest: RefCell::new(None),
+    };
+    match match_route::<Vec<(&str, String)>>(tokens, settings, route, Some(&log)) {
+        Ok((unmatched, captures)) => MatchReport {
+            mismatch: None,
+            captures: captures
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            unmatched: unmatched.to_string(),
+        },
+        Err(_) => MatchReport {
+            mismatch: log.furthest.into_inner(),
+            captures: vec![],
+            unmatched: String::new(),
+        },
+    }
+}
+
+/// Records the furthest point at which matching failed, so that it can be explained.
+///
+/// Every failure is recorded before it is returned, so if matching fails, a mismatch is known.
+struct FailureLog<'a> {
+    route: &'a str,
+    furthest: RefCell<Option<Mismatch>>,
+}
+
+impl<'a> FailureLog<'a> {
+    /// Records a failure at the start of the remaining input, unless one was recorded at or after
+    /// it already.
+    ///
+    /// Captures and queries record their specific reasons without knowing their token,
+    /// which is filled in once the token's failure is recorded at the same offset.
+    fn record(&self, token: Option<&MatcherToken>, remaining: &str, reason: MismatchReason) {
+        let offset = self.route.len() - remaining.len();
+        let mut furthest = self.furthest.borrow_mut();
+        match furthest.as_mut() {
+            Some(mismatch) if mismatch.offset == offset && mismatch.token.is_none() => {
+                mismatch.token = token.cloned();
+            }
+            Some(mismatch) if mismatch.offset >= offset => {}
+            _ => {
+                *furthest = Some(Mismatch {
+                    token: token.cloned(),
+                    offset,
+                    reason,
+                })
+            }
+        }
+    }
 
-    let mut iter = tokens.iter().peekable();
+    /// Records a token that failed, with a reason that only depends on the kind of token.
+    fn record_error(
+        &self,
+        token: &MatcherToken,
+        i: &str,
+        error: &nom::Err<(&str, ErrorKind)>,
+        settings: MatcherSettings,
+    ) {
+        let remaining = match error {
+            nom::Err::Error((remaining, _)) | nom::Err::Failure((remaining, _)) => remaining,
+            nom::Err::Incomplete(_) => i,
+        };
+        match token {
+            MatcherToken::Exact(literal) => {
+                // Point at the first character that differs from the literal.
+                let matching: usize = literal
+                    .chars()
+                    .zip(i.chars())
+                    .take_while(|(a, b)| {
+                        a == b || (settings.case_insensitive && a.eq_ignore_ascii_case(b))
+                    })
+                    .map(|(_, b)| b.len_utf8())
+                    .sum();
+                self.record(Some(token), &i[matching..], MismatchReason::Literal)
+            }
+            MatcherToken::Capture(_) => {
+                self.record(Some(token), remaining, MismatchReason::Capture)
+            }
+            MatcherToken::Query { .. } => {
+                self.record(Some(token), remaining, MismatchReason::Query)
+            }
+            MatcherToken::End => self.record(
+                Some(token),
+                remaining,
+                MismatchReason::LeftoverInput {
+                    remaining: remaining.to_string(),
+                },
+            ),
+            // Optional sections don't fail themselves, the tokens within or after them do.
+            MatcherToken::Optional(_) => {}
+        }
+    }
+}
+
+/// Matches the route, recording why it failed if a log is provided.
+fn match_route<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+    tokens: &'b [MatcherToken],
+    settings: MatcherSettings,
+    i: &'a str,
+    log: Option<&FailureLog>,
+) -> IResult<&'a str, CAP> {
+    trace!("Attempting to match route: {:?} using: {:?}", i, tokens);
+    if settings.trailing_slash == TrailingSlash::Require && !path_section(i).ends_with('/') {
+        if let Some(log) = log {
+            let path_end = &i[path_section(i).len()..];
+            log.record(None, path_end, MismatchReason::TrailingSlashRequired);
+        }
+        return Err(nom::Err::Error((i, ErrorKind::Verify)));
+    }
+    let (i, captures) = match_cursor(TokenCursor::new(tokens), settings, i, log)?;
+    trace!("Route Matched");
+    Ok((i, captures))
+}
 
+/// Matches the tokens remaining in the cursor.
+///
+/// When an optional section is encountered, the remainder of the route is first matched with the
+/// optional section included, and if that fails, it is matched again with the section skipped.
+fn match_cursor<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+    mut iter: TokenCursor<'b, '_>,
+    settings: MatcherSettings,
+    mut i: &'a str,
+    log: Option<&FailureLog>,
+) -> IResult<&'a str, CAP> {
     let mut captures: CAP = CAP::new2();
 
     while let Some(token) = iter.next() {
-        i = match token {
+        let result = match token {
             MatcherToken::Exact(literal) => {
                 trace!("Matching '{}' against literal: '{}'", i, literal);
-                tag_possibly_case_sensitive(literal.as_str(), !settings.case_insensitive)(i)?.0
+                match_literal(i, literal, &iter, settings)
             }
-            MatcherToken::Capture(capture) => match &capture {
-                CaptureVariant::Named(name) => capture_named(i, &mut iter, &name, &mut captures)?,
-                CaptureVariant::ManyNamed(name) => {
-                    capture_many_named(i, &mut iter, &name, &mut captures)?
-                }
-                CaptureVariant::NumberedNamed { sections, name } => {
-                    capture_numbered_named(i, &mut iter, Some((&name, &mut captures)), *sections)?
+            MatcherToken::Capture(capture) => {
+                let (capture_key, candidates) = match &capture {
+                    CaptureVariant::Named { name, constraint } => (
+                        name.as_str(),
+                        capture_named(i, &iter, settings, constraint.as_ref(), log),
+                    ),
+                    CaptureVariant::ManyNamed(name) => {
+                        (name.as_str(), capture_many_named(i, &iter, settings))
+                    }
+                    CaptureVariant::NumberedNamed { sections, name } => (
+                        name.as_str(),
+                        capture_numbered_named(i, &iter, settings, *sections),
+                    ),
+                    CaptureVariant::Unnamed => ("", capture_named(i, &iter, settings, None, log)),
+                    CaptureVariant::ManyUnnamed => ("", capture_many_named(i, &iter, settings)),
+                    CaptureVariant::NumberedUnnamed { sections } => {
+                        ("", capture_numbered_named(i, &iter, settings, *sections))
+                    }
+                };
+                match candidates {
+                    Ok(candidates) => {
+                        return match_after_capture(
+                            iter,
+                            settings,
+                            capture_key,
+                            candidates,
+                            captures,
+                            log,
+                        )
+                    }
+                    Err(error) => Err(error),
                 }
-                CaptureVariant::Unnamed => capture_named(i, &mut iter, "", &mut captures)?,
-                CaptureVariant::ManyUnnamed => capture_many_named(i, &mut iter, "", &mut captures)?,
-                CaptureVariant::NumberedUnnamed { sections } => {
-                    capture_numbered_named(i, &mut iter, Some(("", &mut captures)), *sections)?
-                }
-            },
+            }
             MatcherToken::End => {
+                let i = skip_trailing_slash(i, settings);
                 if !i.is_empty() {
                     // this is approximately correct, but ultimately doesn't matter
-                    return Err(nom::Err::Failure((i, ErrorKind::Eof)));
+                    Err(nom::Err::Failure((i, ErrorKind::Eof)))
                 } else {
-                    i
+                    Ok(i)
+                }
+            }
+            MatcherToken::Query {
+                parameters,
+                deny_unknown,
+                ..
+            } => {
+                let i = skip_trailing_slash(i, settings);
+                capture_query(i, parameters, *deny_unknown, settings, &mut captures, log)
+            }
+            MatcherToken::Optional(tokens) => {
+                trace!("Matching '{}' against optional section: {:?}", i, tokens);
+                let (ii, rest_captures) =
+                    match_cursor::<CAP>(TokenCursor::followed_by(tokens, &iter), settings, i, log)
+                        .or_else(|_| {
+                            trace!("Skipping optional section");
+                            match_cursor::<CAP>(iter, settings, i, log)
+                        })?;
+                captures.extend2(rest_captures);
+                return Ok((ii, captures));
+            }
+        };
+        i = match result {
+            Ok(ii) => ii,
+            Err(error) => {
+                if let Some(log) = log {
+                    log.record_error(token, i, &error, settings);
                 }
+                return Err(error);
             }
         };
     }
-    trace!("Route Matched");
 
     Ok((i, captures))
 }
 
-fn capture_named<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+/// Matches a literal.
+///
+/// If the trailing slash is lenient, a literal that ends the path section may also match without
+/// its trailing slash.
+fn match_literal<'a>(
     i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
-    capture_key: &'b str,
-    matches: &mut CAP,
+    literal: &'a str,
+    iter: &TokenCursor,
+    settings: MatcherSettings,
 ) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
-    log::trace!("Matching Named ({})", capture_key);
-    if let Some(_peaked_next_token) = iter.peek() {
-        let delimiter = next_delimiter(iter);
-        let (ii, captured) = consume_until(delimiter)(i)?;
-        matches.insert2(capture_key, captured);
-        Ok(ii)
+    let is_sensitive = !settings.case_insensitive;
+    let result = tag_possibly_case_sensitive(literal, is_sensitive)(i).map(|(ii, _)| ii);
+    let ends_path = match iter.peek() {
+        None | Some(MatcherToken::End) | Some(MatcherToken::Query { .. }) => true,
+        _ => false,
+    };
+    if result.is_err()
+        && settings.trailing_slash.is_lenient()
+        && ends_path
+        && literal.len() > 1
+        && literal.ends_with('/')
+    {
+        let without_slash = &literal[..literal.len() - 1];
+        let (ii, _) = tag_possibly_case_sensitive(without_slash, is_sensitive)(i)?;
+        if path_section(ii).is_empty() {
+            return Ok(ii);
+        }
+    }
+    result
+}
+
+/// Skips a slash that ends the path section, if the trailing slash is lenient.
+fn skip_trailing_slash(i: &str, settings: MatcherSettings) -> &str {
+    if settings.trailing_slash.is_lenient() && path_section(i) == "/" {
+        &i[1..]
     } else {
-        let (ii, captured) = map(valid_capture_characters, String::from)(i)?;
-        matches.insert2(capture_key, captured);
-        Ok(ii)
+        i
     }
 }
 
-fn capture_many_named<'a, 'b, CAP: CaptureCollection<'b>>(
+/// Gets the part of the route before the query and fragment.
+fn path_section(i: &str) -> &str {
+    match i.find(&['?', '#'][..]) {
+        Some(index) => &i[..index],
+        None => i,
+    }
+}
+
+/// A value that a capture could take, and the input that follows it.
+type Candidate<'a> = (&'a str, String);
+
+/// Finds the values that a single section capture could take, longest first.
+///
+/// The capture can't extend past the end of its section, and must be followed by one of the
+/// delimiters that may come next, so a literal may appear several times within the section,
+/// as in `{stem}.{ext}`.
+/// Values that don't satisfy the constraint are left out.
+fn capture_named<'a>(
     i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
+    iter: &TokenCursor,
+    settings: MatcherSettings,
+    constraint: Option<&CaptureConstraint>,
+    log: Option<&FailureLog>,
+) -> Result<Vec<Candidate<'a>>, nom::Err<(&'a str, ErrorKind)>> {
+    let candidates = if let Some(_peaked_next_token) = iter.peek() {
+        capture_candidates(i, iter, settings, INVALID_CHARACTERS)?
+    } else {
+        let (ii, captured) = valid_capture_characters(i)?;
+        vec![(ii, captured.to_string())]
+    };
+    let constraint = match constraint {
+        Some(constraint) => constraint,
+        None => return Ok(candidates),
+    };
+    let longest = decode(&candidates[0].1);
+    let candidates: Vec<_> = candidates
+        .into_iter()
+        .filter(|(_, captured)| satisfies_constraint(constraint, &decode(captured)))
+        .collect();
+    if candidates.is_empty() {
+        trace!(
+            "'{}' does not satisfy constraint: {:?}",
+            longest,
+            constraint
+        );
+        if let Some(log) = log {
+            log.record(None, i, MismatchReason::Constraint { value: longest });
+        }
+        return Err(nom::Err::Error((i, ErrorKind::Verify)));
+    }
+    Ok(candidates)
+}
+
+/// Finds the values that can be captured before one of the delimiters that may come next,
+/// longest first.
+///
+/// The captured value can't contain any of the `invalid_characters`.
+fn capture_candidates<'a>(
+    i: &'a str,
+    iter: &TokenCursor,
+    settings: MatcherSettings,
+    invalid_characters: &'static str,
+) -> Result<Vec<Candidate<'a>>, nom::Err<(&'a str, ErrorKind)>> {
+    let end = i
+        .find(|c| invalid_characters.contains(c))
+        .unwrap_or(i.len());
+    let delimiter = next_delimiter(iter, invalid_characters, settings);
+    let candidates: Vec<_> = i[..end]
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(once(end))
+        .rev()
+        .filter(|&index| delimiter(&i[index..]).is_ok())
+        .map(|index| (&i[index..], i[..index].to_string()))
+        .collect();
+    if candidates.is_empty() {
+        return Err(nom::Err::Error((&i[end..], ErrorKind::Eof)));
+    }
+    Ok(candidates)
+}
+
+/// Matches the rest of the route after each of the values a capture could take, in turn,
+/// keeping the first value that lets the rest of the route match.
+fn match_after_capture<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+    iter: TokenCursor<'b, '_>,
+    settings: MatcherSettings,
     capture_key: &'b str,
-    matches: &mut CAP,
-) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
-    log::trace!("Matching ManyUnnamed ({})", capture_key);
+    candidates: Vec<Candidate<'a>>,
+    mut captures: CAP,
+    log: Option<&FailureLog>,
+) -> IResult<&'a str, CAP> {
+    let mut error = nom::Err::Error(("", ErrorKind::Eof));
+    for (i, captured) in candidates {
+        trace!("Trying '{}' for capture: '{}'", captured, capture_key);
+        match match_cursor::<CAP>(iter, settings, i, log) {
+            Ok((i, rest_captures)) => {
+                captures.insert2(capture_key, captured);
+                captures.extend2(rest_captures);
+                return Ok((i, captures));
+            }
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// Finds the values that a capture spanning many sections could take, shortest first.
+///
+/// The capture may contain slashes, so the literal that follows it may be found in several places,
+/// as in `/{*:path}/edit!` matching `/a/edit/b/edit`.
+/// It takes the first of them, unless the rest of the route only matches after a later one.
+fn capture_many_named<'a>(
+    i: &'a str,
+    iter: &TokenCursor,
+    settings: MatcherSettings,
+) -> Result<Vec<Candidate<'a>>, nom::Err<(&'a str, ErrorKind)>> {
     if let Some(_peaked_next_token) = iter.peek() {
-        let delimiter = next_delimiter(iter);
-        let (ii, captured) = consume_until(delimiter)(i)?;
-        matches.insert2(&capture_key, captured);
-        Ok(ii)
+        let mut candidates = capture_candidates(i, iter, settings, INVALID_MANY_CHARACTERS)?;
+        candidates.reverse();
+        Ok(candidates)
     } else if i.is_empty() {
         // If the route string is empty, return an empty value.
-        matches.insert2(&capture_key, "".to_string());
-        Ok(i) // Match even if nothing is left
+        Ok(vec![(i, "".to_string())]) // Match even if nothing is left
     } else {
-        let (ii, c) = map(valid_many_capture_characters, String::from)(i)?;
-        matches.insert2(&capture_key, c);
-        Ok(ii)
+        let (ii, c) = valid_many_capture_characters(i)?;
+        Ok(vec![(ii, c.to_string())])
     }
 }
 
-fn capture_numbered_named<'a, 'b, CAP: CaptureCollection<'b>>(
+/// Finds the values that a capture spanning a number of sections could take, longest first.
+///
+/// Every section but the last must end with a slash,
+/// and the last one is captured like a single section capture.
+fn capture_numbered_named<'a>(
     mut i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
-    name_and_captures: Option<(&'b str, &mut CAP)>,
-    mut sections: usize,
-) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
+    iter: &TokenCursor,
+    settings: MatcherSettings,
+    sections: usize,
+) -> Result<Vec<Candidate<'a>>, nom::Err<(&'a str, ErrorKind)>> {
     log::trace!("Matching NumberedNamed ({})", sections);
-    let mut captured = "".to_string();
+    if sections == 0 {
+        return Ok(vec![(i, "".to_string())]);
+    }
+    let start = i;
+    for _ in 1..sections {
+        let (ii, _) = terminated(valid_capture_characters, tag("/"))(i)?;
+        i = ii;
+    }
+    let leading_sections = &start[..start.len() - i.len()];
+    let candidates = capture_named(i, iter, settings, None, None)?
+        .into_iter()
+        .map(|(ii, captured)| (ii, leading_sections.to_string() + &captured))
+        .collect();
+    Ok(candidates)
+}
 
-    if let Some(_peaked_next_token) = iter.peek() {
-        while sections > 0 {
-            if sections > 1 {
-                let (ii, c) = terminated(valid_capture_characters, tag("/"))(i)?;
-                i = ii;
-                captured += c;
-                captured += "/";
-            } else {
-                let delimiter = next_delimiter(iter);
-                let (ii, c) = consume_until(delimiter)(i)?;
-                i = ii;
-                captured += &c;
+/// Matches the query section by key, consuming it up to the fragment.
+///
+/// Parameters may appear in any order, and for repeated keys, the first occurrence is used,
+/// unless the parameter is captured with `{*}` or `{*:name}`, which captures every occurrence.
+/// A route without a query section is treated as having no parameters.
+fn capture_query<'a, 'b, CAP: CaptureCollection<'b>>(
+    i: &'a str,
+    parameters: &'b [QueryParameter],
+    deny_unknown: bool,
+    settings: MatcherSettings,
+    matches: &mut CAP,
+    log: Option<&FailureLog>,
+) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
+    log::trace!("Matching Query ({:?})", parameters);
+    let (query, rest) = if i.starts_with('?') || i.starts_with('&') {
+        let end = i.find('#').unwrap_or(i.len());
+        (&i[1..end], &i[end..])
+    } else if i.is_empty() || i.starts_with('#') {
+        ("", i)
+    } else {
+        return Err(nom::Err::Error((i, ErrorKind::Tag)));
+    };
+
+    // Keys are compared once they are decoded. Values are captured still encoded, like other
+    // captures, but with the `+`s that stand for spaces escaped, so they are decoded as spaces.
+    let pairs: Vec<(String, String)> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut split = pair.splitn(2, '=');
+            let key = decode_query(split.next().unwrap_or(""));
+            let value = split.next().unwrap_or("").replace('+', "%20");
+            (key, value)
+        })
+        .collect();
+    let eq = |a: &str, b: &str| {
+        if settings.case_insensitive {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    };
+
+    if deny_unknown {
+        if let Some((key, _)) = pairs
+            .iter()
+            .find(|(key, _)| !parameters.iter().any(|p| eq(&p.key, key)))
+        {
+            trace!("Unknown query parameter: '{}'", key);
+            if let Some(log) = log {
+                let key = key.clone();
+                log.record(None, i, MismatchReason::UnknownQueryParameter { key });
             }
-            sections -= 1;
+            return Err(nom::Err::Error((i, ErrorKind::Verify)));
         }
-    } else {
-        while sections > 0 {
-            if sections > 1 {
-                let (ii, c) = terminated(valid_capture_characters, tag("/"))(i)?;
-                i = ii;
-                captured += c;
-                captured += "/";
-            } else {
-                // Don't consume the next character on the last section
-                let (ii, c) = valid_capture_characters(i)?;
-                i = ii;
-                captured += c;
+    }
+
+    for parameter in parameters {
+        let repeated_key = match &parameter.value {
+            QueryValue::Capture(CaptureVariant::ManyNamed(name)) => Some(name.as_str()),
+            QueryValue::Capture(CaptureVariant::ManyUnnamed) => Some(""),
+            _ => None,
+        };
+        if let Some(capture_key) = repeated_key {
+            let values = pairs
+                .iter()
+                .filter(|(key, _)| eq(&parameter.key, key))
+                .map(|(_, value)| value.clone())
+                .collect();
+            matches.insert_many2(capture_key, values);
+            continue;
+        }
+
+        let value = pairs
+            .iter()
+            .find(|(key, _)| eq(&parameter.key, key))
+            .map(|(_, value)| value.as_str());
+        match (&parameter.value, value) {
+            (QueryValue::Exact(expected), Some(value))
+                if eq(&decode_query(expected), &decode(value)) => {}
+            (QueryValue::Capture(capture), Some(value)) => {
+                let (capture_key, constraint) = match capture {
+                    CaptureVariant::Named { name, constraint } => {
+                        (name.as_str(), constraint.as_ref())
+                    }
+                    CaptureVariant::ManyNamed(name)
+                    | CaptureVariant::NumberedNamed { name, .. } => (name.as_str(), None),
+                    CaptureVariant::Unnamed
+                    | CaptureVariant::ManyUnnamed
+                    | CaptureVariant::NumberedUnnamed { .. } => ("", None),
+                };
+                if let Some(constraint) = constraint {
+                    let decoded = decode(value);
+                    if !satisfies_constraint(constraint, &decoded) {
+                        trace!(
+                            "'{}' does not satisfy constraint: {:?}",
+                            decoded,
+                            constraint
+                        );
+                        if let Some(log) = log {
+                            log.record(None, i, MismatchReason::Constraint { value: decoded });
+                        }
+                        return Err(nom::Err::Error((i, ErrorKind::Verify)));
+                    }
+                }
+                matches.insert2(capture_key, value.to_string());
+            }
+            (QueryValue::Capture(_), None) if parameter.optional => {}
+            _ => {
+                trace!("Query parameter '{}' did not match", parameter.key);
+                if let Some(log) = log {
+                    let key = parameter.key.clone();
+                    log.record(None, i, MismatchReason::QueryParameter { key });
+                }
+                return Err(nom::Err::Error((i, ErrorKind::Tag)));
             }
-            sections -= 1;
         }
     }
 
-    if let Some((name, captures)) = name_and_captures {
-        captures.insert2(&name, captured);
+    for (key, value) in pairs {
+        if !parameters.iter().any(|p| eq(&p.key, &key)) {
+            matches.insert_unmatched_query2(key, decode(&value));
+        }
     }
-    Ok(i)
+    Ok(rest)
 }
 
+/// Characters that can't be captured within a single section.
+const INVALID_CHARACTERS: &str = " */#&?{}=";
+/// Characters that can't be captured across many sections.
+const INVALID_MANY_CHARACTERS: &str = " #&?=";
+
 /// Characters that don't interfere with parsing logic for capturing characters
 fn valid_capture_characters(i: &str) -> IResult<&str, &str> {
-    const INVALID_CHARACTERS: &str = " */#&?{}=";
     is_not(INVALID_CHARACTERS)(i)
 }
 
 fn valid_many_capture_characters(i: &str) -> IResult<&str, &str> {
-    const INVALID_CHARACTERS: &str = " #&?=";
-    is_not(INVALID_CHARACTERS)(i)
+    is_not(INVALID_MANY_CHARACTERS)(i)
 }
 
 #[cfg(test)]
@@ -306,6 +790,64 @@ mod integration_test {
         assert_eq!(matches["cap"], "anything".to_string())
     }
 
+    #[test]
+    fn many_capture_backtracks_past_literal() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{*:path}/edit!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/a/edit/b/edit")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["path"], "a/edit/b".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/edit/b")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn many_capture_backtracks_for_constraint() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{*:path}/edit/{id:u32}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/a/edit/b/edit/3")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["path"], "a/edit/b".to_string());
+        assert_eq!(matches["id"], "3".to_string());
+    }
+
+    #[test]
+    fn many_captures_backtrack_together() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{*:a}/x/{*:b}/y!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Vec<String> = matcher_impl(&x, MatcherSettings::default(), "/1/x/2/y/3/x/4/y")
+            .expect("should match")
+            .1;
+        assert_eq!(matches, vec!["1".to_string(), "2/y/3/x/4".to_string()]);
+    }
+
+    #[test]
+    fn numbered_capture_backtracks_within_last_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{2:path}.{ext}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/a/b.c.d")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["path"], "a/b.c".to_string());
+        assert_eq!(matches["ext"], "d".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/b/c.d")
+            .expect_err("should not match");
+    }
+
     #[test]
     fn match_fragment() {
         let x = yew_router_route_parser::parse_str_and_optimize_tokens(
@@ -385,6 +927,276 @@ mod integration_test {
         matcher_impl::<Captures>(&x, settings, "/HeLLo").expect("should match");
     }
 
+    #[test]
+    fn named_capture_is_left_encoded() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/users/{name}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/users/J%C3%BCrgen")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["name"], "J%C3%BCrgen".to_string())
+    }
+
+    #[test]
+    fn escaped_slash_stays_within_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{cap}/thing",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/a%2Fb/thing")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["cap"], "a%2Fb".to_string())
+    }
+
+    #[test]
+    fn many_capture_is_left_encoded() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{*:cap}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/a/b%20c")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["cap"], "a/b%20c".to_string())
+    }
+
+    #[test]
+    fn type_constrained_capture() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/user/{id:u32}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/user/42")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["id"], "42".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/user/lorem")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn regex_constrained_capture() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            r"/post/{date:\d{4}-\d{2}-\d{2}}/{slug:[a-z0-9-]+}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            "/post/2020-03-14/hello-world",
+        )
+        .expect("should match")
+        .1;
+        assert_eq!(matches["date"], "2020-03-14".to_string());
+        assert_eq!(matches["slug"], "hello-world".to_string());
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            "/post/2020-3-14/hello-world",
+        )
+        .expect_err("should not match");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/post/2020-03-14/Hello")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn captures_within_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/files/{stem}.{ext}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/files/notes.txt")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["stem"], "notes".to_string());
+        assert_eq!(matches["ext"], "txt".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/files/notes")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn capture_takes_longest_value_within_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/files/{stem}.{ext}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures =
+            matcher_impl(&x, MatcherSettings::default(), "/files/archive.tar.gz")
+                .expect("should match")
+                .1;
+        assert_eq!(matches["stem"], "archive.tar".to_string());
+        assert_eq!(matches["ext"], "gz".to_string());
+    }
+
+    #[test]
+    fn capture_does_not_leave_section_for_delimiter() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/files/{stem}.{ext}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/files/dir/notes.txt")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn capture_backtracks_to_shorter_value() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/v{major:u32}.{minor:u32}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/v1.2")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["major"], "1".to_string());
+        assert_eq!(matches["minor"], "2".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/v1.2.3")
+            .expect_err("should not match");
+
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{name}.{ext}!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Vec<String> = matcher_impl(&x, MatcherSettings::default(), "/a.b.c")
+            .expect("should match")
+            .1;
+        assert_eq!(matches, vec!["a.b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn many_captures_within_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{year}-{month}-{day}/{slug}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Vec<String> =
+            matcher_impl(&x, MatcherSettings::default(), "/2020-03-14/hello-world")
+                .expect("should match")
+                .1;
+        assert_eq!(
+            matches,
+            vec![
+                "2020".to_string(),
+                "03".to_string(),
+                "14".to_string(),
+                "hello-world".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_section_present() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/posts[/page/{n}]!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/posts/page/3")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["n"], "3".to_string())
+    }
+
+    #[test]
+    fn optional_section_absent() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/posts[/page/{n}]!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/posts")
+            .expect("should match")
+            .1;
+        assert!(matches.is_empty())
+    }
+
+    #[test]
+    fn optional_section_skipped_when_constraint_fails() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/posts[/{n:u32}]/{slug}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/posts/hello")
+            .expect("should match")
+            .1;
+        assert_eq!(matches.get("n"), None);
+        assert_eq!(matches["slug"], "hello".to_string());
+
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/posts/4/hello")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["n"], "4".to_string());
+        assert_eq!(matches["slug"], "hello".to_string());
+    }
+
+    #[test]
+    fn capture_before_optional_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/{id}[/edit]",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let (rest, matches): (&str, Captures) =
+            matcher_impl(&x, MatcherSettings::default(), "/42/edit").expect("should match");
+        assert_eq!(matches["id"], "42".to_string());
+        assert_eq!(rest, "");
+
+        let (rest, matches): (&str, Captures) =
+            matcher_impl(&x, MatcherSettings::default(), "/42").expect("should match");
+        assert_eq!(matches["id"], "42".to_string());
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn nested_optional_sections() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/a[/{b}[/{c}]]!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Vec<String> = matcher_impl(&x, MatcherSettings::default(), "/a/1/2")
+            .expect("should match")
+            .1;
+        assert_eq!(matches, vec!["1".to_string(), "2".to_string()]);
+        let matches: Vec<String> = matcher_impl(&x, MatcherSettings::default(), "/a/1")
+            .expect("should match")
+            .1;
+        assert_eq!(matches, vec!["1".to_string()]);
+        let matches: Vec<String> = matcher_impl(&x, MatcherSettings::default(), "/a")
+            .expect("should match")
+            .1;
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn capture_before_end_token() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/lorem/{cap}!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/lorem/ipsum")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["cap"], "ipsum".to_string());
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/lorem/ipsum/dolor")
+            .expect_err("should not match");
+    }
+
     #[test]
     fn end_token() {
         let x = yew_router_route_parser::parse_str_and_optimize_tokens(
@@ -396,4 +1208,232 @@ mod integration_test {
         matcher_impl::<Captures>(&x, Default::default(), "/lorem/ipsum")
             .expect_err("should not match");
     }
+
+    #[test]
+    fn query_parameters_in_any_order() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}&page={page}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures =
+            matcher_impl(&x, MatcherSettings::default(), "/search?page=2&q=lorem")
+                .expect("should match")
+                .1;
+        assert_eq!(matches["query"], "lorem".to_string());
+        assert_eq!(matches["page"], "2".to_string());
+    }
+
+    #[test]
+    fn unknown_query_parameters_are_ignored() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            "/search?utm_source=mail&q=lorem",
+        )
+        .expect("should match")
+        .1;
+        assert_eq!(matches["query"], "lorem".to_string());
+    }
+
+    #[test]
+    fn unknown_query_parameters_are_rejected_before_end_token() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/search?q=lorem")
+            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            "/search?q=lorem&utm_source=mail",
+        )
+        .expect_err("should not match");
+    }
+
+    #[test]
+    fn encoded_query_keys_are_decoded() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/search?%71=lorem")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["query"], "lorem".to_string());
+    }
+
+    #[test]
+    fn plus_in_query_is_a_space() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}&sort=by+date",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let matches: MultiCaptures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            "/search?q=a+b%2Bc&sort=by+date&first+name=J",
+        )
+        .expect("should match")
+        .1;
+        assert_eq!(decode(&matches.captures["query"][0]), "a b+c");
+        assert_eq!(
+            matches.unmatched_query,
+            vec![("first name".to_string(), "J".to_string())]
+        );
+    }
+
+    #[test]
+    fn missing_query_parameter() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}&page={page}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/search?page=2")
+            .expect_err("should not match");
+
+        let mut x = x;
+        if let Some(MatcherToken::Query { parameters, .. }) = x.last_mut() {
+            parameters[0].optional = true;
+        }
+        let matches: Captures = matcher_impl(&x, MatcherSettings::default(), "/search?page=2")
+            .expect("should match")
+            .1;
+        assert_eq!(matches.get("query"), None);
+        assert_eq!(matches["page"], "2".to_string());
+    }
+
+    #[test]
+    fn exact_query_value() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?sort=new&q={query}",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/search?q=lorem&sort=new")
+            .expect("should match");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/search?q=lorem&sort=old")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn repeated_query_parameter() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/posts?tag={*:tags}",
+            FieldNamingScheme::Named,
+        )
+        .expect("Should parse");
+        let matches: MultiCaptures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            "/posts?tag=rust&page=2&tag=web%20dev",
+        )
+        .expect("should match")
+        .1;
+        assert_eq!(
+            matches.captures["tags"],
+            vec!["rust".to_string(), "web%20dev".to_string()]
+        );
+
+        let matches: MultiCaptures = matcher_impl(&x, MatcherSettings::default(), "/posts")
+            .expect("should match")
+            .1;
+        assert!(matches.captures["tags"].is_empty());
+    }
+
+    #[test]
+    fn unmatched_query_parameters() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/search?q={query}",
+            FieldNamingScheme::Named,
+        )
+        .expect("Should parse");
+        let matches: MultiCaptures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            "/search?utm_source=mail&q=lorem&ref=a%26b",
+        )
+        .expect("should match")
+        .1;
+        assert_eq!(matches.captures["query"], vec!["lorem".to_string()]);
+        assert_eq!(
+            matches.unmatched_query,
+            vec![
+                ("utm_source".to_string(), "mail".to_string()),
+                ("ref".to_string(), "a&b".to_string())
+            ]
+        );
+    }
+
+    fn trailing_slash(trailing_slash: TrailingSlash) -> MatcherSettings {
+        MatcherSettings {
+            trailing_slash,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn strict_trailing_slash() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/about!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let settings = trailing_slash(TrailingSlash::Strict);
+        matcher_impl::<Captures>(&x, settings, "/about").expect("should match");
+        matcher_impl::<Captures>(&x, settings, "/about/").expect_err("should not match");
+    }
+
+    #[test]
+    fn ignored_trailing_slash() {
+        let settings = trailing_slash(TrailingSlash::Ignore);
+        for matcher in &["/about!", "/about/!"] {
+            let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+                matcher,
+                FieldNamingScheme::Unnamed,
+            )
+            .expect("Should parse");
+            matcher_impl::<Captures>(&x, settings, "/about").expect("should match");
+            matcher_impl::<Captures>(&x, settings, "/about/").expect("should match");
+            matcher_impl::<Captures>(&x, settings, "/aboutx").expect_err("should not match");
+        }
+    }
+
+    #[test]
+    fn ignored_trailing_slash_after_capture() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/users/{id}?tab={tab}!",
+            FieldNamingScheme::Named,
+        )
+        .expect("Should parse");
+        let settings = trailing_slash(TrailingSlash::Ignore);
+        let matches: Captures = matcher_impl(&x, settings, "/users/1/?tab=posts")
+            .expect("should match")
+            .1;
+        assert_eq!(matches["id"], "1".to_string());
+        assert_eq!(matches["tab"], "posts".to_string());
+    }
+
+    #[test]
+    fn required_trailing_slash() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/about!",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let settings = trailing_slash(TrailingSlash::Require);
+        matcher_impl::<Captures>(&x, settings, "/about/").expect("should match");
+        matcher_impl::<Captures>(&x, settings, "/about").expect_err("should not match");
+        matcher_impl::<Captures>(&x, settings, "/about?q=/").expect_err("should not match");
+    }
 }
@@ -1,16 +1,112 @@
 //! Module for matching route strings based on tokens generated from the yew_router_route_parser
 //! crate.
 
+pub mod encoding;
+pub mod explain;
 mod matcher_impl;
+pub mod prefix_tree;
+pub mod urlencoded;
 mod util;
 
+use self::explain::MatchReport;
 use nom::IResult;
-use std::collections::HashSet;
-use yew_router_route_parser::{parse_str_and_optimize_tokens, PrettyParseError};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+use yew_router_route_parser::{matcher_string, parse_str_and_optimize_tokens, PrettyParseError};
 
-pub use yew_router_route_parser::{CaptureVariant, Captures, MatcherToken};
+pub use yew_router_route_parser::{
+    CaptureConstraint, CaptureVariant, Captures, MatcherToken, PrimitiveType, QueryParameter,
+    QueryValue,
+};
+
+/// Captures that can hold more than one value for each key.
+///
+/// Every occurrence of a query parameter captured with `{*:name}` is kept,
+/// as are the query parameters that aren't part of the matcher string.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MultiCaptures<'a> {
+    /// The values captured for each key, in the order they appear in the route.
+    pub captures: HashMap<&'a str, Vec<String>>,
+    /// Decoded query parameters that aren't part of the matcher string, in the order they appear
+    /// in the route.
+    pub unmatched_query: Vec<(String, String)>,
+}
+
+impl<'a> MultiCaptures<'a> {
+    /// Removes the values captured for the key, returning the first one.
+    pub fn remove_first(&mut self, key: &str) -> Option<String> {
+        self.captures
+            .remove(key)
+            .and_then(|values| values.into_iter().next())
+    }
+
+    /// Removes the values captured for the key, returning all of them.
+    pub fn remove_all(&mut self, key: &str) -> Vec<String> {
+        self.captures.remove(key).unwrap_or_default()
+    }
+}
+
+/// Error produced by `RouteMatcher::capture_into`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// The route didn't match.
+    NoMatch,
+    /// A captured value couldn't be converted into the type of its field,
+    /// or a field that isn't an `Option` wasn't captured.
+    Field {
+        /// The name of the field.
+        field: String,
+        /// Why the value couldn't be converted.
+        message: String,
+    },
+    /// The captures couldn't be deserialized into the type, for example because it isn't a struct
+    /// or map.
+    Deserialize(String),
+}
+
+impl From<urlencoded::Error> for CaptureError {
+    fn from(error: urlencoded::Error) -> Self {
+        match error.field() {
+            Some(field) => CaptureError::Field {
+                field: field.to_string(),
+                message: error.message().to_string(),
+            },
+            None => CaptureError::Deserialize(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NoMatch => f.write_str("The route didn't match"),
+            CaptureError::Field { field, message } => write!(
+                f,
+                "The captured value couldn't be converted for the field '{}': {}",
+                field, message
+            ),
+            CaptureError::Deserialize(message) => {
+                write!(f, "The captures couldn't be deserialized: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
 
 /// Attempts to match routes, transform the route to Component props and render that Component.
+///
+/// It is displayed as its canonical matcher string, which `RouteMatcher::try_from` parses back into
+/// the same tokens. The settings aren't part of the matcher string.
+///
+/// With the `matcher_serde` feature, it implements `Serialize` and `Deserialize`,
+/// so that matchers can be loaded from configuration at runtime.
+/// It is serialized as its matcher string and settings, like
+/// `{"matcher": "/users/{id}", "settings": {...}}`, and the matcher string is parsed when it is
+/// deserialized, so invalid ones are rejected. The settings may be left out.
 #[derive(Debug, PartialEq, Clone)]
 pub struct RouteMatcher {
     /// Tokens used to determine how the matcher will match a route string.
@@ -19,21 +115,114 @@ pub struct RouteMatcher {
     pub settings: MatcherSettings,
 }
 
+/// How a `RouteMatcher` is serialized.
+#[cfg(feature = "matcher_serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "RouteMatcher")]
+struct SerializedRouteMatcher {
+    matcher: String,
+    #[serde(default)]
+    settings: MatcherSettings,
+}
+
+#[cfg(feature = "matcher_serde")]
+impl serde::Serialize for RouteMatcher {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedRouteMatcher {
+            matcher: self.to_string(),
+            settings: self.settings,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "matcher_serde")]
+impl<'de> serde::Deserialize<'de> for RouteMatcher {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedRouteMatcher::deserialize(deserializer)?;
+        RouteMatcher::new(&serialized.matcher, serialized.settings)
+            .map_err(|error| serde::de::Error::custom(format!("{:?}", error)))
+    }
+}
+
 /// Settings used for the matcher.
+///
+/// More settings may be added, so they are best created from the default settings with the
+/// `with_*` methods, rather than with a struct literal.
+///
+/// # Example
+/// ```
+/// use yew_router::matcher::{MatcherSettings, TrailingSlash};
+///
+/// let settings = MatcherSettings::default()
+///     .with_case_insensitive(true)
+///     .with_trailing_slash(TrailingSlash::Ignore);
+/// assert!(settings.case_insensitive);
+/// ```
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(
+    feature = "matcher_serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub struct MatcherSettings {
     /// All literal matches do not care about case.
     pub case_insensitive: bool,
+    /// How a slash at the end of the path section of a route is treated.
+    pub trailing_slash: TrailingSlash,
 }
 
 impl Default for MatcherSettings {
     fn default() -> Self {
         MatcherSettings {
             case_insensitive: false,
+            trailing_slash: TrailingSlash::Strict,
         }
     }
 }
 
+impl MatcherSettings {
+    /// Sets whether literals are matched without caring about case.
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets how a slash at the end of the path section of a route is treated.
+    pub fn with_trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+}
+
+/// How a slash at the end of the path section of a route is treated.
+///
+/// The path section is everything before the query or fragment.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(
+    feature = "matcher_serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum TrailingSlash {
+    /// The route is matched as it is, so it must have a trailing slash where the matcher string
+    /// does.
+    Strict,
+    /// The trailing slash may be left out or added, regardless of the matcher string,
+    /// so `/about` and `/about/` match the same routes.
+    Ignore,
+    /// The path must end with a slash, regardless of the matcher string.
+    Require,
+    /// Matches like `Ignore`, but the `Router` replaces the route with the one built from the
+    /// switched item, so the trailing slash is shown as it is written in the matcher string.
+    Redirect,
+}
+
+impl TrailingSlash {
+    /// Whether a trailing slash may differ from the one in the matcher string.
+    pub fn is_lenient(self) -> bool {
+        self != TrailingSlash::Strict
+    }
+}
+
 impl RouteMatcher {
     /// Attempt to create a RouteMatcher from a "matcher string".
     pub fn try_from(i: &str) -> Result<Self, PrettyParseError> {
@@ -60,6 +249,14 @@ impl RouteMatcher {
         matcher_impl::match_into_map(&self.tokens, &self.settings)(i)
     }
 
+    /// Match a route string, collecting the results into a map that can hold many values per key.
+    pub fn capture_route_into_multimap<'a, 'b: 'a>(
+        &'b self,
+        i: &'a str,
+    ) -> IResult<&'a str, MultiCaptures<'a>> {
+        matcher_impl::match_into_multimap(&self.tokens, &self.settings)(i)
+    }
+
     /// Match a route string, collecting the results into a vector.
     pub fn capture_route_into_vec<'a, 'b: 'a>(
         &'b self,
@@ -68,26 +265,71 @@ impl RouteMatcher {
         matcher_impl::match_into_vec(&self.tokens, &self.settings)(i)
     }
 
+    /// Match a route string, deserializing the named captures into a struct or map.
+    ///
+    /// Captured values are percent-decoded, and then converted like query parameters are by
+    /// `urlencoded::from_pairs`,
+    /// so numbers and booleans are parsed from the captured strings,
+    /// every value captured by `{*:name}` in the query can be deserialized into a `Vec`,
+    /// and captures that are absent or empty are `None` for `Option` fields.
+    /// Unnamed captures, and query parameters that aren't part of the matcher string, are left out.
+    pub fn capture_into<T: DeserializeOwned>(&self, i: &str) -> Result<T, CaptureError> {
+        let (_, captures) = self
+            .capture_route_into_multimap(i)
+            .map_err(|_| CaptureError::NoMatch)?;
+        let pairs = captures
+            .captures
+            .into_iter()
+            .filter(|(key, _)| !key.is_empty())
+            .flat_map(|(key, values)| {
+                values
+                    .into_iter()
+                    .map(move |value| (key.to_string(), encoding::decode(&value)))
+            })
+            .collect();
+        urlencoded::from_pairs(pairs).map_err(CaptureError::from)
+    }
+
+    /// Match a route string, explaining where and why it failed to match.
+    ///
+    /// This is slower than capturing the route, so it is meant for debugging.
+    pub fn explain(&self, route: &str) -> MatchReport {
+        matcher_impl::explain(&self.tokens, self.settings, route)
+    }
+
     /// Gets a set of all names that will be captured.
     /// This is useful in determining if a given struct will be able to be populated by a given path
     /// matcher before being given a concrete path to match.
     pub fn capture_names(&self) -> HashSet<&str> {
+        fn capture_name(capture: &CaptureVariant) -> Option<&str> {
+            match capture {
+                CaptureVariant::ManyNamed(name)
+                | CaptureVariant::Named { name, .. }
+                | CaptureVariant::NumberedNamed { name, .. } => Some(name),
+                CaptureVariant::Unnamed
+                | CaptureVariant::ManyUnnamed
+                | CaptureVariant::NumberedUnnamed { .. } => None,
+            }
+        }
         fn capture_names_impl(tokens: &[MatcherToken]) -> HashSet<&str> {
             tokens
                 .iter()
                 .fold(HashSet::new(), |mut acc: HashSet<&str>, token| {
                     match token {
                         MatcherToken::Exact(_) | MatcherToken::End => {}
-                        MatcherToken::Capture(capture) => match &capture {
-                            CaptureVariant::ManyNamed(name)
-                            | CaptureVariant::Named(name)
-                            | CaptureVariant::NumberedNamed { name, .. } => {
-                                acc.insert(&name);
-                            }
-                            CaptureVariant::Unnamed
-                            | CaptureVariant::ManyUnnamed
-                            | CaptureVariant::NumberedUnnamed { .. } => {}
-                        },
+                        MatcherToken::Capture(capture) => acc.extend(capture_name(capture)),
+                        MatcherToken::Optional(tokens) => acc.extend(capture_names_impl(tokens)),
+                        MatcherToken::Query {
+                            parameters, rest, ..
+                        } => {
+                            acc.extend(parameters.iter().filter_map(|parameter| {
+                                match &parameter.value {
+                                    QueryValue::Capture(capture) => capture_name(capture),
+                                    QueryValue::Exact(_) => None,
+                                }
+                            }));
+                            acc.extend(rest.as_ref().map(String::as_str))
+                        }
                     }
                     acc
                 })
@@ -96,6 +338,12 @@ impl RouteMatcher {
     }
 }
 
+impl fmt::Display for RouteMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&matcher_string(&self.tokens))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,7 +389,10 @@ mod tests {
     fn simple_capture() {
         let tokens = vec![
             RouteParserToken::Separator,
-            RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "lorem",
+                constraint: None,
+            }),
             RouteParserToken::Separator,
         ];
         let path_matcher = RouteMatcher::from(tokens);
@@ -155,7 +406,10 @@ mod tests {
     fn simple_capture_with_no_trailing_separator() {
         let tokens = vec![
             RouteParserToken::Separator,
-            RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "lorem",
+                constraint: None,
+            }),
         ];
         let path_matcher = RouteMatcher::from(tokens);
         let (_, matches) = path_matcher
@@ -184,7 +438,10 @@ mod tests {
             RouteParserToken::Separator,
             RouteParserToken::Exact("a"),
             RouteParserToken::Separator,
-            RouteParserToken::Capture(RefCaptureVariant::Named("lorem")),
+            RouteParserToken::Capture(RefCaptureVariant::Named {
+                name: "lorem",
+                constraint: None,
+            }),
         ];
         let path_matcher = RouteMatcher::from(tokens);
         path_matcher
@@ -277,4 +534,245 @@ mod tests {
             "garbage1/garbage2/garbage3".to_string()
         )
     }
+
+    #[test]
+    fn display_round_trips() {
+        let matchers = [
+            "/users/{id:u32}[/posts/{*:path}]!",
+            "/search?q={query}&page={page}!",
+            "/wow!!/{{braces}}#a&b",
+        ];
+        for matcher in matchers.iter() {
+            let m = RouteMatcher::try_from(matcher).expect("should parse");
+            assert_eq!(&m.to_string(), matcher);
+            assert_eq!(RouteMatcher::try_from(&m.to_string()), Ok(m));
+        }
+    }
+
+    #[cfg(feature = "matcher_serde")]
+    #[test]
+    fn serde_round_trips() {
+        let m = RouteMatcher::new(
+            "/users/{id:u32}?tab={tab}!",
+            MatcherSettings {
+                case_insensitive: true,
+                trailing_slash: TrailingSlash::Ignore,
+            },
+        )
+        .expect("should parse");
+        let json = serde_json::to_string(&m).expect("should serialize");
+        let deserialized: RouteMatcher = serde_json::from_str(&json).expect("should deserialize");
+        assert_eq!(deserialized, m);
+    }
+
+    #[cfg(feature = "matcher_serde")]
+    #[test]
+    fn settings_default_when_deserializing() {
+        let json = r#"{"matcher": "/about!"}"#;
+        let m: RouteMatcher = serde_json::from_str(json).expect("should deserialize");
+        assert_eq!(m, RouteMatcher::try_from("/about!").expect("should parse"));
+    }
+
+    #[cfg(feature = "matcher_serde")]
+    #[test]
+    fn invalid_matcher_is_rejected_when_deserializing() {
+        let json = r#"{"matcher": "/a/{}{}"}"#;
+        let error = serde_json::from_str::<RouteMatcher>(json).expect_err("should not deserialize");
+        assert!(error.to_string().contains("Could not parse route"));
+    }
+
+    mod capture_into {
+        use super::*;
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Post {
+            id: u32,
+            slug: String,
+            page: Option<usize>,
+            draft: bool,
+        }
+
+        fn matcher() -> RouteMatcher {
+            RouteMatcher::try_from("/posts/{id}/{slug}[/page/{page}]?draft={draft}")
+                .expect("should parse")
+        }
+
+        #[test]
+        fn captures_are_converted() {
+            let post: Post = matcher()
+                .capture_into("/posts/42/hello%20world/page/2?draft=true")
+                .expect("should capture");
+            assert_eq!(
+                post,
+                Post {
+                    id: 42,
+                    slug: "hello world".to_string(),
+                    page: Some(2),
+                    draft: true,
+                }
+            );
+        }
+
+        #[test]
+        fn absent_optional_capture_is_none() {
+            let post: Post = matcher()
+                .capture_into("/posts/42/hello?draft=false")
+                .expect("should capture");
+            assert_eq!(post.page, None);
+        }
+
+        #[test]
+        fn conversion_error_names_field() {
+            let error = matcher()
+                .capture_into::<Post>("/posts/lorem/hello?draft=false")
+                .expect_err("should not capture");
+            match error {
+                CaptureError::Field { field, .. } => assert_eq!(field, "id"),
+                error => panic!("unexpected error: {:?}", error),
+            }
+        }
+
+        #[test]
+        fn missing_field_is_named() {
+            #[derive(Debug, Deserialize)]
+            struct Missing {
+                #[allow(dead_code)]
+                author: String,
+            }
+            let error = matcher()
+                .capture_into::<Missing>("/posts/42/hello?draft=false")
+                .expect_err("should not capture");
+            match error {
+                CaptureError::Field { field, .. } => assert_eq!(field, "author"),
+                error => panic!("unexpected error: {:?}", error),
+            }
+        }
+
+        #[test]
+        fn no_match() {
+            let error = matcher()
+                .capture_into::<Post>("/users/42")
+                .expect_err("should not capture");
+            assert_eq!(error, CaptureError::NoMatch);
+        }
+
+        #[test]
+        fn into_map() {
+            let captures: HashMap<String, String> = matcher()
+                .capture_into("/posts/42/hello?draft=false")
+                .expect("should capture");
+            assert_eq!(captures["slug"], "hello");
+            assert_eq!(captures.len(), 3);
+        }
+    }
+
+    mod explain {
+        use super::*;
+        use crate::matcher::explain::{Mismatch, MismatchReason};
+
+        fn explain_mismatch(matcher_string: &str, route: &str) -> Mismatch {
+            RouteMatcher::try_from(matcher_string)
+                .expect("should parse")
+                .explain(route)
+                .mismatch
+                .expect("should not match")
+        }
+
+        #[test]
+        fn matched() {
+            let matcher = RouteMatcher::try_from("/users/{id}").expect("should parse");
+            let report = matcher.explain("/users/1/posts");
+            assert!(report.is_match());
+            assert_eq!(report.captures, vec![("id".to_string(), "1".to_string())]);
+            assert_eq!(report.unmatched, "/posts");
+        }
+
+        #[test]
+        fn literal_points_at_first_difference() {
+            let mismatch = explain_mismatch("/users/new", "/users/old");
+            assert_eq!(mismatch.offset, 7);
+            assert_eq!(mismatch.reason, MismatchReason::Literal);
+            assert_eq!(
+                mismatch.token,
+                Some(MatcherToken::Exact("/users/new".to_string()))
+            );
+        }
+
+        #[test]
+        fn constraint() {
+            let mismatch = explain_mismatch("/users/{id:u32}", "/users/lorem");
+            assert_eq!(mismatch.offset, 7);
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::Constraint {
+                    value: "lorem".to_string()
+                }
+            );
+            match mismatch.token {
+                Some(MatcherToken::Capture(_)) => {}
+                token => panic!("unexpected token: {:?}", token),
+            }
+        }
+
+        #[test]
+        fn leftover_input_at_end() {
+            let mismatch = explain_mismatch("/about!", "/about/team");
+            assert_eq!(mismatch.offset, 6);
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::LeftoverInput {
+                    remaining: "/team".to_string()
+                }
+            );
+            assert_eq!(mismatch.token, Some(MatcherToken::End));
+        }
+
+        #[test]
+        fn query_parameter() {
+            let mismatch = explain_mismatch("/search?q={query}", "/search?page=2");
+            assert_eq!(mismatch.offset, 7);
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::QueryParameter {
+                    key: "q".to_string()
+                }
+            );
+            let mismatch = explain_mismatch("/search?q={query}!", "/search?q=lorem&page=2");
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::UnknownQueryParameter {
+                    key: "page".to_string()
+                }
+            );
+        }
+
+        #[test]
+        fn furthest_attempt_through_optional_section() {
+            let mismatch = explain_mismatch("/posts[/page/{n:u32}]!", "/posts/page/lorem");
+            assert_eq!(mismatch.offset, 12);
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::Constraint {
+                    value: "lorem".to_string()
+                }
+            );
+        }
+
+        #[test]
+        fn required_trailing_slash() {
+            let settings = MatcherSettings {
+                trailing_slash: TrailingSlash::Require,
+                ..Default::default()
+            };
+            let matcher = RouteMatcher::new("/about", settings).expect("should parse");
+            let mismatch = matcher
+                .explain("/about?q=1")
+                .mismatch
+                .expect("should not match");
+            assert_eq!(mismatch.offset, 6);
+            assert_eq!(mismatch.reason, MismatchReason::TrailingSlashRequired);
+            assert_eq!(mismatch.token, None);
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Dispatch from a route to the matchers that could match it.
+//!
+//! Derived `Switch` implementations use this to skip variants whose leading literals
+//! don't match the route, instead of trying every variant's matcher in turn.
+use super::RouteMatcher;
+use yew_router_route_parser::MatcherToken;
+
+/// A prefix tree over the leading literals of a list of matchers.
+///
+/// Literals are compared ignoring ASCII case, so the matchers that are returned as candidates
+/// still have to be run, but the ones that are left out can't match the route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixTree {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Node {
+    /// Child nodes, sorted by the lowercased byte leading to them.
+    children: Vec<(u8, usize)>,
+    /// Matchers whose leading literal ends at this node or before it.
+    matching: Vec<usize>,
+    /// Matchers whose leading literal ends at this node, before it, or after it.
+    possible: Vec<usize>,
+}
+
+impl PrefixTree {
+    /// Creates a tree over the leading `Exact` tokens of the matchers.
+    pub fn new(matchers: &[RouteMatcher]) -> Self {
+        let mut nodes = vec![Node::default()];
+        let mut parents = vec![None];
+        let mut ends = vec![vec![]];
+        for (index, matcher) in matchers.iter().enumerate() {
+            let mut current = 0;
+            nodes[current].possible.push(index);
+            for byte in literal_prefix(matcher) {
+                current = match nodes[current]
+                    .children
+                    .binary_search_by_key(&byte, |(b, _)| *b)
+                {
+                    Ok(position) => nodes[current].children[position].1,
+                    Err(position) => {
+                        let child = nodes.len();
+                        nodes.push(Node::default());
+                        parents.push(Some(current));
+                        ends.push(vec![]);
+                        nodes[current].children.insert(position, (byte, child));
+                        child
+                    }
+                };
+                nodes[current].possible.push(index);
+            }
+            ends[current].push(index);
+        }
+        // Parents are always created before their children,
+        // so the matchers that end before a node are known by the time it is reached.
+        for (current, parent) in parents.into_iter().enumerate() {
+            let mut before = match parent {
+                Some(parent) => nodes[parent].matching.clone(),
+                None => vec![],
+            };
+            let node = &mut nodes[current];
+            node.possible.extend_from_slice(&before);
+            node.possible.sort_unstable();
+            before.extend_from_slice(&ends[current]);
+            before.sort_unstable();
+            node.matching = before;
+        }
+        PrefixTree { nodes }
+    }
+
+    /// Gets the indices of the matchers that could match the route, in ascending order.
+    pub fn candidates(&self, route: &str) -> &[usize] {
+        let mut node = &self.nodes[0];
+        for byte in route.bytes() {
+            // Non-ASCII characters may match ASCII literals when case is ignored.
+            if !byte.is_ascii() {
+                return &node.possible;
+            }
+            let byte = byte.to_ascii_lowercase();
+            match node.children.binary_search_by_key(&byte, |(b, _)| *b) {
+                Ok(position) => node = &self.nodes[node.children[position].1],
+                Err(_) => break,
+            }
+        }
+        &node.matching
+    }
+}
+
+/// Gets the lowercased leading literal of the matcher, up to its first non-ASCII character.
+///
+/// If the trailing slash is lenient, a slash that ends the literal is left out,
+/// because the route may not have it.
+fn literal_prefix(matcher: &RouteMatcher) -> Vec<u8> {
+    let mut prefix = vec![];
+    'tokens: for token in &matcher.tokens {
+        let literal = match token {
+            MatcherToken::Exact(literal) => literal,
+            _ => break,
+        };
+        for byte in literal.bytes() {
+            if !byte.is_ascii() {
+                break 'tokens;
+            }
+            prefix.push(byte.to_ascii_lowercase());
+        }
+    }
+    if matcher.settings.trailing_slash.is_lenient() && prefix.len() > 1 && prefix.ends_with(b"/") {
+        prefix.pop();
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::{MatcherSettings, TrailingSlash};
+
+    fn tree(matcher_strings: &[&str]) -> PrefixTree {
+        let matchers: Vec<RouteMatcher> = matcher_strings
+            .iter()
+            .map(|s| RouteMatcher::new(s, MatcherSettings::default()).expect("should parse"))
+            .collect();
+        PrefixTree::new(&matchers)
+    }
+
+    #[test]
+    fn candidates_share_prefix() {
+        let tree = tree(&["/users", "/users/{id}", "/posts", "{*}"]);
+        assert_eq!(tree.candidates("/users/1"), &[0, 1, 3]);
+        assert_eq!(tree.candidates("/posts"), &[2, 3]);
+        assert_eq!(tree.candidates("/other"), &[3]);
+    }
+
+    #[test]
+    fn candidates_ignore_ascii_case() {
+        let tree = tree(&["/users", "/posts"]);
+        assert_eq!(tree.candidates("/USERS"), &[0]);
+    }
+
+    #[test]
+    fn short_route_excludes_longer_literals() {
+        let tree = tree(&["/a", "/abc"]);
+        assert_eq!(tree.candidates("/ab"), &[0]);
+    }
+
+    #[test]
+    fn non_ascii_route_keeps_every_possible_candidate() {
+        let tree = tree(&["/k", "/kelvin", "/other"]);
+        assert_eq!(tree.candidates("/\u{212A}"), &[0, 1, 2]);
+        assert_eq!(tree.candidates("/k\u{212A}"), &[0, 1]);
+    }
+
+    #[test]
+    fn lenient_trailing_slash_is_left_out() {
+        let settings = MatcherSettings {
+            trailing_slash: TrailingSlash::Ignore,
+            ..Default::default()
+        };
+        let matcher = RouteMatcher::new("/about/", settings).expect("should parse");
+        let tree = PrefixTree::new(&[matcher]);
+        assert_eq!(tree.candidates("/about"), &[0]);
+    }
+
+    #[test]
+    fn non_ascii_literal_is_truncated() {
+        let tree = tree(&["/größe", "/grün"]);
+        assert_eq!(tree.candidates("/grxyz"), &[0, 1]);
+        assert!(tree.candidates("/other").is_empty());
+    }
+}
@@ -0,0 +1,426 @@
+//! Conversion between structs or maps and query parameters.
+//!
+//! This works on query parameters that have already been split into decoded key-value pairs,
+//! like those in `MultiCaptures::unmatched_query`, so percent-encoding is left to the matcher.
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any, Serialize,
+};
+use serde_json::Value;
+use std::fmt;
+
+/// Error produced when a value can't be converted to or from query parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    message: String,
+    field: Option<String>,
+}
+
+impl Error {
+    fn new(message: String) -> Self {
+        Error {
+            message,
+            field: None,
+        }
+    }
+
+    /// The key of the value that couldn't be converted, or that was missing.
+    ///
+    /// This is `None` if the error doesn't concern a single key.
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_ref().map(String::as_str)
+    }
+
+    /// The reason the conversion failed, without the key.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Attributes the error to the key, unless it was already attributed to one.
+    fn in_field(mut self, field: String) -> Self {
+        self.field.get_or_insert(field);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "{}: {}", field, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::new("missing value".to_string()).in_field(field.to_string())
+    }
+}
+
+/// Deserializes a struct or map from query parameters.
+///
+/// Every occurrence of a repeated key can be deserialized into a sequence, like a `Vec<T>`,
+/// otherwise only the first occurrence is used.
+/// Empty values are deserialized as `None` for `Option` fields.
+pub fn from_pairs<T: DeserializeOwned>(pairs: Vec<(String, String)>) -> Result<T, Error> {
+    let mut grouped: Vec<(String, Vec<String>)> = vec![];
+    for (key, value) in pairs {
+        match grouped.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => grouped.push((key, vec![value])),
+        }
+    }
+    T::deserialize(PairsDeserializer(grouped))
+}
+
+/// Serializes a struct or map into query parameters, sorted by key.
+///
+/// Sequences are written as a repeated key, and `None` values are left out.
+/// Nested structs, maps and sequences are not supported.
+pub fn to_pairs<T: Serialize>(value: &T) -> Result<Vec<(String, String)>, Error> {
+    let object = match serde_json::to_value(value).map_err(de::Error::custom)? {
+        Value::Object(object) => object,
+        Value::Null => return Ok(vec![]),
+        _ => {
+            return Err(Error::new(
+                "Only structs and maps can be written as query parameters".to_string(),
+            ))
+        }
+    };
+    let mut pairs = vec![];
+    for (key, value) in object {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    if let Some(value) = scalar_to_string(value)? {
+                        pairs.push((key.clone(), value));
+                    }
+                }
+            }
+            value => {
+                if let Some(value) = scalar_to_string(value)? {
+                    pairs.push((key, value));
+                }
+            }
+        }
+    }
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(pairs)
+}
+
+fn scalar_to_string(value: Value) -> Result<Option<String>, Error> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(value) => Ok(Some(value.to_string())),
+        Value::Number(value) => Ok(Some(value.to_string())),
+        Value::String(value) => Ok(Some(value)),
+        Value::Array(_) | Value::Object(_) => Err(Error::new(
+            "Nested values can't be written as query parameters".to_string(),
+        )),
+    }
+}
+
+/// Deserializes the query parameters as a map, with the values of repeated keys grouped together.
+struct PairsDeserializer(Vec<(String, Vec<String>)>);
+
+impl<'de> de::Deserializer<'de> for PairsDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(PairsAccess {
+            pairs: self.0.into_iter(),
+            current: None,
+        })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct PairsAccess {
+    pairs: std::vec::IntoIter<(String, Vec<String>)>,
+    /// The key that was just deserialized, and its values.
+    current: Option<(String, Vec<String>)>,
+}
+
+impl<'de> MapAccess<'de> for PairsAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.pairs.next() {
+            Some((key, values)) => {
+                self.current = Some((key.clone(), values));
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, values) = self
+            .current
+            .take()
+            .ok_or_else(|| Error::new("A value was requested before its key".to_string()))?;
+        seed.deserialize(ValuesDeserializer(values))
+            .map_err(|error| error.in_field(key))
+    }
+}
+
+/// Deserializes the values of one key, of which there is at least one.
+struct ValuesDeserializer(Vec<String>);
+
+impl ValuesDeserializer {
+    fn first(self) -> String {
+        self.0.into_iter().next().unwrap_or_default()
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let value = self.first();
+                match value.parse() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&value), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValuesDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.first())
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.iter().all(String::is_empty) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(ValuesAccess(self.0.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.first().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct map struct identifier ignored_any
+    }
+}
+
+struct ValuesAccess(std::vec::IntoIter<String>);
+
+impl<'de> SeqAccess<'de> for ValuesAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(ValuesDeserializer(vec![value])).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Filters {
+        q: String,
+        page: Option<u32>,
+        tag: Vec<String>,
+        exact: bool,
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn struct_from_pairs() {
+        let filters: Filters = from_pairs(pairs(&[
+            ("tag", "a"),
+            ("q", "lorem ipsum"),
+            ("exact", "true"),
+            ("tag", "b"),
+        ]))
+        .expect("should deserialize");
+        assert_eq!(
+            filters,
+            Filters {
+                q: "lorem ipsum".to_string(),
+                page: None,
+                tag: vec!["a".to_string(), "b".to_string()],
+                exact: true,
+            }
+        );
+    }
+
+    #[test]
+    fn empty_value_is_none() {
+        let filters: Filters = from_pairs(pairs(&[
+            ("q", "lorem"),
+            ("page", ""),
+            ("tag", "a"),
+            ("exact", "false"),
+        ]))
+        .expect("should deserialize");
+        assert_eq!(filters.page, None);
+    }
+
+    #[test]
+    fn unparsable_value_is_an_error() {
+        from_pairs::<Filters>(pairs(&[
+            ("q", "lorem"),
+            ("page", "two"),
+            ("tag", "a"),
+            ("exact", "false"),
+        ]))
+        .expect_err("should not deserialize");
+    }
+
+    #[test]
+    fn error_names_field() {
+        let error = from_pairs::<Filters>(pairs(&[
+            ("q", "lorem"),
+            ("page", "two"),
+            ("tag", "a"),
+            ("exact", "false"),
+        ]))
+        .expect_err("should not deserialize");
+        assert_eq!(error.field(), Some("page"));
+
+        let error = from_pairs::<Filters>(pairs(&[("q", "lorem"), ("tag", "a")]))
+            .expect_err("should not deserialize");
+        assert_eq!(error.field(), Some("exact"));
+    }
+
+    #[test]
+    fn map_from_pairs() {
+        let map: HashMap<String, u32> =
+            from_pairs(pairs(&[("a", "1"), ("b", "2")])).expect("should deserialize");
+        assert_eq!(map["a"], 1);
+        assert_eq!(map["b"], 2);
+    }
+
+    #[test]
+    fn struct_to_pairs() {
+        let filters = Filters {
+            q: "lorem".to_string(),
+            page: None,
+            tag: vec!["b".to_string(), "a".to_string()],
+            exact: false,
+        };
+        assert_eq!(
+            to_pairs(&filters).expect("should serialize"),
+            pairs(&[
+                ("exact", "false"),
+                ("q", "lorem"),
+                ("tag", "b"),
+                ("tag", "a")
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_struct_is_an_error() {
+        #[derive(Serialize)]
+        struct Outer {
+            inner: HashMap<String, String>,
+        }
+        to_pairs(&Outer {
+            inner: HashMap::new(),
+        })
+        .expect_err("should not serialize");
+    }
+}
@@ -1,14 +1,15 @@
 use nom::{
     bytes::complete::{tag, tag_no_case},
-    character::complete::anychar,
-    combinator::{cond, map, peek, rest},
+    combinator::{cond, map},
     error::{ErrorKind, ParseError},
-    multi::many_till,
     sequence::pair,
     IResult,
 };
-use std::{iter::Peekable, rc::Rc, slice::Iter};
-use yew_router_route_parser::MatcherToken;
+use regex::Regex;
+use std::{cell::RefCell, collections::HashMap, iter::once};
+use yew_router_route_parser::{CaptureConstraint, MatcherToken};
+
+use super::MatcherSettings;
 
 /// Allows a configurable tag that can optionally be case insensitive.
 pub fn tag_possibly_case_sensitive<'a, 'b: 'a>(
@@ -38,50 +39,162 @@ pub fn alternative(alternatives: Vec<String>) -> impl Fn(&str) -> IResult<&str,
 }
 
 /// Consumes the input until the provided parser succeeds.
-/// The consumed input is returned in the form of an allocated string.
+/// The consumed input is returned as a slice of the input.
 /// # Note
 /// `stop_parser` only peeks its input.
-pub fn consume_until<'a, F, E>(stop_parser: F) -> impl Fn(&'a str) -> IResult<&'a str, String, E>
+#[allow(unused)]
+pub fn consume_until<'a, F, E>(stop_parser: F) -> impl Fn(&'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str>,
     F: Fn(&'a str) -> IResult<&'a str, &'a str, E>,
 {
-    // In order for the returned fn to be Fn instead of FnOnce, wrap the inner fn in an RC.
-    let f = Rc::new(many_till(
-        anychar,
-        peek(stop_parser), // once this succeeds, stop folding.
-    ));
-    move |i: &str| {
-        let (i, (first, _stop)): (&str, (Vec<char>, &str)) = (f)(i)?;
-        let ret = first.into_iter().collect();
-        Ok((i, ret))
+    move |i: &'a str| {
+        let boundaries = i
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(once(i.len()));
+        for index in boundaries {
+            match stop_parser(&i[index..]) {
+                Ok(_) => return Ok((&i[index..], &i[..index])),
+                Err(nom::Err::Error(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let rest = &i[i.len()..];
+        Err(nom::Err::Error(E::from_error_kind(rest, ErrorKind::Eof)))
+    }
+}
+
+/// Iterates over the tokens of a matcher.
+///
+/// Once the tokens within an optional section are exhausted,
+/// it continues on to the tokens that follow the optional section.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenCursor<'b, 'c> {
+    tokens: &'b [MatcherToken],
+    then: Option<&'c TokenCursor<'b, 'c>>,
+}
+
+impl<'b, 'c> TokenCursor<'b, 'c> {
+    /// Creates a cursor over the provided tokens.
+    pub fn new(tokens: &'b [MatcherToken]) -> Self {
+        TokenCursor { tokens, then: None }
+    }
+
+    /// Creates a cursor over the provided tokens, which continues with `then` once they run out.
+    pub fn followed_by(tokens: &'b [MatcherToken], then: &'c TokenCursor<'b, 'c>) -> Self {
+        TokenCursor {
+            tokens,
+            then: Some(then),
+        }
+    }
+
+    /// Gets the next token without advancing the cursor.
+    pub fn peek(&self) -> Option<&'b MatcherToken> {
+        let mut cursor = *self;
+        cursor.next()
+    }
+}
+
+impl<'b, 'c> Iterator for TokenCursor<'b, 'c> {
+    type Item = &'b MatcherToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((first, rest)) = self.tokens.split_first() {
+                self.tokens = rest;
+                return Some(first);
+            }
+            *self = *self.then?;
+        }
     }
 }
 
 /// Produces a parser combinator that searches for the next possible set of strings of
 /// characters used to terminate a forward search.
 ///
+/// If no literals may follow, the search is terminated by the end of the input,
+/// or by any of the `invalid_characters`.
+/// Literals are matched ignoring case if the `settings` say so.
+///
 /// # Panics
-/// This function assumes that the next item after a Capture must be an Exact.
+/// This function assumes that the next item after a Capture must not be another Capture.
 /// If this is violated, this function will panic.
-pub fn next_delimiter<'a>(
-    iter: &mut Peekable<Iter<MatcherToken>>,
-) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
-    let t: MatcherToken = iter
-        .peek()
-        .copied()
-        .cloned()
-        .expect("There must be at least one token to peak in next_delimiter");
-
-    move |i: &'a str| match &t {
-        MatcherToken::Exact(sequence) => tag(sequence.as_str())(i),
-        MatcherToken::End => rest(i),
-        MatcherToken::Capture(_) => {
+pub fn next_delimiter<'a, 'b, 'c>(
+    cursor: &TokenCursor<'b, 'c>,
+    invalid_characters: &'static str,
+    settings: MatcherSettings,
+) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> + 'c
+where
+    'b: 'c,
+{
+    let cursor = *cursor;
+    move |i: &'a str| match_delimiter(cursor, invalid_characters, settings, i)
+}
+
+/// Matches any of the delimiters that could come next.
+///
+/// An optional section can be either matched or skipped,
+/// so its first token and the token following it are both possible delimiters.
+fn match_delimiter<'a>(
+    mut cursor: TokenCursor,
+    invalid_characters: &'static str,
+    settings: MatcherSettings,
+    i: &'a str,
+) -> IResult<&'a str, &'a str> {
+    match cursor.next() {
+        Some(MatcherToken::Exact(sequence)) if settings.case_insensitive => {
+            tag_no_case(sequence.as_str())(i)
+        }
+        Some(MatcherToken::Exact(sequence)) => tag(sequence.as_str())(i),
+        // The query section begins with a character that can't be captured, or is absent.
+        Some(MatcherToken::End) | Some(MatcherToken::Query { .. }) | None => {
+            if i.is_empty() || i.starts_with(|c| invalid_characters.contains(c)) {
+                Ok((i, ""))
+            } else {
+                Err(nom::Err::Error((i, ErrorKind::Eof)))
+            }
+        }
+        Some(MatcherToken::Capture(_)) => {
             panic!("underlying parser should not allow two captures in a row")
         }
+        Some(MatcherToken::Optional(tokens)) => {
+            let within = TokenCursor::followed_by(tokens, &cursor);
+            match_delimiter(within, invalid_characters, settings, i)
+                .or_else(|_| match_delimiter(cursor, invalid_characters, settings, i))
+        }
     }
 }
 
+/// Determines if a captured value satisfies the constraint placed on its capture section.
+pub fn satisfies_constraint(constraint: &CaptureConstraint, value: &str) -> bool {
+    match constraint {
+        CaptureConstraint::Type(ty) => ty.accepts(value),
+        CaptureConstraint::Regex(pattern) => matches_regex(pattern, value),
+    }
+}
+
+/// Matches the whole value against a regular expression.
+///
+/// Compiled expressions are cached, so each pattern is only compiled once per thread.
+fn matches_regex(pattern: &str, value: &str) -> bool {
+    thread_local! {
+        static CACHE: RefCell<HashMap<String, Option<Regex>>> = RefCell::new(HashMap::new());
+    }
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(regex) = cache.get(pattern) {
+            return regex.as_ref().map_or(false, |regex| regex.is_match(value));
+        }
+        let regex = cache.entry(pattern.to_string()).or_insert_with(|| {
+            Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| log::error!("Invalid regex constraint '{}': {}", pattern, e))
+                .ok()
+        });
+        regex.as_ref().map_or(false, |regex| regex.is_match(value))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,7 +203,7 @@ mod tests {
     fn consume_until_simple() {
         let parser = consume_until::<_, ()>(tag("z"));
         let parsed = parser("abcz").expect("Should parse");
-        assert_eq!(parsed, ("z", "abc".to_string()))
+        assert_eq!(parsed, ("z", "abc"))
     }
 
     #[test]
@@ -100,6 +213,54 @@ mod tests {
         assert_eq!(e, nom::Err::Error(("", ErrorKind::Eof)))
     }
 
+    #[test]
+    fn consume_until_multibyte() {
+        let parser = consume_until::<_, ()>(tag("/"));
+        let parsed = parser("größe/").expect("Should parse");
+        assert_eq!(parsed, ("/", "größe"))
+    }
+
+    #[test]
+    fn consume_until_stops_immediately() {
+        let parser = consume_until::<_, ()>(tag(""));
+        let parsed = parser("abc").expect("Should parse");
+        assert_eq!(parsed, ("abc", ""))
+    }
+
+    #[test]
+    fn next_delimiter_within_or_after_optional() {
+        let tokens = vec![
+            MatcherToken::Optional(vec![MatcherToken::Exact("/".to_string())]),
+            MatcherToken::Exact("#".to_string()),
+        ];
+        let cursor = TokenCursor::new(&tokens);
+        let parser = next_delimiter(&cursor, "", MatcherSettings::default());
+        assert_eq!(parser("/a").expect("Should parse"), ("a", "/"));
+        assert_eq!(parser("#a").expect("Should parse"), ("a", "#"));
+        parser("a").expect_err("Should not parse");
+    }
+
+    #[test]
+    fn next_delimiter_follows_case_sensitivity() {
+        let tokens = vec![MatcherToken::Exact(".TXT".to_string())];
+        let cursor = TokenCursor::new(&tokens);
+        let insensitive = MatcherSettings::default().with_case_insensitive(true);
+        let parser = next_delimiter(&cursor, "", insensitive);
+        assert_eq!(parser(".txt").expect("Should parse"), ("", ".txt"));
+        let parser = next_delimiter(&cursor, "", MatcherSettings::default());
+        parser(".txt").expect_err("Should not parse");
+    }
+
+    #[test]
+    fn next_delimiter_at_end() {
+        let tokens = vec![MatcherToken::End];
+        let cursor = TokenCursor::new(&tokens);
+        let parser = next_delimiter(&cursor, "?#", MatcherSettings::default());
+        assert_eq!(parser("").expect("Should parse"), ("", ""));
+        assert_eq!(parser("?a").expect("Should parse"), ("?a", ""));
+        parser("a").expect_err("Should not parse");
+    }
+
     #[test]
     fn alternative_simple() {
         let parser = alternative(
@@ -121,7 +282,7 @@ mod tests {
                 .collect(),
         ));
         let parsed = parser("first_stuff_abc").expect("should parse");
-        assert_eq!(parsed, ("abc", "first_stuff_".to_string()))
+        assert_eq!(parsed, ("abc", "first_stuff_"))
     }
 
     #[test]
@@ -137,4 +298,26 @@ mod tests {
         parser("lorem").expect("Should match");
         parser("LoREm").expect("Should match");
     }
+
+    #[test]
+    fn type_constraint() {
+        let constraint = CaptureConstraint::new("u8");
+        assert!(satisfies_constraint(&constraint, "255"));
+        assert!(!satisfies_constraint(&constraint, "256"));
+        assert!(!satisfies_constraint(&constraint, "lorem"));
+    }
+
+    #[test]
+    fn regex_constraint_matches_whole_value() {
+        let constraint = CaptureConstraint::new(r"\d{4}-\d{2}-\d{2}");
+        assert!(satisfies_constraint(&constraint, "2020-03-14"));
+        assert!(!satisfies_constraint(&constraint, "x2020-03-14"));
+        assert!(!satisfies_constraint(&constraint, "2020-03-14x"));
+    }
+
+    #[test]
+    fn invalid_regex_constraint_never_matches() {
+        let constraint = CaptureConstraint::new("(");
+        assert!(!satisfies_constraint(&constraint, "("));
+    }
 }
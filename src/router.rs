@@ -1,7 +1,7 @@
 //! Router Component.
 
 use crate::{
-    agent::{RouteAgentBridge, RouteRequest},
+    agent::{RouteAgentBridge, RouteRequest, RouteResponse},
     route::Route,
     RouteState, Switch,
 };
@@ -9,7 +9,9 @@ use std::{
     fmt::{self, Debug, Error as FmtError, Formatter},
     rc::Rc,
 };
-use yew::{html, virtual_dom::VNode, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::{
+    html, virtual_dom::VNode, Callback, Component, ComponentLink, Html, Properties, ShouldRender,
+};
 
 
 /// Any state that can be managed by the `Router` must meet the criteria of this trait.
@@ -176,7 +178,12 @@ where
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let callback = link.callback(Msg::UpdateRoute);
-        let router_agent = RouteAgentBridge::new(callback);
+        // The router doesn't move through the history, so it only receives routes.
+        let router_agent = RouteAgentBridge::new(Callback::from(move |response| {
+            if let RouteResponse::Route(route) = response {
+                callback.emit(route)
+            }
+        }));
 
         Router {
             switch: Default::default(), /* This must be updated by immediately requesting a route
@@ -196,6 +203,28 @@ where
             Msg::UpdateRoute(route) => {
                 let mut switch = SW::switch(route.clone());
 
+                if let Some(redirected) = switch
+                    .as_ref()
+                    .and_then(|switch| trailing_slash_redirect(switch, &route.route))
+                {
+                    log::trace!(
+                        "Route matched, but redirecting route to change its trailing slash."
+                    );
+                    self.router_agent
+                        .send(RouteRequest::ReplaceRouteNoBroadcast(Route {
+                            route: redirected,
+                            state: route.state.clone(),
+                        }));
+                }
+
+                if cfg!(debug_assertions) && switch.is_none() {
+                    for report in SW::explain_route(&route.route) {
+                        if let Some(mismatch) = report.report.mismatch {
+                            log::debug!("{} did not match: {}", report.variant, mismatch);
+                        }
+                    }
+                }
+
                 if switch.is_none() {
                     if let Some(redirect) = &self.props.redirect {
                         let redirected: SW = (&redirect.0)(route);
@@ -233,3 +262,22 @@ where
         }
     }
 }
+
+/// Gets the route that should replace the switched route, if the switch redirects trailing slashes
+/// and the path of the route built from it only differs by its trailing slash.
+///
+/// The query and fragment of the switched route are kept.
+fn trailing_slash_redirect<SW: Switch + Clone>(switch: &SW, route: &str) -> Option<String> {
+    if !switch.redirect_trailing_slash() {
+        return None;
+    }
+    let built: Route<()> = switch.clone().into();
+    let path_end = |route: &str| route.find(&['?', '#'][..]).unwrap_or(route.len());
+    let (path, rest) = route.split_at(path_end(route));
+    let built_path = &built.route[..path_end(&built.route)];
+    if path != built_path && path.trim_end_matches('/') == built_path.trim_end_matches('/') {
+        Some(format!("{}{}", built_path, rest))
+    } else {
+        None
+    }
+}
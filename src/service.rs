@@ -2,38 +2,256 @@
 
 use yew::callback::Callback;
 
-use crate::route::{Route, RouteState};
+use crate::{
+    route::{Route, RouteState},
+    state_codec::{state_format, Entry},
+};
 use cfg_if::cfg_if;
 use cfg_match::cfg_match;
-use std::marker::PhantomData;
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    rc::Rc,
+};
 
 cfg_if! {
     if #[cfg(feature = "std_web")] {
         use stdweb::{
             js,
-            unstable::{TryFrom, TryInto},
-            web::{event::PopStateEvent, window, EventListenerHandle, History, IEventTarget, Location},
+            web::{
+                document,
+                event::{BeforeUnloadEvent, HashChangeEvent, IEvent, PopStateEvent},
+                set_timeout, window, EventListenerHandle, History, IEventTarget,
+                INonElementParentNode, IParentNode, Location,
+            },
+            unstable::TryInto,
             Value,
         };
     } else if #[cfg(feature = "web_sys")] {
-        use web_sys::{History, Location, PopStateEvent};
-        use gloo::events::EventListener;
+        use web_sys::{
+            BeforeUnloadEvent, History, HtmlBaseElement, Location, PopStateEvent, ScrollRestoration,
+        };
+        use gloo::{
+            events::{EventListener, EventListenerOptions},
+            timers::callback::Timeout,
+        };
         use wasm_bindgen::{JsValue as Value, JsCast};
     }
 }
 
+thread_local! {
+    static BASE_PATH: RefCell<Option<String>> = RefCell::new(None);
+    static ROUTING_MODE: Cell<RoutingMode> = Cell::new(RoutingMode::History);
+}
+
+/// Determines where route services keep the route in the browser's URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingMode {
+    /// The route is the path, query and fragment of the URL, like `/posts?page=2`.
+    ///
+    /// This needs a server that responds with the app to every route.
+    History,
+    /// The route is kept in the fragment of the URL, like `/#/posts?page=2`.
+    ///
+    /// This works on static file hosts that only serve the app from its `index.html`.
+    Hash,
+}
+
+impl Default for RoutingMode {
+    fn default() -> Self {
+        RoutingMode::History
+    }
+}
+
+/// Sets where route services keep the route in the browser's URL.
+///
+/// Like `set_base_path`, this must be called before the first route service is created.
+/// `RouterAnchor`s build their `href`s for the mode as well.
+pub fn set_routing_mode(mode: RoutingMode) {
+    ROUTING_MODE.with(|cell| cell.set(mode));
+}
+
+/// Gets where route services keep the route in the browser's URL.
+///
+/// See `set_routing_mode`.
+pub fn routing_mode() -> RoutingMode {
+    ROUTING_MODE.with(Cell::get)
+}
+
+/// Builds the `href` that links to the route, for the current routing mode and base path.
+pub fn href(route: &str) -> String {
+    route_href(routing_mode(), &base_path(), route)
+}
+
+/// Sets the path that the app is served under, like `/portal`.
+///
+/// Route services strip it from the routes they get from the browser,
+/// and prepend it to the routes they set, so the routes the app deals with don't include it.
+/// `RouterAnchor`s also prepend it to their `href`s.
+///
+/// This must be called before the first route service is created, which happens when the first
+/// `Router` or `RouteAgent` bridge is created.
+/// If it isn't called, the path that the page's `<base>` element points to is used, with relative
+/// `href`s resolved against the page's URL, and if there is none, routes are left as they are.
+pub fn set_base_path(base_path: &str) {
+    BASE_PATH.with(|cell| *cell.borrow_mut() = Some(normalize_base_path(base_path)));
+}
+
+/// Gets the path that the app is served under, without a trailing slash.
+///
+/// This is empty if the app is served from the root.
+/// See `set_base_path`.
+pub fn base_path() -> String {
+    BASE_PATH.with(|cell| {
+        cell.borrow_mut()
+            .get_or_insert_with(|| {
+                base_href()
+                    .map(|href| normalize_base_path(path_of_url(&href)))
+                    .unwrap_or_default()
+            })
+            .clone()
+    })
+}
+
+/// Gets the URL of the page's `<base>` element.
+///
+/// The element's `href` property is resolved against the page's URL,
+/// so relative ones like `./` or `../` give the path they point to.
+fn base_href() -> Option<String> {
+    cfg_match! {
+        feature = "std_web" => document()
+            .query_selector("base[href]")
+            .unwrap_or_default()
+            .and_then(|base| js! { return @{base}.href; }.into_string()),
+        feature = "web_sys" => web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.query_selector("base[href]").unwrap_or_default())
+            .and_then(|base| base.dyn_into::<HtmlBaseElement>().ok())
+            .map(|base| base.href()),
+    }
+}
+
+/// Gets the path of a URL, which may also be just a path.
+fn path_of_url(url: &str) -> &str {
+    let path = match url.find("//") {
+        Some(index) => {
+            let after_host = &url[index + 2..];
+            after_host
+                .find('/')
+                .map_or("", |index| &after_host[index..])
+        }
+        None => url,
+    };
+    path.split(&['?', '#'][..]).next().unwrap_or_default()
+}
+
+/// Makes the base path start with a slash, and removes its trailing slash.
+fn normalize_base_path(base_path: &str) -> String {
+    let base_path = base_path.trim_matches('/');
+    if base_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", base_path)
+    }
+}
+
+/// Removes the base path from the start of the route.
+///
+/// The route keeps its leading `/`, so the base path itself, with or without a query or fragment,
+/// becomes `/`, `/?q=a` or `/#x`.
+/// Routes that don't start with the base path are left as they are.
+pub(crate) fn strip_base_path(base_path: &str, route: &str) -> String {
+    if !route.starts_with(base_path) {
+        return route.to_string();
+    }
+    let rest = &route[base_path.len()..];
+    if rest.starts_with('/') {
+        rest.to_string()
+    } else if rest.is_empty() || rest.starts_with('?') || rest.starts_with('#') {
+        format!("/{}", rest)
+    } else {
+        route.to_string()
+    }
+}
+
+/// Prepends the base path to a route that starts with a `/`.
+///
+/// Other routes, like those that only consist of a query or fragment,
+/// are relative to the current URL, which already includes the base path.
+pub(crate) fn prepend_base_path(base_path: &str, route: &str) -> String {
+    if route.starts_with('/') {
+        format!("{}{}", base_path, route)
+    } else {
+        route.to_string()
+    }
+}
+
+/// Builds the URL that is shown for the route in the given routing mode.
+///
+/// In hash mode, routes that start with a `/` are kept in the fragment of the base path.
+/// Other routes are put in the fragment of the current URL.
+pub(crate) fn route_href(mode: RoutingMode, base_path: &str, route: &str) -> String {
+    match mode {
+        RoutingMode::History => prepend_base_path(base_path, route),
+        RoutingMode::Hash if route.starts_with('/') => format!("{}/#{}", base_path, route),
+        RoutingMode::Hash => format!("#{}", route),
+    }
+}
+
+/// Gets the route kept in the fragment of the URL in hash mode.
+///
+/// An empty fragment is the `/` route.
+pub(crate) fn route_from_hash(hash: &str) -> String {
+    let route = if hash.starts_with('#') {
+        &hash[1..]
+    } else {
+        hash
+    };
+    if route.starts_with('/') {
+        route.to_string()
+    } else {
+        format!("/{}", route)
+    }
+}
+
 /// A service that facilitates manipulation of the browser's URL bar and responding to browser events
 /// when users press 'forward' or 'back'.
 ///
 /// The `T` determines what route state can be stored in the route service.
+///
+/// Routes are relative to the [base path](fn.set_base_path.html),
+/// and are kept in the browser's URL according to the [routing mode](fn.set_routing_mode.html).
 #[derive(Debug)]
 pub struct RouteService<STATE = ()> {
     history: History,
     location: Location,
+    base_path: String,
+    mode: RoutingMode,
+    /// The route that was last set or emitted, so that a `hashchange` that follows a `popstate`
+    /// for the same navigation isn't emitted twice.
+    last_route: Rc<RefCell<Option<String>>>,
+    /// The entry that was last set or emitted, which the entries that the browser creates for
+    /// links to fragments follow, and which how far the page is scrolled is recorded for when it
+    /// is left.
+    last_entry: Rc<Cell<Entry>>,
+    /// The index of the newest entry that was created since the page was loaded,
+    /// which is the last one that can be moved forward to.
+    newest_index: Rc<Cell<Option<u32>>>,
+    /// Whether how far the page is scrolled is recorded when an entry is left,
+    /// see `enable_scroll_restoration`.
+    scroll_restoration: Rc<Cell<bool>>,
     #[cfg(feature = "std_web")]
     event_listener: Option<EventListenerHandle>,
     #[cfg(feature = "web_sys")]
     event_listener: Option<EventListener>,
+    #[cfg(feature = "std_web")]
+    hash_listener: Option<EventListenerHandle>,
+    #[cfg(feature = "web_sys")]
+    hash_listener: Option<EventListener>,
+    #[cfg(feature = "std_web")]
+    unload_listener: Option<EventListenerHandle>,
+    #[cfg(feature = "web_sys")]
+    unload_listener: Option<EventListener>,
     phantom_data: PhantomData<STATE>,
 }
 
@@ -48,7 +266,14 @@ where
 
 impl<T> RouteService<T> {
     /// Creates the route service.
+    ///
+    /// It keeps the route according to the mode set with `set_routing_mode`.
     pub fn new() -> RouteService<T> {
+        RouteService::with_routing_mode(routing_mode())
+    }
+
+    /// Creates a route service that keeps the route according to the given mode.
+    pub fn with_routing_mode(mode: RoutingMode) -> RouteService<T> {
         let (history, location) = cfg_match! {
             feature = "std_web" => ({
                 (
@@ -65,35 +290,122 @@ impl<T> RouteService<T> {
             }),
         };
 
+        let base_path = base_path();
+        let route = Self::get_route_from_location(&location, &base_path, mode);
         RouteService {
             history,
             location,
+            base_path,
+            mode,
+            last_route: Rc::new(RefCell::new(Some(route))),
+            last_entry: Rc::new(Cell::new(Entry::default())),
+            newest_index: Rc::new(Cell::new(None)),
+            scroll_restoration: Rc::new(Cell::new(false)),
             event_listener: None,
+            hash_listener: None,
+            unload_listener: None,
             phantom_data: PhantomData,
         }
     }
 
     #[inline]
-    fn get_route_from_location(location: &Location) -> String {
+    fn get_route_from_location(location: &Location, base_path: &str, mode: RoutingMode) -> String {
+        let fragment = location.hash().unwrap();
+        if mode == RoutingMode::Hash {
+            return route_from_hash(&fragment);
+        }
         let path = location.pathname().unwrap();
         let query = location.search().unwrap();
-        let fragment = location.hash().unwrap();
-        format_route_string(&path, &query, &fragment)
+        strip_base_path(base_path, &format_route_string(&path, &query, &fragment))
+    }
+
+    /// Gets the path that routes are relative to, without a trailing slash.
+    pub fn base_path(&self) -> &str {
+        &self.base_path
     }
 
-    /// Gets the path name of the current url.
+    /// Gets where the route is kept in the browser's URL.
+    pub fn routing_mode(&self) -> RoutingMode {
+        self.mode
+    }
+
+    /// Scrolls to the element with the id in the fragment of the route, or to the top of the page
+    /// if there is no such element, once the app has rendered the route.
+    pub fn scroll_to_route(&self, route: &str) {
+        scroll_later(
+            fragment_of(route).map_or(ScrollTarget::Position(0.0, 0.0), ScrollTarget::Element),
+        );
+    }
+
+    /// Takes over restoring how far the page is scrolled when the browser moves back or forward
+    /// from the browser, which would restore it before the app has rendered the route.
+    ///
+    /// This sets `history.scrollRestoration` to `"manual"`, and records how far the page is
+    /// scrolled whenever an entry of the browser's history is left, so that `restore_scroll`
+    /// can scroll back to it.
+    pub fn enable_scroll_restoration(&self) {
+        let _ = cfg_match! {
+            feature = "std_web" => js! { @{&self.history}.scrollRestoration = "manual"; },
+            feature = "web_sys" => self.history.set_scroll_restoration(ScrollRestoration::Manual),
+        };
+        self.scroll_restoration.set(true);
+    }
+
+    /// Asks the user to confirm leaving the page, like when closing the tab, until this is called
+    /// with `None`.
+    ///
+    /// Browsers may show their own message instead of the prompt.
+    pub fn set_unload_prompt(&mut self, prompt: Option<String>) {
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                // Handles from stdweb don't remove their listener when they are dropped.
+                if let Some(listener) = self.unload_listener.take() {
+                    listener.remove();
+                }
+                self.unload_listener = prompt.map(|prompt| {
+                    window().add_event_listener(move |event: BeforeUnloadEvent| {
+                        event.prevent_default();
+                        js! { @(no_return) @{event}.returnValue = @{&prompt}; }
+                    })
+                });
+            } else if #[cfg(feature = "web_sys")] {
+                self.unload_listener = prompt.map(|prompt| {
+                    EventListener::new_with_options(
+                        web_sys::window().unwrap().as_ref(),
+                        "beforeunload",
+                        EventListenerOptions::enable_prevent_default(),
+                        move |event| {
+                            event.prevent_default();
+                            if let Some(event) = event.dyn_ref::<BeforeUnloadEvent>() {
+                                event.set_return_value(&prompt);
+                            }
+                        },
+                    )
+                });
+            }
+        };
+    }
+
+    /// Gets the path of the current route, like `/posts`.
+    ///
+    /// Like the route, this is relative to the base path, and in hash mode, it is taken from the
+    /// fragment of the URL.
     pub fn get_path(&self) -> String {
-        self.location.pathname().unwrap()
+        split_route(&self.current_route_string()).0.to_string()
     }
 
-    /// Gets the query string of the current url.
+    /// Gets the query of the current route, including the `?`, see `get_path`.
     pub fn get_query(&self) -> String {
-        self.location.search().unwrap()
+        split_route(&self.current_route_string()).1.to_string()
     }
 
-    /// Gets the fragment of the current url.
+    /// Gets the fragment of the current route, including the `#`, see `get_path`.
     pub fn get_fragment(&self) -> String {
-        self.location.hash().unwrap()
+        split_route(&self.current_route_string()).2.to_string()
+    }
+
+    fn current_route_string(&self) -> String {
+        Self::get_route_from_location(&self.location, &self.base_path, self.mode)
     }
 }
 
@@ -104,17 +416,48 @@ where
     /// Registers a callback to the route service.
     /// Callbacks will be called when the History API experiences a change such as
     /// popping a state off of its stack when the forward or back buttons are pressed.
+    ///
+    /// In hash mode, they are also called when the fragment of the URL changes,
+    /// like when the user edits it or follows a plain link to another fragment.
+    ///
+    /// The entries that the browser creates for links to fragments are given an
+    /// [entry](../state_codec/struct.Entry.html) when they are moved to, following the entry
+    /// they were created after.
     pub fn register_callback(&mut self, callback: Callback<Route<STATE>>) {
+        if self.mode == RoutingMode::Hash {
+            self.register_hash_callback(callback.clone());
+        }
+        // The entry the page was loaded in is given an entry, so that entries without one were
+        // created by the browser.
+        let (entry, state) = decode_state::<STATE>(get_state(&self.history));
+        let entry = entry.unwrap_or_else(|| {
+            let entry = Entry::new(0, random_key());
+            replace_state(&self.history, entry, &state);
+            entry
+        });
+        self.last_entry.set(entry);
+        let base_path = self.base_path.clone();
+        let mode = self.mode;
+        let last_route = self.last_route.clone();
+        let last_entry = self.last_entry.clone();
+        let newest_index = self.newest_index.clone();
+        let scroll_restoration = self.scroll_restoration.clone();
         let cb = move |event: PopStateEvent| {
-            let state_value: Value = event.state();
-            let state_string: String = cfg_match! {
-                feature = "std_web" => String::try_from(state_value).unwrap_or_default(),
-                feature = "web_sys" => state_value.as_string().unwrap_or_default(),
-            };
-            let state: STATE = serde_json::from_str(&state_string).unwrap_or_else(|_| {
-                log::error!("Could not deserialize state string");
-                STATE::default()
+            let (entry, state): (_, STATE) = decode_state(event.state());
+            let entry = entry.unwrap_or_else(|| {
+                let entry = Entry::new(last_entry.get().index() + 1, random_key());
+                let history: History = cfg_match! {
+                    feature = "std_web" => window().history(),
+                    feature = "web_sys" => web_sys::window().unwrap().history().unwrap(),
+                };
+                replace_state(&history, entry, &state);
+                newest_index.set(Some(entry.index()));
+                entry
             });
+            if scroll_restoration.get() {
+                record_scroll_position(last_entry.get());
+            }
+            last_entry.set(entry);
 
             // Can't use the existing location, because this is a callback, and can't move it in
             // here.
@@ -122,7 +465,8 @@ where
                 feature = "std_web" => window().location().unwrap(),
                 feature = "web_sys" => web_sys::window().unwrap().location(),
             };
-            let route: String = Self::get_route_from_location(&location);
+            let route: String = Self::get_route_from_location(&location, &base_path, mode);
+            *last_route.borrow_mut() = Some(route.clone());
 
             callback.emit(Route { route, state })
         };
@@ -141,60 +485,157 @@ where
         };
     }
 
+    /// Calls the callback when the fragment of the URL changes to another route.
+    ///
+    /// Browsers fire a `popstate` before the `hashchange` when the user navigates to another
+    /// fragment, so a route that was just emitted isn't emitted again.
+    fn register_hash_callback(&mut self, callback: Callback<Route<STATE>>) {
+        let last_route = self.last_route.clone();
+        let last_entry = self.last_entry.clone();
+        let scroll_restoration = self.scroll_restoration.clone();
+        let cb = move || {
+            let location: Location = cfg_match! {
+                feature = "std_web" => window().location().unwrap(),
+                feature = "web_sys" => web_sys::window().unwrap().location(),
+            };
+            let route = route_from_hash(&location.hash().unwrap());
+            if last_route.borrow().as_ref() == Some(&route) {
+                return;
+            }
+            if scroll_restoration.get() {
+                record_scroll_position(last_entry.get());
+            }
+            *last_route.borrow_mut() = Some(route.clone());
+
+            callback.emit(Route {
+                route,
+                state: STATE::default(),
+            })
+        };
+
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                self.hash_listener = Some(window().add_event_listener(move |_: HashChangeEvent| {
+                    cb()
+                }));
+            } else if #[cfg(feature = "web_sys")] {
+                self.hash_listener = Some(EventListener::new(web_sys::window().unwrap().as_ref(), "hashchange", move |_| {
+                    cb()
+                }));
+            }
+        };
+    }
+
+    /// Moves through the browser's history by `delta` entries, backwards if it is negative.
+    ///
+    /// Like pressing the forward or back buttons, this calls the registered callback
+    /// once the browser has moved to the entry.
+    ///
+    /// Returns false if the app has no entry that far away, so it would be left.
+    /// Entries are counted with the [index](../state_codec/struct.Entry.html#method.index)
+    /// recorded in their state.
+    /// Until the app creates an entry, the entries after the one the page was loaded in can't be
+    /// told apart from those of other sites, which the browser would move to without notifying
+    /// the app, so the app can't move forward.
+    pub fn go(&mut self, delta: i32) -> bool {
+        let entry = self.entry();
+        let index = i64::from(entry.index()) + i64::from(delta);
+        let newest = self.newest_index.get().unwrap_or_else(|| entry.index());
+        if delta == 0 || index < 0 || index > i64::from(newest) {
+            return false;
+        }
+        let _ = cfg_match! {
+            feature = "std_web" => self.history.go(delta),
+            feature = "web_sys" => self.history.go_with_delta(delta),
+        };
+        true
+    }
+
+    /// Moves back to the previous entry in the browser's history, see `go`.
+    pub fn back(&mut self) -> bool {
+        self.go(-1)
+    }
+
+    /// Moves forward to the next entry in the browser's history, see `go`.
+    pub fn forward(&mut self) -> bool {
+        self.go(1)
+    }
+
+    /// Scrolls to where the page was scrolled when the entry that the browser's history is at was
+    /// last left, once the app has rendered the route.
+    ///
+    /// Positions are only recorded after `enable_scroll_restoration` is called, and are kept in
+    /// the session storage under the [key](../state_codec/struct.Entry.html#method.key) of
+    /// their entry, so they survive reloading the page.
+    /// If no position was recorded for the entry, this is the same as `scroll_to_route`.
+    pub fn restore_scroll(&self, route: &str) {
+        match self.entry().key().and_then(stored_scroll_position) {
+            Some((x, y)) => scroll_later(ScrollTarget::Position(x, y)),
+            None => self.scroll_to_route(route),
+        }
+    }
+
+    /// Gets the entry that the browser's history is at.
+    pub fn entry(&self) -> Entry {
+        decode_state::<STATE>(get_state(&self.history))
+            .0
+            .unwrap_or_default()
+    }
+
     /// Sets the browser's url bar to contain the provided route,
     /// and creates a history entry that can be navigated via the forward and back buttons.
     ///
     /// The route should be a relative path that starts with a `/`.
+    /// The base path is prepended to it, and in hash mode, it is put in the fragment.
+    ///
+    /// If scroll restoration is enabled, how far the page is scrolled is recorded for the entry
+    /// that is left.
     pub fn set_route(&mut self, route: &str, state: STATE) {
-        let state_string: String = serde_json::to_string(&state).unwrap_or_else(|_| {
-            log::error!("Could not serialize state string");
-            "".to_string()
-        });
+        let left = self.entry();
+        if self.scroll_restoration.get() {
+            record_scroll_position(left);
+        }
+        *self.last_route.borrow_mut() = Some(route.to_string());
+        let entry = Entry::new(left.index() + 1, random_key());
+        self.last_entry.set(entry);
+        self.newest_index.set(Some(entry.index()));
+        let route = &route_href(self.mode, &self.base_path, route);
+        let state_value = encode_state(entry, &state);
         cfg_match! {
             feature = "std_web" => ({
-                self.history.push_state(state_string, "", Some(route));
+                self.history.push_state(state_value, "", Some(route));
             }),
             feature = "web_sys" => ({
-                let _ = self.history.push_state_with_url(&Value::from_str(&state_string), "", Some(route));
+                let _ = self.history.push_state_with_url(&state_value, "", Some(route));
             }),
         };
     }
 
     /// Replaces the route with another one removing the most recent history event and
     /// creating another history event in its place.
+    ///
+    /// The base path is prepended to the route, and in hash mode, it is put in the fragment.
     pub fn replace_route(&mut self, route: &str, state: STATE) {
-        let state_string: String = serde_json::to_string(&state).unwrap_or_else(|_| {
-            log::error!("Could not serialize state string");
-            "".to_string()
-        });
+        *self.last_route.borrow_mut() = Some(route.to_string());
+        let entry = self.entry();
+        self.last_entry.set(entry);
+        let route = &route_href(self.mode, &self.base_path, route);
+        let state_value = encode_state(entry, &state);
         cfg_match! {
             feature = "std_web" => ({
-                let _ = self.history.replace_state(state_string, "", Some(route));
+                let _ = self.history.replace_state(state_value, "", Some(route));
             }),
             feature = "web_sys" => ({
-                let _ = self.history.replace_state_with_url(&Value::from_str(&state_string), "", Some(route));
+                let _ = self.history.replace_state_with_url(&state_value, "", Some(route));
             }),
         };
     }
 
-    /// Gets the concatenated path, query, and fragment.
+    /// Gets the concatenated path, query, and fragment, with the base path removed.
     pub fn get_route(&self) -> Route<STATE> {
-        let route_string = Self::get_route_from_location(&self.location);
-        let state: STATE = get_state_string(&self.history)
-            .or_else(|| {
-                log::trace!("History state is empty");
-                None
-            })
-            .and_then(|state_string| -> Option<STATE> {
-                serde_json::from_str(&state_string)
-                    .ok()
-                    .or_else(|| {
-                        log::error!("Could not deserialize state string");
-                        None
-                    })
-                    .and_then(std::convert::identity) // flatten
-            })
-            .unwrap_or_default();
+        let route_string =
+            Self::get_route_from_location(&self.location, &self.base_path, self.mode);
+        let (_, state): (_, STATE) = decode_state(get_state(&self.history));
         Route {
             route: route_string,
             state,
@@ -202,6 +643,123 @@ where
     }
 }
 
+/// Creates a key for a new entry, see [`Entry::key`](../state_codec/struct.Entry.html#method.key).
+fn random_key() -> u32 {
+    let random: f64 = cfg_match! {
+        feature = "std_web" => js! { return Math.random(); }.try_into().unwrap_or_default(),
+        feature = "web_sys" => js_sys::Math::random(),
+    };
+    (random * f64::from(u32::max_value())) as u32
+}
+
+/// The session storage key that how far the page was scrolled in an entry is stored under.
+fn scroll_position_key(key: u32) -> String {
+    format!("yew-router:scroll:{}", key)
+}
+
+/// Records how far the page is scrolled for the entry that is left, if it has a key.
+fn record_scroll_position(entry: Entry) {
+    let key = match entry.key() {
+        Some(key) => scroll_position_key(key),
+        None => return,
+    };
+    cfg_if! {
+        if #[cfg(feature = "std_web")] {
+            let position = (window().page_x_offset(), window().page_y_offset());
+            let value = serde_json::to_string(&position).unwrap_or_default();
+            let _ = window().session_storage().insert(&key, &value);
+        } else if #[cfg(feature = "web_sys")] {
+            let window = match web_sys::window() {
+                Some(window) => window,
+                None => return,
+            };
+            let position = (
+                window.scroll_x().unwrap_or_default(),
+                window.scroll_y().unwrap_or_default(),
+            );
+            let value = serde_json::to_string(&position).unwrap_or_default();
+            if let Ok(Some(storage)) = window.session_storage() {
+                let _ = storage.set_item(&key, &value);
+            }
+        }
+    }
+}
+
+/// Gets how far the page was scrolled when the entry with the key was last left.
+fn stored_scroll_position(key: u32) -> Option<(f64, f64)> {
+    let key = scroll_position_key(key);
+    let value = cfg_match! {
+        feature = "std_web" => window().session_storage().get(&key),
+        feature = "web_sys" => web_sys::window()
+            .and_then(|window| window.session_storage().ok().and_then(|storage| storage))
+            .and_then(|storage| storage.get_item(&key).ok().and_then(|value| value)),
+    };
+    value.and_then(|value| serde_json::from_str(&value).ok())
+}
+
+/// Where the page is scrolled to after navigating.
+enum ScrollTarget {
+    Position(f64, f64),
+    /// The id of an element to scroll into view.
+    Element(String),
+}
+
+/// Scrolls the page after the current task, so the app has rendered the route by then.
+fn scroll_later(target: ScrollTarget) {
+    let scroll = move || {
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                match target {
+                    ScrollTarget::Element(id) => match document().get_element_by_id(&id) {
+                        Some(element) => js! { @(no_return) @{element}.scrollIntoView(); },
+                        None => js! { @(no_return) window.scrollTo(0, 0); },
+                    },
+                    ScrollTarget::Position(x, y) => js! { @(no_return) window.scrollTo(@{x}, @{y}); },
+                }
+            } else if #[cfg(feature = "web_sys")] {
+                let window = web_sys::window().unwrap();
+                let element = match &target {
+                    ScrollTarget::Element(id) => window
+                        .document()
+                        .and_then(|document| document.get_element_by_id(id)),
+                    ScrollTarget::Position(..) => None,
+                };
+                match (element, target) {
+                    (Some(element), _) => element.scroll_into_view(),
+                    (None, ScrollTarget::Position(x, y)) => window.scroll_to_with_x_and_y(x, y),
+                    (None, ScrollTarget::Element(_)) => window.scroll_to_with_x_and_y(0.0, 0.0),
+                }
+            }
+        }
+    };
+    cfg_match! {
+        feature = "std_web" => set_timeout(scroll, 0),
+        feature = "web_sys" => Timeout::new(0, scroll).forget(),
+    };
+}
+
+/// Splits a route into its path, query and fragment, keeping their `?` and `#`.
+pub(crate) fn split_route(route: &str) -> (&str, &str, &str) {
+    let (rest, fragment) = match route.find('#') {
+        Some(index) => route.split_at(index),
+        None => (route, ""),
+    };
+    let (path, query) = match rest.find('?') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    (path, query, fragment)
+}
+
+/// Gets the percent-decoded fragment of the route, without the `#`.
+pub(crate) fn fragment_of(route: &str) -> Option<String> {
+    route
+        .find('#')
+        .map(|index| &route[index + 1..])
+        .filter(|fragment| !fragment.is_empty())
+        .map(crate::matcher::encoding::decode)
+}
+
 /// Formats a path, query, and fragment into a string.
 ///
 /// # Note
@@ -224,9 +782,120 @@ fn get_state(history: &History) -> Value {
     }
 }
 
-fn get_state_string(history: &History) -> Option<String> {
-    cfg_match! {
-        feature = "std_web" => get_state(history).try_into().ok(),
-        feature = "web_sys" => get_state(history).as_string(),
+/// Encodes the state of the entry with the
+/// [state format](../state_codec/fn.set_state_format.html).
+fn encode_state<STATE: RouteState>(entry: Entry, state: &STATE) -> Value {
+    state_format().encode(entry, state).unwrap_or_else(|error| {
+        log::error!("Could not encode the history state: {}", error);
+        Value::from("")
+    })
+}
+
+/// Decodes a state stored in `history.state`, with the entry it is stored in.
+///
+/// Entries that the route services didn't create, like the first one, have no state,
+/// so they have no entry and get the default state.
+fn decode_state<STATE: RouteState>(value: Value) -> (Option<Entry>, STATE) {
+    let is_empty = cfg_match! {
+        feature = "std_web" => value == Value::Null || value == Value::Undefined,
+        feature = "web_sys" => value.is_null() || value.is_undefined(),
+    };
+    if is_empty {
+        log::trace!("History state is empty");
+        return (None, STATE::default());
+    }
+    let (entry, state) = state_format().decode(value);
+    let state = state.unwrap_or_else(|error| {
+        log::error!("Could not decode the history state: {}", error);
+        STATE::default()
+    });
+    (Some(entry), state)
+}
+
+/// Replaces the state of the current entry, without changing its URL.
+fn replace_state<STATE: RouteState>(history: &History, entry: Entry, state: &STATE) {
+    let state_value = encode_state(entry, state);
+    let _ = cfg_match! {
+        feature = "std_web" => history.replace_state(state_value, "", None),
+        feature = "web_sys" => history.replace_state(&state_value, ""),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_of_base_href() {
+        assert_eq!(path_of_url("https://example.com/portal/"), "/portal/");
+        assert_eq!(path_of_url("//example.com/portal/?a=b"), "/portal/");
+        assert_eq!(path_of_url("https://example.com"), "");
+        assert_eq!(path_of_url("/portal/#a"), "/portal/");
+    }
+
+    #[test]
+    fn base_path_is_normalized() {
+        assert_eq!(normalize_base_path("/portal/"), "/portal");
+        assert_eq!(normalize_base_path("portal"), "/portal");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path(""), "");
+    }
+
+    #[test]
+    fn base_path_is_stripped() {
+        assert_eq!(strip_base_path("/portal", "/portal/users/1"), "/users/1");
+        assert_eq!(strip_base_path("/portal", "/portal"), "/");
+        assert_eq!(strip_base_path("/portal", "/portal?q=a"), "/?q=a");
+        assert_eq!(strip_base_path("/portal", "/portal#x"), "/#x");
+        assert_eq!(strip_base_path("/portal", "/portals/1"), "/portals/1");
+        assert_eq!(strip_base_path("", "/users/1"), "/users/1");
+    }
+
+    #[test]
+    fn base_path_is_prepended() {
+        assert_eq!(prepend_base_path("/portal", "/users/1"), "/portal/users/1");
+        assert_eq!(prepend_base_path("/portal", "?q=a"), "?q=a");
+        assert_eq!(prepend_base_path("", "/users/1"), "/users/1");
+    }
+
+    #[test]
+    fn hash_route_href() {
+        assert_eq!(route_href(RoutingMode::Hash, "", "/users/1"), "/#/users/1");
+        assert_eq!(
+            route_href(RoutingMode::Hash, "/portal", "/users/1?q=a"),
+            "/portal/#/users/1?q=a"
+        );
+        assert_eq!(route_href(RoutingMode::Hash, "/portal", "?q=a"), "#?q=a");
+        assert_eq!(
+            route_href(RoutingMode::History, "/portal", "/users/1"),
+            "/portal/users/1"
+        );
+    }
+
+    #[test]
+    fn route_is_read_from_hash() {
+        assert_eq!(route_from_hash("#/users/1?q=a#b"), "/users/1?q=a#b");
+        assert_eq!(route_from_hash("#users"), "/users");
+        assert_eq!(route_from_hash("#"), "/");
+        assert_eq!(route_from_hash(""), "/");
+    }
+
+    #[test]
+    fn route_is_split() {
+        assert_eq!(split_route("/posts?a=b#c"), ("/posts", "?a=b", "#c"));
+        assert_eq!(split_route("/posts#c?d"), ("/posts", "", "#c?d"));
+        assert_eq!(split_route("/posts"), ("/posts", "", ""));
+        assert_eq!(split_route("?a=b"), ("", "?a=b", ""));
+    }
+
+    #[test]
+    fn fragment_of_route() {
+        assert_eq!(fragment_of("/posts#comments"), Some("comments".to_string()));
+        assert_eq!(
+            fragment_of("/posts?a=b#two%20words"),
+            Some("two words".to_string())
+        );
+        assert_eq!(fragment_of("/posts#"), None);
+        assert_eq!(fragment_of("/posts"), None);
     }
 }
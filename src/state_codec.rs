@@ -0,0 +1,541 @@
+//! Codecs that store route states in the browser's history.
+//!
+//! States are wrapped in an envelope that records the version of the app's state type,
+//! so that states written by older deployments can be migrated when they are read.
+//! The envelope also records which entry of the app's history the state is stored in.
+use crate::route::RouteState;
+use cfg_if::cfg_if;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{any::Any, cell::RefCell, fmt::Debug, rc::Rc};
+
+cfg_if! {
+    if #[cfg(feature = "std_web")] {
+        use stdweb::{
+            js,
+            unstable::{TryFrom, TryInto},
+            web::TypedArray,
+            Value,
+        };
+    } else if #[cfg(feature = "web_sys")] {
+        use js_sys::{Uint8Array, JSON};
+        use wasm_bindgen::{JsCast, JsValue as Value};
+    }
+}
+
+/// Error returned when a route state can't be encoded or decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateError {
+    message: String,
+}
+
+impl StateError {
+    /// Creates an error with the message.
+    pub fn new(message: impl Into<String>) -> Self {
+        StateError {
+            message: message.into(),
+        }
+    }
+
+    /// Gets the message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// The history entry that a state is stored in, which is recorded in the state's envelope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    index: u32,
+    #[serde(default)]
+    key: Option<u32>,
+}
+
+impl Entry {
+    pub(crate) fn new(index: u32, key: u32) -> Self {
+        Entry {
+            index,
+            key: Some(key),
+        }
+    }
+
+    /// Gets the number of entries before this one that the app created,
+    /// since the app was loaded in the browser's tab.
+    ///
+    /// Entries that weren't created by the app, like the one the app was loaded in, have index 0.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Gets the random key that tells this entry apart from the other entries in the browser's
+    /// tab, which how far the page was scrolled in the entry is stored under.
+    ///
+    /// Entries that were stored without one, like those of older versions of the app, have none.
+    pub fn key(&self) -> Option<u32> {
+        self.key
+    }
+}
+
+/// A state taken out of its envelope, before it is decoded into a state type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodedState {
+    /// A state stored by the `JsonStringCodec` or `JsObjectCodec`,
+    /// or by versions of yew-router without codecs.
+    Json(serde_json::Value),
+    /// A state stored by the `BinaryCodec`.
+    Binary(Vec<u8>),
+}
+
+impl EncodedState {
+    /// Decodes the state into a state type, like the one an older deployment stored.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, StateError> {
+        match self {
+            EncodedState::Json(json) => {
+                T::deserialize(json).map_err(|error| StateError::new(error.to_string()))
+            }
+            EncodedState::Binary(bytes) => {
+                bincode::deserialize(bytes).map_err(|error| StateError::new(error.to_string()))
+            }
+        }
+    }
+}
+
+/// Turns route states into values that are stored in `history.state`, and back.
+pub trait StateCodec<STATE>: Debug {
+    /// Encodes the state in an envelope with its version and entry.
+    fn encode(&self, version: u32, entry: Entry, state: &STATE) -> Result<Value, StateError>;
+
+    /// Takes a state out of its envelope, returning its version and entry.
+    ///
+    /// Values that aren't in an envelope were stored by versions of yew-router without codecs,
+    /// as a JSON string, and are returned as version 0 with the default entry.
+    fn decode(&self, value: Value) -> Result<(u32, Entry, EncodedState), StateError>;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(default)]
+    entry: Entry,
+    state: T,
+}
+
+/// Stores states as JSON strings, like `{"version":1,"entry":{"index":2,"key":7},"state":...}`.
+///
+/// This is the default codec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonStringCodec;
+
+impl<STATE: Serialize> StateCodec<STATE> for JsonStringCodec {
+    fn encode(&self, version: u32, entry: Entry, state: &STATE) -> Result<Value, StateError> {
+        let json = json_envelope(version, entry, state)?;
+        Ok(Value::from(json))
+    }
+
+    fn decode(&self, value: Value) -> Result<(u32, Entry, EncodedState), StateError> {
+        parse_json_envelope(&string_of(value)?)
+    }
+}
+
+/// Stores states as JavaScript objects,
+/// like `{ version: 1, entry: { index: 2, key: 7 }, state: ... }`,
+/// which can be inspected in the browser's developer tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsObjectCodec;
+
+impl<STATE: Serialize> StateCodec<STATE> for JsObjectCodec {
+    fn encode(&self, version: u32, entry: Entry, state: &STATE) -> Result<Value, StateError> {
+        let json = json_envelope(version, entry, state)?;
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                Ok(js! { return JSON.parse(@{json}); })
+            } else if #[cfg(feature = "web_sys")] {
+                JSON::parse(&json).map_err(|_| StateError::new("the state isn't valid JSON"))
+            }
+        }
+    }
+
+    fn decode(&self, value: Value) -> Result<(u32, Entry, EncodedState), StateError> {
+        // States stored without a codec are strings.
+        if let Ok(json) = string_of(value.clone()) {
+            return parse_json_envelope(&json);
+        }
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                let json: Value = js! { return JSON.stringify(@{value}); };
+                let json: String = json.try_into().map_err(|_| StateError::new("the state isn't an object"))?;
+            } else if #[cfg(feature = "web_sys")] {
+                let json: String = JSON::stringify(&value)
+                    .map_err(|_| StateError::new("the state isn't an object"))?
+                    .into();
+            }
+        }
+        parse_json_envelope(&json)
+    }
+}
+
+/// Stores states as bytes in a `Uint8Array`, encoded with bincode after the version and entry.
+///
+/// This is the most compact format, but states can't be inspected in the browser,
+/// and migrations have to decode states with the exact types that older deployments used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BinaryCodec;
+
+impl<STATE: Serialize> StateCodec<STATE> for BinaryCodec {
+    fn encode(&self, version: u32, entry: Entry, state: &STATE) -> Result<Value, StateError> {
+        let bytes = binary_envelope(version, entry, state)?;
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                Ok(Value::Reference(TypedArray::<u8>::from(bytes.as_slice()).into()))
+            } else if #[cfg(feature = "web_sys")] {
+                Ok(Uint8Array::from(bytes.as_slice()).into())
+            }
+        }
+    }
+
+    fn decode(&self, value: Value) -> Result<(u32, Entry, EncodedState), StateError> {
+        // States stored without a codec are strings.
+        if let Ok(json) = string_of(value.clone()) {
+            return parse_json_envelope(&json);
+        }
+        cfg_if! {
+            if #[cfg(feature = "std_web")] {
+                let bytes = TypedArray::<u8>::try_from(value)
+                    .map_err(|_| StateError::new("the state isn't a Uint8Array"))?
+                    .to_vec();
+            } else if #[cfg(feature = "web_sys")] {
+                let bytes = value
+                    .dyn_into::<Uint8Array>()
+                    .map_err(|_| StateError::new("the state isn't a Uint8Array"))?
+                    .to_vec();
+            }
+        }
+        parse_binary_envelope(&bytes)
+    }
+}
+
+fn string_of(value: Value) -> Result<String, StateError> {
+    cfg_if! {
+        if #[cfg(feature = "std_web")] {
+            String::try_from(value).map_err(|_| StateError::new("the state isn't a string"))
+        } else if #[cfg(feature = "web_sys")] {
+            value.as_string().ok_or_else(|| StateError::new("the state isn't a string"))
+        }
+    }
+}
+
+fn json_envelope<STATE: Serialize>(
+    version: u32,
+    entry: Entry,
+    state: &STATE,
+) -> Result<String, StateError> {
+    serde_json::to_string(&Envelope {
+        version,
+        entry,
+        state,
+    })
+    .map_err(|error| StateError::new(error.to_string()))
+}
+
+/// Takes the state out of a JSON envelope.
+///
+/// JSON that isn't an envelope is a state stored without a codec, so it has version 0.
+fn parse_json_envelope(json: &str) -> Result<(u32, Entry, EncodedState), StateError> {
+    let json: serde_json::Value =
+        serde_json::from_str(json).map_err(|error| StateError::new(error.to_string()))?;
+    match Envelope::<serde_json::Value>::deserialize(&json) {
+        Ok(envelope) => Ok((
+            envelope.version,
+            envelope.entry,
+            EncodedState::Json(envelope.state),
+        )),
+        Err(_) => Ok((0, Entry::default(), EncodedState::Json(json))),
+    }
+}
+
+fn binary_envelope<STATE: Serialize>(
+    version: u32,
+    entry: Entry,
+    state: &STATE,
+) -> Result<Vec<u8>, StateError> {
+    let mut bytes = version.to_le_bytes().to_vec();
+    bincode::serialize_into(&mut bytes, &(entry, state))
+        .map_err(|error| StateError::new(error.to_string()))?;
+    Ok(bytes)
+}
+
+fn parse_binary_envelope(bytes: &[u8]) -> Result<(u32, Entry, EncodedState), StateError> {
+    if bytes.len() < 4 {
+        return Err(StateError::new("the state is too short to hold a version"));
+    }
+    let (version, rest) = bytes.split_at(4);
+    let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+    let mut rest = rest;
+    let entry: Entry =
+        bincode::deserialize_from(&mut rest).map_err(|error| StateError::new(error.to_string()))?;
+    Ok((version, entry, EncodedState::Binary(rest.to_vec())))
+}
+
+type Migration<STATE> = Rc<dyn Fn(u32, &EncodedState) -> Result<STATE, StateError>>;
+
+/// Determines how route states are stored in the browser's history.
+///
+/// # Example
+/// ```no_run
+/// use serde::{Deserialize, Serialize};
+/// use yew_router::state_codec::{JsObjectCodec, StateFormat};
+///
+/// #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// struct State {
+///     scroll_to: Option<String>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct StateV0 {
+///     anchor: String,
+/// }
+///
+/// let format = StateFormat::new(JsObjectCodec)
+///     .version(1)
+///     .migration(|version, state| {
+///         assert_eq!(version, 0);
+///         let old: StateV0 = state.decode()?;
+///         Ok(State {
+///             scroll_to: Some(old.anchor),
+///         })
+///     });
+/// ```
+pub struct StateFormat<STATE> {
+    codec: Rc<dyn StateCodec<STATE>>,
+    version: u32,
+    migration: Option<Migration<STATE>>,
+}
+
+impl<STATE> Debug for StateFormat<STATE> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateFormat")
+            .field("codec", &self.codec)
+            .field("version", &self.version)
+            .field("migration", &self.migration.as_ref().map(|_| "-"))
+            .finish()
+    }
+}
+
+impl<STATE> Clone for StateFormat<STATE> {
+    fn clone(&self) -> Self {
+        StateFormat {
+            codec: self.codec.clone(),
+            version: self.version,
+            migration: self.migration.clone(),
+        }
+    }
+}
+
+impl<STATE: Serialize> Default for StateFormat<STATE> {
+    fn default() -> Self {
+        StateFormat::new(JsonStringCodec)
+    }
+}
+
+impl<STATE> StateFormat<STATE> {
+    /// Creates a format that stores states of version 0 with the codec.
+    pub fn new(codec: impl StateCodec<STATE> + 'static) -> Self {
+        StateFormat {
+            codec: Rc::new(codec),
+            version: 0,
+            migration: None,
+        }
+    }
+
+    /// Sets the version of the state type, which is stored with every state.
+    ///
+    /// It should be increased whenever the state type changes in a way that states stored by
+    /// older deployments can't be decoded anymore.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the hook that turns states stored with an older or newer version into a state.
+    ///
+    /// Without one, such states are decoded as if they had the current version.
+    pub fn migration<F>(mut self, migration: F) -> Self
+    where
+        F: Fn(u32, &EncodedState) -> Result<STATE, StateError> + 'static,
+    {
+        self.migration = Some(Rc::new(migration));
+        self
+    }
+}
+
+impl<STATE: DeserializeOwned> StateFormat<STATE> {
+    /// Encodes the state of the entry into the value that is stored in `history.state`.
+    pub fn encode(&self, entry: Entry, state: &STATE) -> Result<Value, StateError> {
+        self.codec.encode(self.version, entry, state)
+    }
+
+    /// Decodes a value stored in `history.state`, migrating it if it has another version.
+    ///
+    /// The entry is read even if the state can't be decoded, and is the default entry if
+    /// the value isn't an envelope.
+    pub fn decode(&self, value: Value) -> (Entry, Result<STATE, StateError>) {
+        match self.codec.decode(value) {
+            Ok((version, entry, state)) => (
+                entry,
+                decode_version(self.version, self.migration.as_ref(), version, &state),
+            ),
+            Err(error) => (Entry::default(), Err(error)),
+        }
+    }
+}
+
+/// Decodes the state, migrating it if it doesn't have the current version.
+fn decode_version<STATE: DeserializeOwned>(
+    current_version: u32,
+    migration: Option<&Migration<STATE>>,
+    version: u32,
+    state: &EncodedState,
+) -> Result<STATE, StateError> {
+    match migration {
+        Some(migration) if version != current_version => migration(version, state),
+        _ => state.decode(),
+    }
+}
+
+thread_local! {
+    static STATE_FORMAT: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+/// Sets how route services store states of type `STATE` in the browser's history.
+///
+/// Like `service::set_base_path`, this must be called before the first route service is
+/// created.
+/// If it isn't called, states are stored as JSON strings with version 0.
+pub fn set_state_format<STATE: RouteState>(format: StateFormat<STATE>) {
+    STATE_FORMAT.with(|cell| *cell.borrow_mut() = Some(Box::new(format)));
+}
+
+/// Gets how route services store states of type `STATE` in the browser's history.
+///
+/// See `set_state_format`.
+pub fn state_format<STATE: RouteState>() -> StateFormat<STATE> {
+    STATE_FORMAT.with(|cell| {
+        cell.borrow()
+            .as_ref()
+            .and_then(|format| format.downcast_ref::<StateFormat<STATE>>())
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+    struct State {
+        page: u32,
+        anchor: Option<String>,
+    }
+
+    fn state() -> State {
+        State {
+            page: 2,
+            anchor: Some("top".to_string()),
+        }
+    }
+
+    #[test]
+    fn json_envelope_round_trips() {
+        let json = json_envelope(3, Entry::new(5, 9), &state()).expect("should encode");
+        assert_eq!(
+            json,
+            r#"{"version":3,"entry":{"index":5,"key":9},"state":{"page":2,"anchor":"top"}}"#
+        );
+        let (version, entry, encoded) = parse_json_envelope(&json).expect("should parse");
+        assert_eq!(version, 3);
+        assert_eq!(entry, Entry::new(5, 9));
+        assert_eq!(encoded.decode::<State>(), Ok(state()));
+    }
+
+    #[test]
+    fn bare_json_has_version_zero() {
+        let (_, entry, _) = parse_json_envelope(r#"{"version":1,"entry":{"index":5},"state":2}"#)
+            .expect("should parse");
+        assert_eq!(entry.key(), None);
+        let (version, entry, encoded) =
+            parse_json_envelope(r#"{"page":2,"anchor":"top"}"#).expect("should parse");
+        assert_eq!(version, 0);
+        assert_eq!(entry, Entry::default());
+        assert_eq!(encoded.decode::<State>(), Ok(state()));
+        let (version, _, encoded) = parse_json_envelope("null").expect("should parse");
+        assert_eq!(version, 0);
+        assert_eq!(encoded.decode::<Option<State>>(), Ok(None));
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        assert!(parse_json_envelope("{").is_err());
+    }
+
+    #[test]
+    fn binary_envelope_round_trips() {
+        let bytes = binary_envelope(7, Entry::new(5, 9), &state()).expect("should encode");
+        assert_eq!(&bytes[..13], &[7, 0, 0, 0, 5, 0, 0, 0, 1, 9, 0, 0, 0]);
+        let (version, entry, encoded) = parse_binary_envelope(&bytes).expect("should parse");
+        assert_eq!(version, 7);
+        assert_eq!(entry, Entry::new(5, 9));
+        assert_eq!(encoded.decode::<State>(), Ok(state()));
+        assert!(parse_binary_envelope(&[1, 0]).is_err());
+        assert!(parse_binary_envelope(&[1, 0, 0, 0, 5]).is_err());
+    }
+
+    #[test]
+    fn older_versions_are_migrated() {
+        #[derive(Deserialize)]
+        struct StateV1 {
+            page: u32,
+        }
+
+        let migration: Migration<State> = Rc::new(|version, state| {
+            assert_eq!(version, 1);
+            let old: StateV1 = state.decode()?;
+            Ok(State {
+                page: old.page,
+                anchor: None,
+            })
+        });
+        let (version, _, encoded) =
+            parse_json_envelope(r#"{"version":1,"state":{"page":4}}"#).expect("should parse");
+        assert_eq!(
+            decode_version(2, Some(&migration), version, &encoded),
+            Ok(State {
+                page: 4,
+                anchor: None
+            })
+        );
+        let (version, _, encoded) =
+            parse_json_envelope(&json_envelope(2, Entry::default(), &state()).unwrap())
+                .expect("should parse");
+        assert_eq!(
+            decode_version(2, Some(&migration), version, &encoded),
+            Ok(state())
+        );
+    }
+
+    #[test]
+    fn decode_errors_name_the_problem() {
+        let error = EncodedState::Json(serde_json::json!({ "page": "two" }))
+            .decode::<State>()
+            .expect_err("page isn't a number");
+        assert!(error.message().contains("invalid type"), "{}", error);
+    }
+}
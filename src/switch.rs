@@ -1,5 +1,11 @@
 //! Parses routes into enums or structs.
-use crate::route::Route;
+use crate::{
+    matcher::{
+        encoding::{decode, encode_into, Encoding},
+        explain::VariantReport,
+    },
+    route::Route,
+};
 use std::fmt::Write;
 
 /// Alias to Switch.
@@ -56,9 +62,31 @@ pub trait Switch: Sized {
     /// Get self from a part of the state
     fn from_route_part<STATE>(part: String, state: Option<STATE>) -> (Option<Self>, Option<STATE>);
 
+    /// Get self from a section of a route that was captured for a field by a matcher string.
+    ///
+    /// Types that are converted to and from strings percent-decode the section before parsing it,
+    /// see `build_route_capture`.
+    /// Other types, like derived ones, are given the section as it is.
+    fn from_route_capture<STATE>(
+        part: String,
+        state: Option<STATE>,
+    ) -> (Option<Self>, Option<STATE>) {
+        Self::from_route_part(part, state)
+    }
+
     /// Build part of a route from itself.
     fn build_route_section<STATE>(self, route: &mut String) -> Option<STATE>;
 
+    /// Build part of a route from itself, when it is stored in a field that is captured by a
+    /// section of a matcher string.
+    ///
+    /// Types that are converted to and from strings percent-encode themselves with the
+    /// `encoding` of the capture, and decode the captured section in `from_route_capture`.
+    /// Other types, like derived ones, build their route as they would on their own.
+    fn build_route_capture<STATE>(self, route: &mut String, _encoding: Encoding) -> Option<STATE> {
+        self.build_route_section(route)
+    }
+
     /// Called when the key (the named capture group) can't be located. Instead of failing outright,
     /// a default item can be provided instead.
     ///
@@ -70,6 +98,170 @@ pub trait Switch: Sized {
     fn key_not_available() -> Option<Self> {
         None
     }
+
+    /// Whether the route this was switched from should be replaced with the route built from it,
+    /// when the two only differ by a trailing slash.
+    ///
+    /// Derived implementations return true for items with
+    /// `#[switch(trailing_slash = "redirect")]`.
+    fn redirect_trailing_slash(&self) -> bool {
+        false
+    }
+
+    /// Explains how the route is matched by each variant, or by the struct.
+    ///
+    /// Derived implementations report on every variant in order.
+    /// The first variant whose report is a match is the one that `switch` produces.
+    /// A variant whose route matched, but whose captured values couldn't be converted into its
+    /// fields, has a `MismatchReason::Conversion`.
+    fn explain_route(_route: &str) -> Vec<VariantReport> {
+        Vec::new()
+    }
+
+    /// Builds the href that links to this item.
+    ///
+    /// The route is prefixed with the base path that the app is served under, and in hash mode,
+    /// it is put in the fragment, see `service::href`.
+    ///
+    /// In debug builds, an error is logged when the route doesn't switch back into an item that
+    /// builds the same route, see `check_round_trip`.
+    fn to_href(&self) -> String
+    where
+        Self: Clone,
+    {
+        self.to_href_with_query(&[])
+    }
+
+    /// Builds the href that links to this item, with the `query` pairs merged into the query
+    /// section of its route.
+    ///
+    /// The pairs are percent-encoded and appended after any parameters that the route already
+    /// has, in front of the fragment.
+    fn to_href_with_query(&self, query: &[(&str, &str)]) -> String
+    where
+        Self: Clone,
+    {
+        let route = if cfg!(debug_assertions) {
+            self.check_round_trip().unwrap_or_else(|error| {
+                log::error!("{}", error);
+                error.route
+            })
+        } else {
+            Route::<()>::from(self.clone()).route
+        };
+        href_of_route(&merge_query(&route, query))
+    }
+
+    /// Builds an absolute URL that links to this item, for links that leave the app, like the
+    /// ones in emails or share links.
+    ///
+    /// `origin` is the scheme and host that the app is served from, like `https://example.com`.
+    fn to_url(&self, origin: &str) -> String
+    where
+        Self: Clone,
+    {
+        join_origin(origin, &self.to_href())
+    }
+
+    /// Checks that the route built from this item switches back into an item that builds the
+    /// same route, and returns the route.
+    ///
+    /// A mismatch means that `build_route_section` and `from_route_part` disagree for this
+    /// item, so links to it won't lead back to it.
+    fn check_round_trip(&self) -> Result<String, RoundTripError>
+    where
+        Self: Clone,
+    {
+        let route = Route::<()>::from(self.clone()).route;
+        let rebuilt =
+            Self::switch(Route::new_no_state(&route)).map(|item| Route::<()>::from(item).route);
+        if rebuilt.as_ref() == Some(&route) {
+            Ok(route)
+        } else {
+            Err(RoundTripError {
+                type_name: std::any::type_name::<Self>(),
+                route,
+                rebuilt,
+            })
+        }
+    }
+}
+
+/// Error returned by `Switch::check_round_trip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundTripError {
+    /// The name of the type that failed the check.
+    pub type_name: &'static str,
+    /// The route that was built from the item.
+    pub route: String,
+    /// The route built from the item that `route` switched into, if it switched into one.
+    pub rebuilt: Option<String>,
+}
+
+impl std::fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.rebuilt {
+            Some(rebuilt) => write!(
+                f,
+                "the route '{}' built from a {} switches into one that builds '{}'",
+                self.route, self.type_name, rebuilt
+            ),
+            None => write!(
+                f,
+                "the route '{}' built from a {} doesn't switch back into a {}",
+                self.route, self.type_name, self.type_name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RoundTripError {}
+
+/// Builds the href for the route, for the routing mode and base path that the app uses.
+fn href_of_route(route: &str) -> String {
+    #[cfg(feature = "service")]
+    {
+        crate::service::href(route)
+    }
+    #[cfg(not(feature = "service"))]
+    {
+        route.to_string()
+    }
+}
+
+/// Appends the percent-encoded `query` pairs to the query section of the route.
+fn merge_query(route: &str, query: &[(&str, &str)]) -> String {
+    if query.is_empty() {
+        return route.to_string();
+    }
+    let (rest, fragment) = match route.find('#') {
+        Some(index) => route.split_at(index),
+        None => (route, ""),
+    };
+    let mut merged = String::with_capacity(route.len());
+    merged.push_str(rest);
+    for (key, value) in query {
+        if !merged.contains('?') {
+            merged.push('?');
+        } else if !merged.ends_with(&['?', '&'][..]) {
+            merged.push('&');
+        }
+        encode_into(&mut merged, key, Encoding::Segment);
+        merged.push('=');
+        encode_into(&mut merged, value, Encoding::Segment);
+    }
+    merged.push_str(fragment);
+    merged
+}
+
+/// Joins an origin like `https://example.com/` and an href like `/posts`.
+fn join_origin(origin: &str, href: &str) -> String {
+    let origin = origin.trim_end_matches('/');
+    if href.starts_with('/') {
+        format!("{}{}", origin, href)
+    } else {
+        format!("{}/{}", origin, href)
+    }
 }
 
 /// Wrapper that requires that an implementor of Switch must start with a `/`.
@@ -91,10 +283,28 @@ impl<U: Switch> Switch for LeadingSlash<U> {
         }
     }
 
+    fn from_route_capture<STATE>(
+        part: String,
+        state: Option<STATE>,
+    ) -> (Option<Self>, Option<STATE>) {
+        if part.starts_with('/') {
+            let part = part[1..].to_string();
+            let (inner, state) = U::from_route_capture(part, state);
+            (inner.map(LeadingSlash), state)
+        } else {
+            (None, None)
+        }
+    }
+
     fn build_route_section<T>(self, route: &mut String) -> Option<T> {
         write!(route, "/").ok()?;
         self.0.build_route_section(route)
     }
+
+    fn build_route_capture<T>(self, route: &mut String, encoding: Encoding) -> Option<T> {
+        write!(route, "/").ok()?;
+        self.0.build_route_capture(route, encoding)
+    }
 }
 
 /// Successfully match even when the captured section can't be found.
@@ -113,6 +323,18 @@ impl<U: Switch> Switch for Permissive<U> {
         }
     }
 
+    fn from_route_capture<STATE>(
+        part: String,
+        state: Option<STATE>,
+    ) -> (Option<Self>, Option<STATE>) {
+        let (inner, inner_state) = U::from_route_capture(part, state);
+        if inner.is_some() {
+            (Some(Permissive(inner)), inner_state)
+        } else {
+            (Some(Permissive(None)), None)
+        }
+    }
+
     fn build_route_section<STATE>(self, route: &mut String) -> Option<STATE> {
         if let Some(inner) = self.0 {
             inner.build_route_section(route)
@@ -121,6 +343,14 @@ impl<U: Switch> Switch for Permissive<U> {
         }
     }
 
+    fn build_route_capture<STATE>(self, route: &mut String, encoding: Encoding) -> Option<STATE> {
+        if let Some(inner) = self.0 {
+            inner.build_route_capture(route, encoding)
+        } else {
+            None
+        }
+    }
+
     fn key_not_available() -> Option<Self> {
         Some(Permissive(None))
     }
@@ -133,24 +363,44 @@ impl<U: Switch> Switch for Permissive<U> {
 /// if its contents are entirely missing, or starts with a '/'.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct AllowMissing<U: std::fmt::Debug>(pub Option<U>);
-impl<U: Switch + std::fmt::Debug> Switch for AllowMissing<U> {
-    fn from_route_part<STATE>(part: String, state: Option<STATE>) -> (Option<Self>, Option<STATE>) {
-        let route = part.clone();
-        let (inner, inner_state) = U::from_route_part(part, state);
 
+impl<U: std::fmt::Debug> AllowMissing<U> {
+    /// Allows the inner value to be missing, if the part it was parsed from is.
+    fn from_inner<STATE>(
+        route: &str,
+        inner: Option<U>,
+        inner_state: Option<STATE>,
+    ) -> (Option<Self>, Option<STATE>) {
         if inner.is_some() {
             (Some(AllowMissing(inner)), inner_state)
-        } else if &route == ""
-            || (&route).starts_with('/')
-            || (&route).starts_with('?')
-            || (&route).starts_with('&')
-            || (&route).starts_with('#')
+        } else if route.is_empty()
+            || route.starts_with('/')
+            || route.starts_with('?')
+            || route.starts_with('&')
+            || route.starts_with('#')
         {
             (Some(AllowMissing(None)), inner_state)
         } else {
             (None, None)
         }
     }
+}
+
+impl<U: Switch + std::fmt::Debug> Switch for AllowMissing<U> {
+    fn from_route_part<STATE>(part: String, state: Option<STATE>) -> (Option<Self>, Option<STATE>) {
+        let route = part.clone();
+        let (inner, inner_state) = U::from_route_part(part, state);
+        AllowMissing::from_inner(&route, inner, inner_state)
+    }
+
+    fn from_route_capture<STATE>(
+        part: String,
+        state: Option<STATE>,
+    ) -> (Option<Self>, Option<STATE>) {
+        let route = part.clone();
+        let (inner, inner_state) = U::from_route_capture(part, state);
+        AllowMissing::from_inner(&route, inner, inner_state)
+    }
 
     fn build_route_section<STATE>(self, route: &mut String) -> Option<STATE> {
         if let AllowMissing(Some(inner)) = self {
@@ -159,6 +409,14 @@ impl<U: Switch + std::fmt::Debug> Switch for AllowMissing<U> {
             None
         }
     }
+
+    fn build_route_capture<STATE>(self, route: &mut String, encoding: Encoding) -> Option<STATE> {
+        if let AllowMissing(Some(inner)) = self {
+            inner.build_route_capture(route, encoding)
+        } else {
+            None
+        }
+    }
 }
 
 /// Builds a route from a switch.
@@ -180,16 +438,27 @@ impl<SW: Switch, STATE: Default> From<SW> for Route<STATE> {
     }
 }
 
-
+/// Captured sections are percent-decoded before they are parsed,
+/// and the value is percent-encoded when it is written for a capture.
+/// Whole routes are parsed and written as they are.
 impl<T: std::str::FromStr + std::fmt::Display> Switch for T {
     fn from_route_part<U>(part: String, state: Option<U>) -> (Option<Self>, Option<U>) {
         (::std::str::FromStr::from_str(&part).ok(), state)
     }
 
+    fn from_route_capture<U>(part: String, state: Option<U>) -> (Option<Self>, Option<U>) {
+        Self::from_route_part(decode(&part), state)
+    }
+
     fn build_route_section<U>(self, route: &mut String) -> Option<U> {
         write!(route, "{}", self).expect("Writing to string should never fail.");
         None
     }
+
+    fn build_route_capture<U>(self, route: &mut String, encoding: Encoding) -> Option<U> {
+        encode_into(route, &self.to_string(), encoding);
+        None
+    }
 }
 
 #[cfg(test)]
@@ -203,6 +472,24 @@ mod test {
         assert_eq!(route, "/-432".to_string());
     }
 
+    #[test]
+    fn string_route_is_left_as_it_is() {
+        let route: Route<()> = Route::from("/a/b c%20".to_string());
+        assert_eq!(route.route, "/a/b c%20");
+        assert_eq!(String::switch(route), Some("/a/b c%20".to_string()));
+    }
+
+    #[test]
+    fn string_capture_is_percent_decoded_and_encoded() {
+        let (s, _state) = String::from_route_capture::<()>("a%2Fb%20c".to_string(), None);
+        assert_eq!(s, Some("a/b c".to_string()));
+        let mut route = "/".to_string();
+        "a/b c"
+            .to_string()
+            .build_route_capture::<()>(&mut route, Encoding::Segment);
+        assert_eq!(route, "/a%2Fb%20c");
+    }
+
     #[test]
     fn can_get_string_from_empty_str() {
         let (s, _state) = String::from_route_part::<()>("".to_string(), Some(()));
@@ -233,4 +520,66 @@ mod test {
             Permissive::from_route_part("".to_string(), Some(()));
         assert_eq!(s, Some(Permissive(Some("".to_string()))))
     }
+
+    #[test]
+    fn query_is_merged_before_fragment() {
+        let query = [("q", "a b&c"), ("page", "2")];
+        assert_eq!(merge_query("/search", &query), "/search?q=a%20b%26c&page=2");
+        assert_eq!(
+            merge_query("/search?sort=asc#results", &query),
+            "/search?sort=asc&q=a%20b%26c&page=2#results"
+        );
+        assert_eq!(merge_query("/search?", &query[1..]), "/search?page=2");
+        assert_eq!(merge_query("/search#top", &[]), "/search#top");
+    }
+
+    #[test]
+    fn origin_is_joined_with_href() {
+        assert_eq!(
+            join_origin("https://example.com", "/posts"),
+            "https://example.com/posts"
+        );
+        assert_eq!(
+            join_origin("https://example.com/", "/posts"),
+            "https://example.com/posts"
+        );
+        assert_eq!(
+            join_origin("https://example.com", "posts"),
+            "https://example.com/posts"
+        );
+    }
+
+    #[test]
+    fn std_types_round_trip() {
+        assert_eq!(42usize.check_round_trip(), Ok("42".to_string()));
+        assert_eq!(
+            LeadingSlash("lorem".to_string()).check_round_trip(),
+            Ok("/lorem".to_string())
+        );
+    }
+
+    #[derive(Clone)]
+    struct Lossy;
+    impl Switch for Lossy {
+        fn from_route_part<STATE>(
+            part: String,
+            state: Option<STATE>,
+        ) -> (Option<Self>, Option<STATE>) {
+            (Some(Lossy).filter(|_| part == "/lossy"), state)
+        }
+
+        fn build_route_section<STATE>(self, route: &mut String) -> Option<STATE> {
+            route.push_str("/lossy/");
+            None
+        }
+    }
+
+    #[test]
+    fn disagreeing_build_fails_round_trip() {
+        let error = Lossy
+            .check_round_trip()
+            .expect_err("should not switch back");
+        assert_eq!(error.route, "/lossy/");
+        assert_eq!(error.rebuilt, None);
+    }
 }
@@ -0,0 +1,306 @@
+//! Compares the prefix tree dispatch of a derived `Switch` against trying every variant's matcher
+//! in turn, for an enum with many variants.
+//!
+//! Run with `cargo bench -p macro_test`.
+use std::time::{Duration, Instant};
+use yew_router::{
+    matcher::{MatcherSettings, RouteMatcher},
+    prelude::Route,
+    Switch,
+};
+
+const VARIANTS: usize = 120;
+const ITERATIONS: u32 = 20_000;
+
+#[derive(Debug, Switch, PartialEq, Clone)]
+pub enum AppRoute {
+    #[to = "/section0/item/{}"]
+    Section0(usize),
+    #[to = "/section1/item/{}"]
+    Section1(usize),
+    #[to = "/section2/item/{}"]
+    Section2(usize),
+    #[to = "/section3/item/{}"]
+    Section3(usize),
+    #[to = "/section4/item/{}"]
+    Section4(usize),
+    #[to = "/section5/item/{}"]
+    Section5(usize),
+    #[to = "/section6/item/{}"]
+    Section6(usize),
+    #[to = "/section7/item/{}"]
+    Section7(usize),
+    #[to = "/section8/item/{}"]
+    Section8(usize),
+    #[to = "/section9/item/{}"]
+    Section9(usize),
+    #[to = "/section10/item/{}"]
+    Section10(usize),
+    #[to = "/section11/item/{}"]
+    Section11(usize),
+    #[to = "/section12/item/{}"]
+    Section12(usize),
+    #[to = "/section13/item/{}"]
+    Section13(usize),
+    #[to = "/section14/item/{}"]
+    Section14(usize),
+    #[to = "/section15/item/{}"]
+    Section15(usize),
+    #[to = "/section16/item/{}"]
+    Section16(usize),
+    #[to = "/section17/item/{}"]
+    Section17(usize),
+    #[to = "/section18/item/{}"]
+    Section18(usize),
+    #[to = "/section19/item/{}"]
+    Section19(usize),
+    #[to = "/section20/item/{}"]
+    Section20(usize),
+    #[to = "/section21/item/{}"]
+    Section21(usize),
+    #[to = "/section22/item/{}"]
+    Section22(usize),
+    #[to = "/section23/item/{}"]
+    Section23(usize),
+    #[to = "/section24/item/{}"]
+    Section24(usize),
+    #[to = "/section25/item/{}"]
+    Section25(usize),
+    #[to = "/section26/item/{}"]
+    Section26(usize),
+    #[to = "/section27/item/{}"]
+    Section27(usize),
+    #[to = "/section28/item/{}"]
+    Section28(usize),
+    #[to = "/section29/item/{}"]
+    Section29(usize),
+    #[to = "/section30/item/{}"]
+    Section30(usize),
+    #[to = "/section31/item/{}"]
+    Section31(usize),
+    #[to = "/section32/item/{}"]
+    Section32(usize),
+    #[to = "/section33/item/{}"]
+    Section33(usize),
+    #[to = "/section34/item/{}"]
+    Section34(usize),
+    #[to = "/section35/item/{}"]
+    Section35(usize),
+    #[to = "/section36/item/{}"]
+    Section36(usize),
+    #[to = "/section37/item/{}"]
+    Section37(usize),
+    #[to = "/section38/item/{}"]
+    Section38(usize),
+    #[to = "/section39/item/{}"]
+    Section39(usize),
+    #[to = "/section40/item/{}"]
+    Section40(usize),
+    #[to = "/section41/item/{}"]
+    Section41(usize),
+    #[to = "/section42/item/{}"]
+    Section42(usize),
+    #[to = "/section43/item/{}"]
+    Section43(usize),
+    #[to = "/section44/item/{}"]
+    Section44(usize),
+    #[to = "/section45/item/{}"]
+    Section45(usize),
+    #[to = "/section46/item/{}"]
+    Section46(usize),
+    #[to = "/section47/item/{}"]
+    Section47(usize),
+    #[to = "/section48/item/{}"]
+    Section48(usize),
+    #[to = "/section49/item/{}"]
+    Section49(usize),
+    #[to = "/section50/item/{}"]
+    Section50(usize),
+    #[to = "/section51/item/{}"]
+    Section51(usize),
+    #[to = "/section52/item/{}"]
+    Section52(usize),
+    #[to = "/section53/item/{}"]
+    Section53(usize),
+    #[to = "/section54/item/{}"]
+    Section54(usize),
+    #[to = "/section55/item/{}"]
+    Section55(usize),
+    #[to = "/section56/item/{}"]
+    Section56(usize),
+    #[to = "/section57/item/{}"]
+    Section57(usize),
+    #[to = "/section58/item/{}"]
+    Section58(usize),
+    #[to = "/section59/item/{}"]
+    Section59(usize),
+    #[to = "/section60/item/{}"]
+    Section60(usize),
+    #[to = "/section61/item/{}"]
+    Section61(usize),
+    #[to = "/section62/item/{}"]
+    Section62(usize),
+    #[to = "/section63/item/{}"]
+    Section63(usize),
+    #[to = "/section64/item/{}"]
+    Section64(usize),
+    #[to = "/section65/item/{}"]
+    Section65(usize),
+    #[to = "/section66/item/{}"]
+    Section66(usize),
+    #[to = "/section67/item/{}"]
+    Section67(usize),
+    #[to = "/section68/item/{}"]
+    Section68(usize),
+    #[to = "/section69/item/{}"]
+    Section69(usize),
+    #[to = "/section70/item/{}"]
+    Section70(usize),
+    #[to = "/section71/item/{}"]
+    Section71(usize),
+    #[to = "/section72/item/{}"]
+    Section72(usize),
+    #[to = "/section73/item/{}"]
+    Section73(usize),
+    #[to = "/section74/item/{}"]
+    Section74(usize),
+    #[to = "/section75/item/{}"]
+    Section75(usize),
+    #[to = "/section76/item/{}"]
+    Section76(usize),
+    #[to = "/section77/item/{}"]
+    Section77(usize),
+    #[to = "/section78/item/{}"]
+    Section78(usize),
+    #[to = "/section79/item/{}"]
+    Section79(usize),
+    #[to = "/section80/item/{}"]
+    Section80(usize),
+    #[to = "/section81/item/{}"]
+    Section81(usize),
+    #[to = "/section82/item/{}"]
+    Section82(usize),
+    #[to = "/section83/item/{}"]
+    Section83(usize),
+    #[to = "/section84/item/{}"]
+    Section84(usize),
+    #[to = "/section85/item/{}"]
+    Section85(usize),
+    #[to = "/section86/item/{}"]
+    Section86(usize),
+    #[to = "/section87/item/{}"]
+    Section87(usize),
+    #[to = "/section88/item/{}"]
+    Section88(usize),
+    #[to = "/section89/item/{}"]
+    Section89(usize),
+    #[to = "/section90/item/{}"]
+    Section90(usize),
+    #[to = "/section91/item/{}"]
+    Section91(usize),
+    #[to = "/section92/item/{}"]
+    Section92(usize),
+    #[to = "/section93/item/{}"]
+    Section93(usize),
+    #[to = "/section94/item/{}"]
+    Section94(usize),
+    #[to = "/section95/item/{}"]
+    Section95(usize),
+    #[to = "/section96/item/{}"]
+    Section96(usize),
+    #[to = "/section97/item/{}"]
+    Section97(usize),
+    #[to = "/section98/item/{}"]
+    Section98(usize),
+    #[to = "/section99/item/{}"]
+    Section99(usize),
+    #[to = "/section100/item/{}"]
+    Section100(usize),
+    #[to = "/section101/item/{}"]
+    Section101(usize),
+    #[to = "/section102/item/{}"]
+    Section102(usize),
+    #[to = "/section103/item/{}"]
+    Section103(usize),
+    #[to = "/section104/item/{}"]
+    Section104(usize),
+    #[to = "/section105/item/{}"]
+    Section105(usize),
+    #[to = "/section106/item/{}"]
+    Section106(usize),
+    #[to = "/section107/item/{}"]
+    Section107(usize),
+    #[to = "/section108/item/{}"]
+    Section108(usize),
+    #[to = "/section109/item/{}"]
+    Section109(usize),
+    #[to = "/section110/item/{}"]
+    Section110(usize),
+    #[to = "/section111/item/{}"]
+    Section111(usize),
+    #[to = "/section112/item/{}"]
+    Section112(usize),
+    #[to = "/section113/item/{}"]
+    Section113(usize),
+    #[to = "/section114/item/{}"]
+    Section114(usize),
+    #[to = "/section115/item/{}"]
+    Section115(usize),
+    #[to = "/section116/item/{}"]
+    Section116(usize),
+    #[to = "/section117/item/{}"]
+    Section117(usize),
+    #[to = "/section118/item/{}"]
+    Section118(usize),
+    #[to = "/section119/item/{}"]
+    Section119(usize),
+}
+
+/// Tries every matcher in turn, like derived `Switch` implementations did before dispatching with
+/// a prefix tree.
+fn linear_switch(matchers: &[RouteMatcher], route: &str) -> Option<usize> {
+    matchers
+        .iter()
+        .position(|matcher| matcher.capture_route_into_multimap(route).is_ok())
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let settings = MatcherSettings::default().with_case_insensitive(true);
+    let matchers: Vec<RouteMatcher> = (0..VARIANTS)
+        .map(|i| {
+            RouteMatcher::new(&format!("/section{}/item/{{}}", i), settings).expect("should parse")
+        })
+        .collect();
+
+    let routes = [
+        "/section0/item/1",
+        "/section60/item/1",
+        "/section119/item/1",
+        "/missing",
+    ];
+    println!("{:<24}{:>16}{:>16}", "route", "prefix tree", "linear");
+    for route in routes.iter() {
+        let expected = AppRoute::switch(Route::new_no_state(route));
+        assert_eq!(
+            expected.is_some(),
+            linear_switch(&matchers, route).is_some(),
+            "both strategies should agree on {}",
+            route
+        );
+        let tree = time(|| {
+            AppRoute::switch(Route::new_no_state(route));
+        });
+        let linear = time(|| {
+            linear_switch(&matchers, route);
+        });
+        println!("{:<24}{:>16?}{:>16?}", route, tree, linear);
+    }
+}
@@ -162,16 +162,16 @@ mod tests {
     fn multiple_enum_variant_eager_matching() {
         #[derive(Debug, Switch, PartialEq, Clone)]
         pub enum Test {
-            #[to = "/variant"]
-            Variant1,
-            #[to = "/variant/stuff"]
-            Variant2,
+            #[to = "/{}/stuff"]
+            Variant1(String),
+            #[to = "/variant/{}"]
+            Variant2(String),
         }
         let route = Route::new_no_state("/variant/stuff");
         let switched = Test::switch(route).expect("should produce item");
         assert_eq!(
             switched,
-            Test::Variant1,
+            Test::Variant1("variant".to_string()),
             "The first variant should match first"
         )
     }
@@ -411,6 +411,1062 @@ mod tests {
         assert_eq!(switched, Test::Variant)
     }
 
+    mod constraints {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/post/{id:u32}"]
+            PostById { id: u64 },
+            #[to = r"/post/{date:\d{4}-\d{2}-\d{2}}"]
+            PostsByDate { date: String },
+            #[to = "/post/{slug:[a-z0-9-]+}"]
+            PostBySlug(String),
+            #[to = "/post/{other}"]
+            Other(String),
+        }
+
+        #[test]
+        fn type_constraint() {
+            let route = Route::new_no_state("/post/42");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::PostById { id: 42 })
+        }
+
+        #[test]
+        fn type_constraint_rejects_before_conversion() {
+            let route = Route::new_no_state("/post/5000000000");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::PostBySlug("5000000000".to_string()))
+        }
+
+        #[test]
+        fn regex_constraint() {
+            let route = Route::new_no_state("/post/2020-03-14");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(
+                switched,
+                Test::PostsByDate {
+                    date: "2020-03-14".to_string()
+                }
+            )
+        }
+
+        #[test]
+        fn unnamed_field_regex_constraint() {
+            let route = Route::new_no_state("/post/hello-world");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::PostBySlug("hello-world".to_string()))
+        }
+
+        #[test]
+        fn falls_through_unsatisfied_constraints() {
+            let route = Route::new_no_state("/post/Hello");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::Other("Hello".to_string()))
+        }
+
+        #[test]
+        fn query_constraint() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/search?page={page:usize}"]
+            pub struct Search {
+                page: usize,
+            }
+            let route = Route::new_no_state("/search?page=3");
+            assert_eq!(Search::switch(route), Some(Search { page: 3 }));
+            let route = Route::new_no_state("/search?page=three");
+            assert_eq!(Search::switch(route), None);
+        }
+    }
+
+    mod optional_sections {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/posts[/page/{n}]!"]
+            Posts { n: Option<usize> },
+            #[to = "/archive[/{year:u16}[/{month:u8}]]!"]
+            Archive(Option<u16>, Option<u8>),
+        }
+
+        #[test]
+        fn section_present() {
+            let route = Route::new_no_state("/posts/page/3");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::Posts { n: Some(3) })
+        }
+
+        #[test]
+        fn section_absent() {
+            let route = Route::new_no_state("/posts");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(switched, Test::Posts { n: None })
+        }
+
+        #[test]
+        fn unparsable_section_fails() {
+            let route = Route::new_no_state("/posts/page/three");
+            assert_eq!(Test::switch(route), None)
+        }
+
+        #[test]
+        fn nested_unnamed_sections() {
+            let route = Route::new_no_state("/archive/2020/3");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Archive(Some(2020), Some(3)))
+            );
+            let route = Route::new_no_state("/archive/2020");
+            assert_eq!(Test::switch(route), Some(Test::Archive(Some(2020), None)));
+            let route = Route::new_no_state("/archive");
+            assert_eq!(Test::switch(route), Some(Test::Archive(None, None)));
+        }
+
+        #[test]
+        fn build_omits_none() {
+            let route: Route = Test::Posts { n: None }.into();
+            assert_eq!(route.route, "/posts");
+            let route: Route = Test::Archive(Some(2020), None).into();
+            assert_eq!(route.route, "/archive/2020");
+            let route: Route = Test::Archive(None, Some(3)).into();
+            assert_eq!(route.route, "/archive");
+        }
+
+        #[test]
+        fn build_includes_some() {
+            let route: Route = Test::Posts { n: Some(3) }.into();
+            assert_eq!(route.route, "/posts/page/3");
+            let route: Route = Test::Archive(Some(2020), Some(3)).into();
+            assert_eq!(route.route, "/archive/2020/3");
+        }
+
+        #[test]
+        fn struct_with_section_between_literals() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/user/{id}[/{tab}]/settings"]
+            pub struct Settings {
+                id: u32,
+                tab: Option<String>,
+            }
+            let route = Route::new_no_state("/user/1/settings");
+            assert_eq!(Settings::switch(route), Some(Settings { id: 1, tab: None }));
+            let route = Route::new_no_state("/user/1/privacy/settings");
+            let settings = Settings {
+                id: 1,
+                tab: Some("privacy".to_string()),
+            };
+            assert_eq!(Settings::switch(route), Some(settings.clone()));
+            let route: Route = settings.into();
+            assert_eq!(route.route, "/user/1/privacy/settings");
+        }
+    }
+
+    mod query_parameters {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/search?q={query}&page={page}"]
+            Search { query: String, page: Option<usize> },
+            #[to = "/list?sort=new&offset={}!"]
+            List(Option<usize>),
+        }
+
+        #[test]
+        fn parameters_in_any_order() {
+            let route = Route::new_no_state("/search?page=2&q=lorem");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(
+                switched,
+                Test::Search {
+                    query: "lorem".to_string(),
+                    page: Some(2)
+                }
+            )
+        }
+
+        #[test]
+        fn unknown_parameters_are_ignored() {
+            let route = Route::new_no_state("/search?utm_source=mail&q=lorem");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(
+                switched,
+                Test::Search {
+                    query: "lorem".to_string(),
+                    page: None
+                }
+            )
+        }
+
+        #[test]
+        fn unknown_parameters_are_rejected_before_end_token() {
+            let route = Route::new_no_state("/list?offset=10&sort=new");
+            assert_eq!(Test::switch(route), Some(Test::List(Some(10))));
+            let route = Route::new_no_state("/list?sort=new");
+            assert_eq!(Test::switch(route), Some(Test::List(None)));
+            let route = Route::new_no_state("/list?sort=new&utm_source=mail");
+            assert_eq!(Test::switch(route), None);
+        }
+
+        #[test]
+        fn missing_required_parameter_fails() {
+            let route = Route::new_no_state("/search?page=2");
+            assert_eq!(Test::switch(route), None)
+        }
+
+        #[test]
+        fn build_omits_none() {
+            let route: Route = Test::Search {
+                query: "lorem".to_string(),
+                page: None,
+            }
+            .into();
+            assert_eq!(route.route, "/search?q=lorem");
+            let route: Route = Test::List(None).into();
+            assert_eq!(route.route, "/list?sort=new");
+        }
+
+        #[test]
+        fn build_includes_some() {
+            let route: Route = Test::Search {
+                query: "lorem".to_string(),
+                page: Some(2),
+            }
+            .into();
+            assert_eq!(route.route, "/search?q=lorem&page=2");
+            let route: Route = Test::List(Some(10)).into();
+            assert_eq!(route.route, "/list?sort=new&offset=10");
+        }
+    }
+
+    mod query_collections {
+        use super::*;
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/posts?tag={*:tags}&page={page}"]
+            Posts { tags: Vec<u32>, page: Option<usize> },
+            #[to = "/search?q={query}"]
+            Search {
+                query: String,
+                #[query]
+                params: BTreeMap<String, String>,
+            },
+        }
+
+        #[test]
+        fn repeated_parameter_is_collected() {
+            let route = Route::new_no_state("/posts?tag=1&page=2&tag=3");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Posts {
+                    tags: vec![1, 3],
+                    page: Some(2)
+                })
+            );
+            let route = Route::new_no_state("/posts");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Posts {
+                    tags: vec![],
+                    page: None
+                })
+            );
+        }
+
+        #[test]
+        fn unparsable_repeated_parameter_fails() {
+            let route = Route::new_no_state("/posts?tag=1&tag=two");
+            assert_eq!(Test::switch(route), None)
+        }
+
+        #[test]
+        fn unmatched_parameters_are_collected() {
+            let route = Route::new_no_state("/search?utm_source=mail&q=lorem&ref=a%26b");
+            let mut params = BTreeMap::new();
+            params.insert("utm_source".to_string(), "mail".to_string());
+            params.insert("ref".to_string(), "a&b".to_string());
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Search {
+                    query: "lorem".to_string(),
+                    params
+                })
+            );
+        }
+
+        #[test]
+        fn build_repeated_parameter() {
+            let route: Route = Test::Posts {
+                tags: vec![3, 1],
+                page: None,
+            }
+            .into();
+            assert_eq!(route.route, "/posts?tag=3&tag=1");
+        }
+
+        #[test]
+        fn build_unmatched_parameters_in_order() {
+            let mut params = BTreeMap::new();
+            params.insert("utm_source".to_string(), "mail".to_string());
+            params.insert("ref".to_string(), "a&b".to_string());
+            let route: Route = Test::Search {
+                query: "lorem".to_string(),
+                params,
+            }
+            .into();
+            assert_eq!(route.route, "/search?q=lorem&ref=a%26b&utm_source=mail");
+        }
+
+        #[test]
+        fn build_leaves_out_parameter_keys() {
+            let mut params = BTreeMap::new();
+            params.insert("Q".to_string(), "ipsum".to_string());
+            params.insert("q".to_string(), "ipsum".to_string());
+            params.insert("ref".to_string(), "mail".to_string());
+            let item = Test::Search {
+                query: "lorem".to_string(),
+                params,
+            };
+            let route: Route = item.into();
+            assert_eq!(route.route, "/search?q=lorem&ref=mail");
+            let mut params = BTreeMap::new();
+            params.insert("ref".to_string(), "mail".to_string());
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Search {
+                    query: "lorem".to_string(),
+                    params
+                })
+            );
+        }
+
+        #[test]
+        fn query_field_without_query_section() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/list!"]
+            pub struct List {
+                #[query]
+                params: HashMap<String, String>,
+            }
+            let route = Route::new_no_state("/list?b=2&a=1");
+            let list = List::switch(route).expect("should produce item");
+            assert_eq!(list.params.len(), 2);
+            let route: Route = list.into();
+            assert_eq!(route.route, "/list?a=1&b=2");
+        }
+    }
+
+    mod query_structs {
+        use super::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+        pub struct SearchParams {
+            q: String,
+            page: Option<u32>,
+            #[serde(default)]
+            tag: Vec<String>,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/search{?query}!"]
+            Search { query: SearchParams },
+            #[to = "/list?sort={sort}"]
+            List {
+                sort: String,
+                #[query]
+                filters: SearchParams,
+            },
+        }
+
+        #[test]
+        fn query_is_deserialized() {
+            let route = Route::new_no_state("/search?tag=a&q=lorem%20ipsum&tag=b");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Search {
+                    query: SearchParams {
+                        q: "lorem ipsum".to_string(),
+                        page: None,
+                        tag: vec!["a".to_string(), "b".to_string()],
+                    }
+                })
+            );
+        }
+
+        #[test]
+        fn undeserializable_query_fails() {
+            let route = Route::new_no_state("/search?page=2");
+            assert_eq!(Test::switch(route), None);
+            let route = Route::new_no_state("/search?q=lorem&page=two");
+            assert_eq!(Test::switch(route), None);
+        }
+
+        #[test]
+        fn query_attribute_collects_remaining_parameters() {
+            let route = Route::new_no_state("/list?q=lorem&sort=new&page=2");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::List {
+                    sort: "new".to_string(),
+                    filters: SearchParams {
+                        q: "lorem".to_string(),
+                        page: Some(2),
+                        tag: vec![],
+                    }
+                })
+            );
+        }
+
+        #[test]
+        fn build_serializes_query() {
+            let route: Route = Test::Search {
+                query: SearchParams {
+                    q: "a&b".to_string(),
+                    page: Some(2),
+                    tag: vec!["x".to_string(), "y".to_string()],
+                },
+            }
+            .into();
+            assert_eq!(route.route, "/search?page=2&q=a%26b&tag=x&tag=y");
+            let route: Route = Test::List {
+                sort: "new".to_string(),
+                filters: SearchParams {
+                    q: "lorem".to_string(),
+                    page: None,
+                    tag: vec![],
+                },
+            }
+            .into();
+            assert_eq!(route.route, "/list?sort=new&q=lorem");
+        }
+    }
+
+    mod static_matchers {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/a/{id}"]
+            A(usize),
+            #[to = "/b[/{page}]"]
+            B { page: Option<usize> },
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[to = "/c/{name}"]
+        pub struct C {
+            name: String,
+        }
+
+        #[test]
+        fn repeated_switches_match_independently() {
+            for id in 0..3 {
+                let route = Route::new_no_state(format!("/a/{}", id));
+                assert_eq!(Test::switch(route), Some(Test::A(id)));
+            }
+            assert_eq!(
+                Test::switch(Route::new_no_state("/b")),
+                Some(Test::B { page: None })
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/b/2")),
+                Some(Test::B { page: Some(2) })
+            );
+            assert_eq!(Test::switch(Route::new_no_state("/c/lorem")), None);
+        }
+
+        #[test]
+        fn matchers_are_available_on_every_thread() {
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    std::thread::spawn(|| {
+                        let struct_route = Route::new_no_state("/c/lorem");
+                        let enum_route = Route::new_no_state("/a/1");
+                        (C::switch(struct_route), Test::switch(enum_route))
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let (c, test) = handle.join().expect("thread should not panic");
+                assert_eq!(
+                    c,
+                    Some(C {
+                        name: "lorem".to_string()
+                    })
+                );
+                assert_eq!(test, Some(Test::A(1)));
+            }
+        }
+    }
+
+    mod prefix_dispatch {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/users/new"]
+            NewUser,
+            #[to = "/{section}/new"]
+            NewItem { section: String },
+            #[to = "/users/{id}"]
+            User { id: String },
+            #[to = "/posts[/{page}]"]
+            Posts { page: Option<usize> },
+            #[to = "/{}"]
+            Fallback(String),
+        }
+
+        #[test]
+        fn first_matching_variant_wins() {
+            assert_eq!(
+                Test::switch(Route::new_no_state("/users/new")),
+                Some(Test::NewUser)
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/posts/new")),
+                Some(Test::NewItem {
+                    section: "posts".to_string()
+                })
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/users/1")),
+                Some(Test::User {
+                    id: "1".to_string()
+                })
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/posts")),
+                Some(Test::Posts { page: None })
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/post")),
+                Some(Test::Fallback("post".to_string()))
+            );
+        }
+
+        #[test]
+        fn literals_ignore_case() {
+            assert_eq!(
+                Test::switch(Route::new_no_state("/USERS/New")),
+                Some(Test::NewUser)
+            );
+        }
+    }
+
+    mod switch_settings {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(case_sensitive)]
+        pub enum Test {
+            #[to = "/Sensitive!"]
+            Sensitive,
+            #[to = "/insensitive!"]
+            #[switch(case_insensitive)]
+            Insensitive,
+            #[to = "/ignore!"]
+            #[switch(trailing_slash = "ignore")]
+            Ignore,
+            #[to = "/require!"]
+            #[switch(trailing_slash = "require")]
+            Require,
+            #[to = "/redirect/!"]
+            #[switch(trailing_slash = "redirect")]
+            Redirect,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[to = "/users/{id}!"]
+        #[switch(trailing_slash = "ignore")]
+        pub struct User {
+            id: usize,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum AfterCapture {
+            #[to = "/users/{id}/Edit!"]
+            EditUser { id: usize },
+            #[to = "/files/{stem}.TXT!"]
+            TextFile { stem: String },
+            #[to = "/posts/{id}/Edit!"]
+            #[switch(case_sensitive)]
+            EditPost { id: usize },
+        }
+
+        #[test]
+        fn case_sensitivity() {
+            assert_eq!(
+                Test::switch(Route::new_no_state("/Sensitive")),
+                Some(Test::Sensitive)
+            );
+            assert_eq!(Test::switch(Route::new_no_state("/sensitive")), None);
+            assert_eq!(
+                Test::switch(Route::new_no_state("/INSENSITIVE")),
+                Some(Test::Insensitive)
+            );
+        }
+
+        #[test]
+        fn case_sensitivity_of_literals_after_captures() {
+            assert_eq!(
+                AfterCapture::switch(Route::new_no_state("/users/1/edit")),
+                Some(AfterCapture::EditUser { id: 1 })
+            );
+            assert_eq!(
+                AfterCapture::switch(Route::new_no_state("/files/a.txt")),
+                Some(AfterCapture::TextFile {
+                    stem: "a".to_string()
+                })
+            );
+            assert_eq!(
+                AfterCapture::switch(Route::new_no_state("/posts/1/Edit")),
+                Some(AfterCapture::EditPost { id: 1 })
+            );
+            assert_eq!(
+                AfterCapture::switch(Route::new_no_state("/posts/1/edit")),
+                None
+            );
+        }
+
+        #[test]
+        fn trailing_slash() {
+            assert_eq!(
+                Test::switch(Route::new_no_state("/ignore/")),
+                Some(Test::Ignore)
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/ignore")),
+                Some(Test::Ignore)
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/require/")),
+                Some(Test::Require)
+            );
+            assert_eq!(Test::switch(Route::new_no_state("/require")), None);
+            assert_eq!(
+                User::switch(Route::new_no_state("/users/1/")),
+                Some(User { id: 1 })
+            );
+        }
+
+        #[test]
+        fn redirected_trailing_slash() {
+            let switched =
+                Test::switch(Route::new_no_state("/redirect")).expect("should produce item");
+            assert_eq!(switched, Test::Redirect);
+            assert!(switched.redirect_trailing_slash());
+            assert!(!Test::Ignore.redirect_trailing_slash());
+            let route: Route = switched.into();
+            assert_eq!(route.route, "/redirect/");
+        }
+    }
+
+    mod explain {
+        use super::*;
+        use yew_router::matcher::explain::MismatchReason;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/users/{id}!"]
+            User { id: u32 },
+            #[to = "/posts/{}/{}"]
+            Post(String, u32),
+            #[to = "/about!"]
+            About,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[to = "/search?q={query}"]
+        pub struct Search {
+            query: String,
+        }
+
+        #[test]
+        fn reports_every_variant() {
+            let reports = Test::explain_route("/about");
+            let variants: Vec<_> = reports.iter().map(|report| report.variant).collect();
+            assert_eq!(variants, vec!["User", "Post", "About"]);
+            assert!(!reports[0].report.is_match());
+            assert_eq!(
+                reports[0].report.mismatch.as_ref().map(|m| m.offset),
+                Some(1)
+            );
+            assert!(reports[2].report.is_match());
+        }
+
+        #[test]
+        fn rejected_conversion() {
+            let reports = Test::explain_route("/users/lorem");
+            let mismatch = reports[0]
+                .report
+                .mismatch
+                .as_ref()
+                .expect("should not match");
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::Conversion {
+                    field: "id".to_string(),
+                    value: Some("lorem".to_string())
+                }
+            );
+
+            let reports = Test::explain_route("/posts/lorem/ipsum");
+            let mismatch = reports[1]
+                .report
+                .mismatch
+                .as_ref()
+                .expect("should not match");
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::Conversion {
+                    field: "1".to_string(),
+                    value: Some("ipsum".to_string())
+                }
+            );
+        }
+
+        #[test]
+        fn leftover_input_at_end() {
+            let reports = Test::explain_route("/users/1/posts");
+            let mismatch = reports[0]
+                .report
+                .mismatch
+                .as_ref()
+                .expect("should not match");
+            assert_eq!(
+                mismatch.reason,
+                MismatchReason::LeftoverInput {
+                    remaining: "/posts".to_string()
+                }
+            );
+        }
+
+        #[test]
+        fn struct_report() {
+            let reports = Search::explain_route("/search?q=lorem");
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].variant, "Search");
+            assert!(reports[0].report.is_match());
+            let reports = Search::explain_route("/search");
+            assert_eq!(
+                reports[0].report.mismatch.as_ref().map(|m| &m.reason),
+                Some(&MismatchReason::QueryParameter {
+                    key: "q".to_string()
+                })
+            );
+        }
+    }
+
+    mod captures_within_section {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/files/{stem}.{ext}"]
+            File { stem: String, ext: String },
+            #[to = "/v{}.{}"]
+            Version(u32, u32),
+        }
+
+        #[test]
+        fn extension_is_after_last_dot() {
+            let route = Route::new_no_state("/files/archive.tar.gz");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::File {
+                    stem: "archive.tar".to_string(),
+                    ext: "gz".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn unnamed_captures_within_section() {
+            let route = Route::new_no_state("/v1.2");
+            assert_eq!(Test::switch(route), Some(Test::Version(1, 2)));
+        }
+
+        #[test]
+        fn round_trip() {
+            let file = Test::File {
+                stem: "archive.tar".to_string(),
+                ext: "gz".to_string(),
+            };
+            let route: Route = file.clone().into();
+            assert_eq!(route.route, "/files/archive.tar.gz");
+            assert_eq!(Test::switch(route), Some(file));
+        }
+    }
+
+    mod document_tree {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/docs/{*:path}/edit!"]
+            Edit { path: String },
+            #[to = "/docs/{*:path}/history/{revision}!"]
+            History { path: String, revision: u32 },
+            #[to = "/docs/{*:path}"]
+            View { path: String },
+        }
+
+        #[test]
+        fn document_named_like_literal() {
+            let route = Route::new_no_state("/docs/guides/edit/intro/edit");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::Edit {
+                    path: "guides/edit/intro".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn history_of_nested_document() {
+            let route = Route::new_no_state("/docs/history/a/history/3");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::History {
+                    path: "history/a".to_string(),
+                    revision: 3
+                })
+            );
+        }
+
+        #[test]
+        fn view_document() {
+            let route = Route::new_no_state("/docs/guides/edit/intro");
+            assert_eq!(
+                Test::switch(route),
+                Some(Test::View {
+                    path: "guides/edit/intro".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn round_trip() {
+            let edit = Test::Edit {
+                path: "edit/edit".to_string(),
+            };
+            let route: Route = edit.clone().into();
+            assert_eq!(route.route, "/docs/edit/edit/edit");
+            assert_eq!(Test::switch(route), Some(edit));
+        }
+    }
+
+    mod round_trip {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/user/{id}"]
+            User { id: String },
+            #[to = "/search?q={query}"]
+            Search { query: String },
+        }
+
+        #[test]
+        fn derived_routes_round_trip() {
+            let user = Test::User {
+                id: "a b/c".to_string(),
+            };
+            assert_eq!(user.check_round_trip(), Ok("/user/a%20b%2Fc".to_string()));
+            let search = Test::Search {
+                query: "lorem".to_string(),
+            };
+            assert_eq!(search.check_round_trip(), Ok("/search?q=lorem".to_string()));
+        }
+
+        #[test]
+        fn empty_capture_does_not_round_trip() {
+            let user = Test::User { id: String::new() };
+            let error = user.check_round_trip().expect_err("should not match");
+            assert_eq!(error.route, "/user/");
+            assert_eq!(error.rebuilt, None);
+        }
+    }
+
+    mod escaped_literals {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/wow!!/{id}!"]
+            Wow(usize),
+            #[to = "{{braces}}/{name}"]
+            Braces { name: String },
+            #[to = "/a&&b==c"]
+            Symbols,
+        }
+
+        #[test]
+        fn escaped_literals_match() {
+            assert_eq!(
+                Test::switch(Route::new_no_state("/wow!/3")),
+                Some(Test::Wow(3))
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("{braces}/lorem")),
+                Some(Test::Braces {
+                    name: "lorem".to_string()
+                })
+            );
+            assert_eq!(
+                Test::switch(Route::new_no_state("/a&b=c")),
+                Some(Test::Symbols)
+            );
+            assert_eq!(Test::switch(Route::new_no_state("/wow/3")), None);
+        }
+
+        #[test]
+        fn escaped_literals_are_written_once() {
+            let route: Route = Test::Wow(3).into();
+            assert_eq!(route.route, "/wow!/3");
+            let route: Route = Test::Braces {
+                name: "lorem".to_string(),
+            }
+            .into();
+            assert_eq!(route.route, "{braces}/lorem");
+            let route: Route = Test::Symbols.into();
+            assert_eq!(route.route, "/a&b=c");
+        }
+    }
+
+    mod percent_encoding {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/users/{name}"]
+            User { name: String },
+            #[to = "/files/{*:path}"]
+            File { path: String },
+            #[to = "/search?q={query}"]
+            Search { query: String },
+        }
+
+        #[test]
+        fn capture_is_decoded() {
+            let route = Route::new_no_state("/users/J%C3%BCrgen");
+            let switched = Test::switch(route).expect("should produce item");
+            assert_eq!(
+                switched,
+                Test::User {
+                    name: "Jürgen".to_string()
+                }
+            )
+        }
+
+        #[test]
+        fn single_section_escapes_slash() {
+            let route: Route = Test::User {
+                name: "a/b c".to_string(),
+            }
+            .into();
+            assert_eq!(route.route, "/users/a%2Fb%20c");
+        }
+
+        #[test]
+        fn many_sections_keep_slash() {
+            let route: Route = Test::File {
+                path: "a/b c".to_string(),
+            }
+            .into();
+            assert_eq!(route.route, "/files/a/b%20c");
+        }
+
+        #[test]
+        fn query_escapes_separators() {
+            let route: Route = Test::Search {
+                query: "a&b=c#d".to_string(),
+            }
+            .into();
+            assert_eq!(route.route, "/search?q=a%26b%3Dc%23d");
+        }
+
+        #[test]
+        fn round_trips() {
+            let strings = ["Jürgen", "a/b", "?x=1&y=2#z", "100% {weird}", " *!"];
+            for s in strings.iter() {
+                let items = [
+                    Test::User {
+                        name: s.to_string(),
+                    },
+                    Test::File {
+                        path: s.to_string(),
+                    },
+                    Test::Search {
+                        query: s.to_string(),
+                    },
+                ];
+                for item in items.iter() {
+                    let route: Route = item.clone().into();
+                    assert_eq!(Test::switch(route).as_ref(), Some(item));
+                }
+            }
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[to = "/{id}/{name}"]
+        pub struct Inner {
+            id: u32,
+            name: String,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Outer {
+            #[to = "/a{*:inner}"]
+            Many { inner: Inner },
+            #[to = "/b"]
+            #[rest]
+            Rest(Inner),
+            #[to = "{*:path}#{route}"]
+            Fragment { path: String, route: Inner },
+        }
+
+        fn inner(name: &str) -> Inner {
+            Inner {
+                id: 5,
+                name: name.to_string(),
+            }
+        }
+
+        #[test]
+        fn nested_switch_is_not_escaped_again() {
+            let route: Route = Outer::Many {
+                inner: inner("x y"),
+            }
+            .into();
+            assert_eq!(route.route, "/a/5/x%20y");
+            let route: Route = Outer::Rest(inner("x/y")).into();
+            assert_eq!(route.route, "/b/5/x%2Fy");
+            let route: Route = Outer::Fragment {
+                path: "/p q".to_string(),
+                route: inner("x"),
+            }
+            .into();
+            assert_eq!(route.route, "/p%20q#/5/x");
+        }
+
+        #[test]
+        fn nested_switch_round_trips() {
+            for name in ["x", "x y", "a/b", "100%", "?x=1&y=2#z"].iter() {
+                let items = [Outer::Many { inner: inner(name) }, Outer::Rest(inner(name))];
+                for item in items.iter() {
+                    assert!(item.check_round_trip().is_ok(), "{:?}", item);
+                }
+            }
+        }
+    }
+
     mod fragment_routing_tests {
         use super::*;
 